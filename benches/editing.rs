@@ -0,0 +1,150 @@
+// Édition dans de gros blocs : frappe, effacement, déplacement par mot et collage
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use ndown::editor::model::block::{Block, BlockType};
+use ndown::editor::model::document::Document;
+
+// Paragraphe d'environ 100 000 caractères, accents et emoji compris
+fn large_paragraph() -> Document {
+    let text = "Le cœur déjà-vu de l'été 👍🏽, rien à signaler. ".repeat(2_000);
+    Document::from_blocks(vec![Block::new(1, BlockType::Paragraph, &text)])
+}
+
+fn large_code_block() -> Document {
+    let text = "fn main() {\n    let total: u64 = (0..10).sum();\n}\n".repeat(2_000);
+    Document::from_blocks(vec![Block::new(1, BlockType::CodeBlock, &text)])
+}
+
+fn typing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("typing");
+    for (name, make) in [
+        ("paragraph", large_paragraph as fn() -> Document),
+        ("code_block", large_code_block),
+    ] {
+        group.bench_function(format!("{name}/middle"), |b| {
+            b.iter_batched_ref(
+                make,
+                |doc| {
+                    let mut pos = doc.blocks[0].text_len() / 2;
+                    for c in ["a", "é", "👍"] {
+                        pos += doc.insert_text_at(0, pos, black_box(c));
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function(format!("{name}/end"), |b| {
+            b.iter_batched_ref(
+                make,
+                |doc| {
+                    let mut pos = doc.blocks[0].text_len();
+                    for c in ["a", "é", "👍"] {
+                        pos += doc.insert_text_at(0, pos, black_box(c));
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn deletion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deletion");
+    group.bench_function("backspace_middle", |b| {
+        b.iter_batched_ref(
+            large_paragraph,
+            |doc| {
+                let mut pos = doc.blocks[0].text_len() / 2;
+                for _ in 0..10 {
+                    pos = doc.blocks[0].prev_grapheme(pos);
+                    doc.remove_grapheme_at(0, pos);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("delete_word_range", |b| {
+        b.iter_batched_ref(
+            large_paragraph,
+            |doc| {
+                let end = doc.blocks[0].text_len() / 2;
+                let start = doc.blocks[0].prev_word_start(end);
+                doc.delete_range((0, start), (0, end));
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+fn word_navigation(c: &mut Criterion) {
+    let doc = large_paragraph();
+    let block = &doc.blocks[0];
+    let middle = block.text_len() / 2;
+    let mut group = c.benchmark_group("word_navigation");
+    group.bench_function("next_word_end", |b| {
+        b.iter(|| {
+            let mut pos = middle;
+            for _ in 0..20 {
+                pos = block.next_word_end(black_box(pos));
+            }
+            pos
+        })
+    });
+    group.bench_function("prev_word_start", |b| {
+        b.iter(|| {
+            let mut pos = middle;
+            for _ in 0..20 {
+                pos = block.prev_word_start(black_box(pos));
+            }
+            pos
+        })
+    });
+    group.bench_function("grapheme_left_right", |b| {
+        b.iter(|| {
+            let mut pos = middle;
+            for _ in 0..20 {
+                pos = block.next_grapheme(black_box(pos));
+            }
+            for _ in 0..20 {
+                pos = block.prev_grapheme(black_box(pos));
+            }
+            pos
+        })
+    });
+    group.finish();
+}
+
+fn paste(c: &mut Criterion) {
+    let lines = "Une ligne collée, avec des accents : à, é, ç.\n".repeat(10_000);
+    let mut group = c.benchmark_group("paste");
+    group.bench_function("10k_lines", |b| {
+        b.iter_batched_ref(
+            large_paragraph,
+            |doc| {
+                let middle = doc.blocks[0].text_len() / 2;
+                doc.insert_lines(0, middle, black_box(&lines))
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("delete_10k_blocks", |b| {
+        b.iter_batched_ref(
+            || {
+                let mut doc = large_paragraph();
+                let middle = doc.blocks[0].text_len() / 2;
+                doc.insert_lines(0, middle, &lines);
+                doc
+            },
+            |doc| {
+                let last = doc.blocks.len() - 1;
+                doc.delete_range((0, 10), (last, 10))
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, typing, deletion, word_navigation, paste);
+criterion_main!(benches);
@@ -1,8 +1,16 @@
 use makepad_widgets::*;
+use once_cell::sync::OnceCell;
 
 // Imports nécessaires pour les actions et extensions de widgets
+use crate::conflict_banner::*;
 use crate::editor::*;
 use crate::file_explorer::*;
+use crate::link_dialog::*;
+use crate::panel::*;
+use crate::quick_open::*;
+use crate::recovery_prompt::*;
+use crate::save_prompt::*;
+use crate::top_bar::*;
 
 live_design! {
     use link::theme::*;
@@ -10,9 +18,14 @@ live_design! {
     use crate::theme::*;
 
     use crate::top_bar::*;
+    use crate::conflict_banner::*;
     use crate::file_explorer::*;
     use crate::panel::*;
     use crate::editor::*;
+    use crate::link_dialog::*;
+    use crate::save_prompt::*;
+    use crate::recovery_prompt::*;
+    use crate::quick_open::*;
 
     App = {{App}} {
         ui: <Window> {
@@ -30,17 +43,80 @@ live_design! {
                     flow: Down
 
                     top_bar = <TopBar> {}
+                    conflict_banner = <ConflictBanner> {}
                     editor = <EditorArea> {}
                 }
 
                 right_sidebar = <OutlinePanel> {}
             }
+
+            link_modal = <Modal> {
+                content: {
+                    link_dialog = <LinkDialog> {}
+                }
+            }
+
+            save_modal = <Modal> {
+                content: {
+                    save_prompt = <SavePrompt> {}
+                }
+            }
+
+            recovery_modal = <Modal> {
+                content: {
+                    recovery_prompt = <RecoveryPrompt> {}
+                }
+            }
+
+            quick_open_modal = <Modal> {
+                content: {
+                    quick_open = <QuickOpen> {}
+                }
+            }
         }
     }
 }
 
 app_main!(App);
 
+/// Document opened when the window starts, chosen from the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StartupDocument {
+    File(String),
+    /// `--new`, or a folder given without a file.
+    Empty,
+}
+
+impl Default for StartupDocument {
+    fn default() -> Self {
+        StartupDocument::File("story.md".to_string())
+    }
+}
+
+/// Options given on the command line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StartupOptions {
+    pub document: StartupDocument,
+    /// Save the open file a few seconds after the last edit.
+    pub autosave: bool,
+    /// Previous versions kept in `.ndown/backups` (0 = none).
+    pub backups: usize,
+}
+
+static STARTUP_OPTIONS: OnceCell<StartupOptions> = OnceCell::new();
+
+/// Must be called before `app_main`.
+pub fn set_startup_options(options: StartupOptions) {
+    let _ = STARTUP_OPTIONS.set(options);
+}
+
+// Action mise en attente par la question "Enregistrer les modifications ?"
+#[derive(Clone, Debug)]
+enum PendingSwitch {
+    CloseTab(usize),
+    Quit,
+}
+
 #[derive(Live, LiveHook)]
 pub struct App {
     #[live]
@@ -49,6 +125,54 @@ pub struct App {
     left_visible: bool,
     #[rust(true)]
     right_visible: bool,
+    #[rust]
+    pending: Option<PendingSwitch>,
+    // "Enregistrer" a été choisi : `pending` attend la fin de l'enregistrement
+    #[rust]
+    save_before_switch: bool,
+    #[rust]
+    quitting: bool,
+}
+
+impl App {
+    // Demande s'il faut enregistrer avant de fermer un onglet ou de quitter ; pour
+    // quitter, chaque onglet modifié est affiché à son tour
+    fn request_switch(&mut self, cx: &mut Cx, switch: PendingSwitch) {
+        let editor = self.ui.editor_area(ids!(body.center.editor));
+        let tabs = editor.tabs();
+        let modified_tab = match switch {
+            PendingSwitch::CloseTab(idx) => tabs.get(idx).filter(|tab| tab.modified).map(|_| idx),
+            PendingSwitch::Quit => tabs.iter().position(|tab| tab.modified),
+        };
+        let Some(tab) = modified_tab else {
+            self.run_switch(cx, switch);
+            return;
+        };
+        editor.select_tab(cx, tab);
+        let name = editor
+            .current_file()
+            .unwrap_or_else(|| "Sans titre".to_string());
+        self.ui
+            .save_prompt(ids!(save_modal.save_prompt))
+            .set_file(cx, &name);
+        self.pending = Some(switch);
+        self.save_before_switch = false;
+        self.ui.modal(ids!(save_modal)).open(cx);
+    }
+
+    fn run_switch(&mut self, cx: &mut Cx, switch: PendingSwitch) {
+        match switch {
+            PendingSwitch::CloseTab(idx) => {
+                self.ui
+                    .editor_area(ids!(body.center.editor))
+                    .close_tab(cx, idx);
+            }
+            PendingSwitch::Quit => {
+                self.quitting = true;
+                cx.quit();
+            }
+        }
+    }
 }
 
 impl LiveRegister for App {
@@ -126,26 +250,233 @@ impl MatchEvent for App {
         }
 
         // --- GESTION FICHIERS ---
-        if let Some(file_explorer) = self.ui.file_explorer(ids!(body.left_sidebar)).borrow() {
-            if let Some(path) = file_explorer.handle_file_actions(cx, actions) {
-                // Mettre à jour le titre dans la TopBar
+        let opened = self
+            .ui
+            .file_explorer(ids!(body.left_sidebar))
+            .borrow_mut()
+            .and_then(|mut file_explorer| file_explorer.handle_file_actions(cx, actions));
+        if let Some(path) = opened {
+            // Ouvert dans un nouvel onglet : rien n'est perdu
+            let editor = self.ui.editor_area(ids!(body.center.editor));
+            editor.load_file(cx, path);
+        }
+
+        // --- ONGLETS ---
+        let tab_request = self
+            .ui
+            .top_bar(ids!(body.center.top_bar))
+            .borrow()
+            .and_then(|top_bar| top_bar.handle_tab_actions(actions));
+        match tab_request {
+            Some(TabRequest::Select(idx)) => {
+                let editor = self.ui.editor_area(ids!(body.center.editor));
+                editor.select_tab(cx, idx);
+                let editor_view = self.ui.view(ids!(body.center.editor));
+                cx.set_key_focus(editor_view.area());
+            }
+            Some(TabRequest::Close(idx)) => self.request_switch(cx, PendingSwitch::CloseTab(idx)),
+            None => {}
+        }
+
+        // --- STRUCTURE ---
+        let outline_click = self
+            .ui
+            .outline_panel(ids!(body.right_sidebar))
+            .borrow()
+            .and_then(|panel| panel.handle_outline_actions(actions));
+        if let Some(block_idx) = outline_click {
+            let editor = self.ui.editor_area(ids!(body.center.editor));
+            editor.scroll_to_block(cx, block_idx);
+            let editor_view = self.ui.view(ids!(body.center.editor));
+            cx.set_key_focus(editor_view.area());
+        }
+
+        // Gestion des actions de l'éditeur
+        for action in actions {
+            if let Some(FileExplorerAction::FileMoved { from, to }) =
+                action.as_widget_action().cast()
+            {
+                let editor = self.ui.editor_area(ids!(body.center.editor));
+                if let Some(path) = editor.file_moved(&from, &to) {
+                    self.ui
+                        .top_bar(ids!(body.center.top_bar))
+                        .set_title(cx, &path);
+                }
+            }
+            // Envoyé depuis le dessin de l'éditeur (Cx::post_action)
+            if let EditorAction::OutlineChanged { entries, current } = action.cast() {
+                let panel = self.ui.outline_panel(ids!(body.right_sidebar));
+                if let Some(mut panel) = panel.borrow_mut() {
+                    panel.set_outline(cx, entries, current);
+                }
+            }
+            // Titre, onglets et bandeau de conflit suivent l'onglet affiché
+            if let EditorAction::TabsChanged { tabs, active } = action.cast() {
+                let top_bar = self.ui.top_bar(ids!(body.center.top_bar));
+                let banner = self.ui.conflict_banner(ids!(body.center.conflict_banner));
+                if let Some(tab) = tabs.get(active) {
+                    top_bar.set_title(cx, tab.path.as_deref().unwrap_or("Sans titre"));
+                    if let Some(path) = &tab.path {
+                        self.ui
+                            .quick_open(ids!(quick_open_modal.quick_open))
+                            .add_recent(path);
+                    }
+                    if tab.conflict {
+                        banner.show(cx, &tab.title);
+                    } else {
+                        banner.hide(cx);
+                    }
+                }
+                top_bar.set_tabs(cx, tabs, active);
+            }
+            if let Some(EditorAction::ExternalConflict(path)) = action.as_widget_action().cast() {
+                self.ui
+                    .conflict_banner(ids!(body.center.conflict_banner))
+                    .show(cx, &path);
+            }
+            if let EditorAction::ModifiedChanged(modified) = action.cast() {
+                self.ui
+                    .top_bar(ids!(body.center.top_bar))
+                    .set_modified(cx, modified);
+            }
+            if let Some(EditorAction::FileSaved(_)) = action.as_widget_action().cast() {
+                if std::mem::take(&mut self.save_before_switch) {
+                    if let Some(switch) = self.pending.take() {
+                        // Pour quitter, passe à l'onglet modifié suivant
+                        self.request_switch(cx, switch);
+                    }
+                }
+            }
+            if let Some(EditorAction::RecoveryAvailable(path)) = action.as_widget_action().cast() {
+                self.ui
+                    .recovery_prompt(ids!(recovery_modal.recovery_prompt))
+                    .set_file(cx, &path);
+                self.ui.modal(ids!(recovery_modal)).open(cx);
+            }
+            if let EditorAction::SaveFailed(_) = action.cast() {
+                // On reste sur le document non enregistré
+                self.save_before_switch = false;
+                self.pending = None;
+            }
+            if let Some(EditorAction::EditLink { text, url }) = action.as_widget_action().cast() {
                 self.ui
-                    .label(ids!(body.center.top_bar.title))
-                    .set_text(cx, &path);
+                    .link_dialog(ids!(link_modal.link_dialog))
+                    .set_link(cx, &text, &url);
+                self.ui.modal(ids!(link_modal)).open(cx);
+            }
+        }
+
+        // --- DIALOGUE DE LIEN (Ctrl+K) ---
+        let link_result = self
+            .ui
+            .link_dialog(ids!(link_modal.link_dialog))
+            .borrow()
+            .and_then(|dialog| dialog.handle_dialog_actions(actions));
+        let dismissed = self.ui.modal(ids!(link_modal)).dismissed(actions);
+
+        if link_result.is_some() || dismissed {
+            let editor = self.ui.editor_area(ids!(body.center.editor));
+            match link_result {
+                Some(LinkDialogResult::Apply { text, url }) => {
+                    editor.apply_link_edit(cx, &text, Some(&url));
+                }
+                Some(LinkDialogResult::Remove) => editor.apply_link_edit(cx, "", None),
+                _ => {}
+            }
+            self.ui.modal(ids!(link_modal)).close(cx);
+            let editor_view = self.ui.view(ids!(body.center.editor));
+            cx.set_key_focus(editor_view.area());
+        }
+
+        // --- ENREGISTRER AVANT DE CHANGER DE FICHIER ---
+        let prompt_result = self
+            .ui
+            .save_prompt(ids!(save_modal.save_prompt))
+            .borrow()
+            .and_then(|prompt| prompt.handle_prompt_actions(actions));
+        let dismissed = self.ui.modal(ids!(save_modal)).dismissed(actions);
+
+        if prompt_result.is_some() || dismissed {
+            self.ui.modal(ids!(save_modal)).close(cx);
+            match prompt_result {
+                Some(SavePromptResult::Save) => {
+                    self.save_before_switch = true;
+                    self.ui.editor_area(ids!(body.center.editor)).save();
+                }
+                Some(SavePromptResult::Discard) => {
+                    let editor = self.ui.editor_area(ids!(body.center.editor));
+                    editor.discard_journal();
+                    match self.pending.take() {
+                        // L'onglet abandonné est fermé, puis on passe au suivant
+                        Some(PendingSwitch::Quit) => {
+                            editor.close_tab(cx, editor.active_tab());
+                            self.request_switch(cx, PendingSwitch::Quit);
+                        }
+                        Some(switch) => self.run_switch(cx, switch),
+                        None => {}
+                    }
+                }
+                _ => self.pending = None,
+            }
+        }
+
+        // --- FICHIER MODIFIÉ SUR LE DISQUE ---
+        let conflict_choice = self
+            .ui
+            .conflict_banner(ids!(body.center.conflict_banner))
+            .borrow()
+            .and_then(|banner| banner.handle_banner_actions(actions));
+        if let Some(choice) = conflict_choice {
+            let editor = self.ui.editor_area(ids!(body.center.editor));
+            match choice {
+                ConflictChoice::Reload => editor.reload_from_disk(cx),
+                ConflictChoice::KeepMine => editor.keep_mine(),
+                ConflictChoice::Merge => {
+                    let conflicts = editor.merge_with_disk(cx);
+                    if conflicts > 0 {
+                        makepad_widgets::log!(
+                            "{} conflit(s) à résoudre entre les marqueurs",
+                            conflicts
+                        );
+                    }
+                }
+            }
+            self.ui
+                .conflict_banner(ids!(body.center.conflict_banner))
+                .hide(cx);
+        }
+
+        // --- RECHERCHE RAPIDE (Ctrl+P) ---
+        let quick_open_result = self
+            .ui
+            .quick_open(ids!(quick_open_modal.quick_open))
+            .borrow_mut()
+            .and_then(|mut quick_open| quick_open.handle_quick_open_actions(cx, actions));
+        let dismissed = self.ui.modal(ids!(quick_open_modal)).dismissed(actions);
 
-                // Charger le fichier dans l'éditeur
+        if quick_open_result.is_some() || dismissed {
+            if let Some(QuickOpenResult::Open(path)) = quick_open_result {
                 let editor = self.ui.editor_area(ids!(body.center.editor));
                 editor.load_file(cx, path);
             }
+            self.ui.modal(ids!(quick_open_modal)).close(cx);
+            let editor_view = self.ui.view(ids!(body.center.editor));
+            cx.set_key_focus(editor_view.area());
         }
 
-        // Gestion des actions de l'éditeur (s'il en émet)
-        // Note: EditorArea n'émet pas encore d'actions WidgetAction formelles dans le code actuel,
-        // mais si on voulait écouter des événements:
-        for action in actions {
-            if let Some(EditorAction::FileLoaded(_)) = action.as_widget_action().cast() {
-                makepad_widgets::log!("File loaded successfully.");
+        // --- RÉCUPÉRATION APRÈS UNE FERMETURE IMPRÉVUE ---
+        let recovery_result = self
+            .ui
+            .recovery_prompt(ids!(recovery_modal.recovery_prompt))
+            .borrow()
+            .and_then(|prompt| prompt.handle_prompt_actions(actions));
+        if let Some(result) = recovery_result {
+            let editor = self.ui.editor_area(ids!(body.center.editor));
+            match result {
+                RecoveryPromptResult::Restore => editor.restore_journal(),
+                RecoveryPromptResult::Discard => editor.discard_journal(),
             }
+            self.ui.modal(ids!(recovery_modal)).close(cx);
         }
     }
 }
@@ -153,17 +484,44 @@ impl MatchEvent for App {
 impl AppMain for App {
     fn handle_event(&mut self, cx: &mut Cx, event: &Event) {
         if let Event::Startup = event {
-            let initial_file = "story.md".to_string();
-            self.ui
-                .label(ids!(body.center.top_bar.title))
-                .set_text(cx, &initial_file);
-            self.ui
-                .editor_area(ids!(body.center.editor))
-                .load_file(cx, initial_file);
+            let options = STARTUP_OPTIONS.get().cloned().unwrap_or_default();
+            let editor = self.ui.editor_area(ids!(body.center.editor));
+            editor.set_autosave(options.autosave);
+            editor.set_backups(options.backups);
+            match options.document {
+                StartupDocument::File(initial_file) => {
+                    self.ui
+                        .top_bar(ids!(body.center.top_bar))
+                        .set_title(cx, &initial_file);
+                    editor.load_file(cx, initial_file);
+                }
+                StartupDocument::Empty => {
+                    self.ui
+                        .top_bar(ids!(body.center.top_bar))
+                        .set_title(cx, "Sans titre");
+                    editor.new_document(cx);
+                }
+            }
 
             let editor = self.ui.view(ids!(body.center.editor));
             cx.set_key_focus(editor.area());
         }
+        if let Event::KeyDown(ke) = event {
+            let ctrl = ke.modifiers.control || ke.modifiers.logo;
+            if ctrl && ke.key_code == KeyCode::KeyP {
+                self.ui.modal(ids!(quick_open_modal)).open(cx);
+                self.ui
+                    .quick_open(ids!(quick_open_modal.quick_open))
+                    .open(cx);
+            }
+        }
+        if let Event::WindowCloseRequested(e) = event {
+            let editor = self.ui.editor_area(ids!(body.center.editor));
+            if !self.quitting && editor.tabs().iter().any(|tab| tab.modified) {
+                e.accept_close.set(false);
+                self.request_switch(cx, PendingSwitch::Quit);
+            }
+        }
         self.match_event(cx, event);
         self.ui.handle_event(cx, event, &mut Scope::empty());
     }
@@ -175,6 +533,9 @@ impl Default for App {
             ui: WidgetRef::default(),
             left_visible: true,
             right_visible: true,
+            pending: None,
+            save_before_switch: false,
+            quitting: false,
         }
     }
 }
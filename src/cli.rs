@@ -0,0 +1,122 @@
+// Arguments de la ligne de commande : ndown [--new] [FICHIER | DOSSIER]
+use ndown::app::{StartupDocument, StartupOptions};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: ndown [--new] [--autosave] [--backups N] [FILE | FOLDER]
+
+  FILE         Markdown file to open
+  FOLDER       folder to use as the workspace root
+  --new        start with an empty document
+  --autosave   save the file a few seconds after the last edit
+  --backups N  keep the last N versions of saved files in .ndown/backups
+  -h, --help   print this help";
+
+#[derive(Debug, PartialEq)]
+pub struct Args {
+    /// Folder that becomes the workspace root (the current directory otherwise).
+    pub workspace: Option<PathBuf>,
+    pub options: StartupOptions,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(Args),
+    Help,
+}
+
+/// Parses the arguments that follow the program name.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Command, String> {
+    let mut new_document = false;
+    let mut autosave = false;
+    let mut backups = 0;
+    let mut path = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--new" => new_document = true,
+            "--autosave" => autosave = true,
+            "--backups" => {
+                let value = args.next().unwrap_or_default();
+                backups = value
+                    .parse()
+                    .map_err(|_| format!("--backups expects a number, got {value:?}"))?;
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option: {arg}"));
+            }
+            _ if path.is_some() => return Err(format!("unexpected argument: {arg}")),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let (workspace, file) = match path {
+        Some(path) if path.is_dir() => (Some(path), None),
+        Some(path) => (None, Some(path)),
+        None => (None, None),
+    };
+    let document = match file {
+        Some(_) if new_document => {
+            return Err("--new cannot be combined with a file".to_string());
+        }
+        Some(file) => StartupDocument::File(file.to_string_lossy().into_owned()),
+        None if new_document || workspace.is_some() => StartupDocument::Empty,
+        None => StartupDocument::default(),
+    };
+    Ok(Command::Run(Args {
+        workspace,
+        options: StartupOptions {
+            document,
+            autosave,
+            backups,
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let run = |workspace: Option<&str>, document| {
+            Ok(Command::Run(Args {
+                workspace: workspace.map(PathBuf::from),
+                options: StartupOptions {
+                    document,
+                    autosave: false,
+                    backups: 0,
+                },
+            }))
+        };
+        assert_eq!(parse(&[]), run(None, StartupDocument::default()));
+        assert_eq!(
+            parse(&["notes/a.md"]),
+            run(None, StartupDocument::File("notes/a.md".to_string()))
+        );
+        assert_eq!(parse(&["src"]), run(Some("src"), StartupDocument::Empty));
+        assert_eq!(parse(&["--new"]), run(None, StartupDocument::Empty));
+        assert_eq!(
+            parse(&["--new", "src"]),
+            run(Some("src"), StartupDocument::Empty)
+        );
+        assert_eq!(parse(&["a.md", "--help"]), Ok(Command::Help));
+        let Ok(Command::Run(args)) = parse(&["--autosave", "a.md"]) else {
+            panic!("--autosave should parse");
+        };
+        assert!(args.options.autosave);
+        let Ok(Command::Run(args)) = parse(&["--backups", "5"]) else {
+            panic!("--backups should parse");
+        };
+        assert_eq!(args.options.backups, 5);
+        assert!(parse(&["--backups", "many"]).is_err());
+        assert!(parse(&["--new", "a.md"]).is_err());
+        assert!(parse(&["a.md", "b.md"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+}
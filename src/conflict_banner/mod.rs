@@ -0,0 +1,98 @@
+use makepad_widgets::*;
+
+// Choix proposé quand le fichier ouvert change sur le disque pendant l'édition
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConflictChoice {
+    Reload,
+    KeepMine,
+    Merge,
+}
+
+live_design! {
+    use link::theme::*;
+    use link::widgets::*;
+    use crate::theme::*;
+
+    pub ConflictBanner = {{ConflictBanner}}{
+        visible: false // Affiché seulement en cas de conflit
+        width: Fill, height: Fit
+        flow: Right, spacing: 10, padding: {left: 15, right: 15, top: 8, bottom: 8}
+        align: {y: 0.5}
+        show_bg: true
+        draw_bg: { color: (NORD_POLAR_2) }
+
+        message = <Label> {
+            width: Fill
+            text: ""
+            draw_text: {
+                text_style: <THEME_FONT_REGULAR> {font_size: 10}, color: (NORD_AURORA_YELLOW)
+                wrap: Word
+            }
+        }
+
+        reload_btn = <Button> { text: "Recharger", draw_text: { color: (NORD_AURORA_RED) } }
+        keep_btn = <Button> { text: "Garder mes modifications", draw_text: { color: (NORD_SNOW_2) } }
+        merge_btn = <Button> { text: "Fusionner", draw_text: { color: (NORD_FROST_1) } }
+    }
+}
+
+#[derive(Live, Widget)]
+pub struct ConflictBanner {
+    #[deref]
+    view: View,
+}
+
+impl LiveHook for ConflictBanner {}
+
+impl ConflictBanner {
+    pub fn show(&mut self, cx: &mut Cx, name: &str) {
+        self.view.label(ids!(message)).set_text(
+            cx,
+            &format!("« {name} » a été modifié par un autre programme pendant l'édition."),
+        );
+        self.view.apply_over(cx, live! {visible: true});
+        self.view.redraw(cx);
+    }
+
+    pub fn hide(&mut self, cx: &mut Cx) {
+        self.view.apply_over(cx, live! {visible: false});
+        self.view.redraw(cx);
+    }
+
+    pub fn handle_banner_actions(&self, actions: &Actions) -> Option<ConflictChoice> {
+        if self.view.button(ids!(reload_btn)).clicked(actions) {
+            return Some(ConflictChoice::Reload);
+        }
+        if self.view.button(ids!(keep_btn)).clicked(actions) {
+            return Some(ConflictChoice::KeepMine);
+        }
+        if self.view.button(ids!(merge_btn)).clicked(actions) {
+            return Some(ConflictChoice::Merge);
+        }
+        None
+    }
+}
+
+impl ConflictBannerRef {
+    pub fn show(&self, cx: &mut Cx, name: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.show(cx, name);
+        }
+    }
+
+    pub fn hide(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.hide(cx);
+        }
+    }
+}
+
+impl Widget for ConflictBanner {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope)
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
@@ -0,0 +1,77 @@
+// Versions précédentes des fichiers, gardées dans .ndown/backups à la racine de l'espace de travail
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const BACKUP_DIR: &str = ".ndown/backups";
+
+/// `notes/idées.md` keeps its versions in `.ndown/backups/notes/idées.md/`.
+pub fn backup_dir(root: &Path, file: &str) -> PathBuf {
+    let path = Path::new(file);
+    let relative = if path.is_absolute() {
+        match path.strip_prefix(root) {
+            Ok(relative) => relative,
+            // Hors de l'espace de travail : seul le nom est gardé
+            Err(_) => Path::new(path.file_name().unwrap_or_default()),
+        }
+    } else {
+        path
+    };
+    root.join(BACKUP_DIR).join(relative)
+}
+
+/// Copies the current content of `file` to its backup folder before it is overwritten,
+/// then removes the oldest copies so that at most `keep` remain.
+pub fn backup_before_save(root: &Path, file: &str, keep: usize) -> io::Result<()> {
+    if keep == 0 || !Path::new(file).is_file() {
+        return Ok(());
+    }
+    let dir = backup_dir(root, file);
+    fs::create_dir_all(&dir)?;
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S%.3f");
+    let ext = Path::new(file)
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    fs::copy(file, dir.join(format!("{stamp}{ext}")))?;
+
+    // Les noms horodatés se trient du plus ancien au plus récent
+    let mut versions: Vec<PathBuf> = fs::read_dir(&dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    versions.sort();
+    let excess = versions.len().saturating_sub(keep);
+    for old in &versions[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backups_keep_last_versions() {
+        let root = std::env::temp_dir().join(format!("ndown-backup-{}", std::process::id()));
+        fs::create_dir_all(root.join("notes")).unwrap();
+        let file = root.join("notes/a.md").to_string_lossy().into_owned();
+        let dir = backup_dir(&root, &file);
+        assert_eq!(dir, root.join(BACKUP_DIR).join("notes/a.md"));
+
+        for version in 1..=3 {
+            fs::write(&file, format!("v{version}")).unwrap();
+            backup_before_save(&root, &file, 2).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let mut kept: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+            .collect();
+        kept.sort();
+        assert_eq!(kept, ["v2", "v3"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,335 @@
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    Type,
+    Function,
+    String,
+    Number,
+    Comment,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte range in the highlighted text.
+    pub range: Range<usize>,
+}
+
+struct Language {
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    types: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    // En Rust, `'` ouvre un caractère ('a') mais aussi une durée de vie ('a)
+    char_literals: bool,
+    // Identifiant commençant par une majuscule = type
+    capitalized_types: bool,
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        types: &[
+            "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16",
+            "i32", "i64", "i128", "isize", "f32", "f64",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        char_literals: true,
+        capitalized_types: true,
+    },
+    Language {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+            "True", "try", "while", "with", "yield",
+        ],
+        types: &["int", "float", "str", "bool", "list", "dict", "set", "tuple", "bytes"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        char_literals: false,
+        capitalized_types: true,
+    },
+    Language {
+        names: &["javascript", "js", "jsx", "typescript", "ts", "tsx"],
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "default",
+            "delete", "do", "else", "export", "extends", "false", "finally", "for", "from",
+            "function", "if", "import", "in", "instanceof", "interface", "let", "new", "null",
+            "return", "static", "super", "switch", "this", "throw", "true", "try", "type",
+            "typeof", "undefined", "var", "void", "while", "yield",
+        ],
+        types: &["string", "number", "boolean", "any", "unknown", "never", "object"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        char_literals: false,
+        capitalized_types: true,
+    },
+    Language {
+        names: &["c", "h", "cpp", "c++", "cc", "hpp", "java", "cs", "csharp"],
+        keywords: &[
+            "break", "case", "catch", "class", "const", "continue", "default", "delete", "do",
+            "else", "enum", "extends", "false", "final", "for", "goto", "if", "implements",
+            "import", "include", "namespace", "new", "nullptr", "null", "package", "private",
+            "protected", "public", "return", "sizeof", "static", "struct", "switch", "template",
+            "this", "throw", "true", "try", "typedef", "union", "using", "virtual", "void",
+            "volatile", "while",
+        ],
+        types: &[
+            "bool", "byte", "char", "double", "float", "int", "long", "short", "signed",
+            "unsigned", "size_t", "auto", "String",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        char_literals: true,
+        capitalized_types: true,
+    },
+    Language {
+        names: &["go", "golang"],
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else",
+            "fallthrough", "false", "for", "func", "go", "goto", "if", "import", "interface",
+            "map", "nil", "package", "range", "return", "select", "struct", "switch", "true",
+            "type", "var",
+        ],
+        types: &[
+            "bool", "byte", "error", "float32", "float64", "int", "int8", "int16", "int32",
+            "int64", "rune", "string", "uint", "uint8", "uint16", "uint32", "uint64",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '`'],
+        char_literals: true,
+        capitalized_types: false,
+    },
+    Language {
+        names: &["sh", "bash", "shell", "zsh", "console"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function",
+            "if", "in", "local", "return", "then", "until", "while",
+        ],
+        types: &[],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        char_literals: false,
+        capitalized_types: false,
+    },
+    Language {
+        names: &["json", "toml", "yaml", "yml", "ini"],
+        keywords: &["true", "false", "null"],
+        types: &[],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        char_literals: false,
+        capitalized_types: false,
+    },
+];
+
+fn find_language(name: &str) -> Option<&'static Language> {
+    let name = name.to_ascii_lowercase();
+    LANGUAGES.iter().find(|lang| lang.names.contains(&name.as_str()))
+}
+
+pub fn is_supported(language: &str) -> bool {
+    find_language(language).is_some()
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Longueur d'un littéral caractère ('a', '\n', '\u{1F600}') au début de `rest`, sinon None
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let mut chars = rest.char_indices().skip(1);
+    let (_, c) = chars.next()?;
+    if c == '\\' {
+        let close = rest[1..].find('\'')? + 1;
+        return (close <= 12 && !rest[..close].contains('\n')).then_some(close + 1);
+    }
+    let (i, close) = chars.next()?;
+    (close == '\'').then_some(i + 1)
+}
+
+fn quoted_len(rest: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return i + c.len_utf8();
+        } else if c == '\n' && quote != '`' {
+            return i;
+        }
+    }
+    rest.len()
+}
+
+/// Splits `code` into coloured tokens. The tokens cover the whole text, in order.
+/// Unknown languages come back as a single plain token.
+pub fn tokenize(language: &str, code: &str) -> Vec<Token> {
+    let Some(lang) = find_language(language) else {
+        return vec![Token {
+            kind: TokenKind::Plain,
+            range: 0..code.len(),
+        }];
+    };
+
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut push = |kind: TokenKind, range: Range<usize>| {
+        if range.is_empty() {
+            return;
+        }
+        match tokens.last_mut() {
+            Some(last) if last.kind == kind && last.range.end == range.start => {
+                last.range.end = range.end;
+            }
+            _ => tokens.push(Token { kind, range }),
+        }
+    };
+
+    let mut pos = 0;
+    while pos < code.len() {
+        let rest = &code[pos..];
+        let c = rest.chars().next().unwrap();
+
+        if let Some(len) = lang
+            .line_comments
+            .iter()
+            .find(|prefix| rest.starts_with(*prefix))
+            .map(|_| rest.find('\n').unwrap_or(rest.len()))
+        {
+            push(TokenKind::Comment, pos..pos + len);
+            pos += len;
+            continue;
+        }
+
+        if let Some((open, close)) = lang.block_comment {
+            if let Some(body) = rest.strip_prefix(open) {
+                let len = body
+                    .find(close)
+                    .map(|i| open.len() + i + close.len())
+                    .unwrap_or(rest.len());
+                push(TokenKind::Comment, pos..pos + len);
+                pos += len;
+                continue;
+            }
+        }
+
+        if lang.char_literals && c == '\'' {
+            if let Some(len) = char_literal_len(rest) {
+                push(TokenKind::String, pos..pos + len);
+                pos += len;
+                continue;
+            }
+        }
+
+        if lang.quotes.contains(&c) {
+            let len = quoted_len(rest, c);
+            push(TokenKind::String, pos..pos + len);
+            pos += len;
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let len = rest
+                .find(|ch: char| !(is_ident_char(ch) || ch == '.'))
+                .unwrap_or(rest.len());
+            push(TokenKind::Number, pos..pos + len);
+            pos += len;
+            continue;
+        }
+
+        if is_ident_char(c) {
+            let len = rest.find(|ch| !is_ident_char(ch)).unwrap_or(rest.len());
+            let word = &rest[..len];
+            let kind = if lang.keywords.contains(&word) {
+                TokenKind::Keyword
+            } else if lang.types.contains(&word)
+                || (lang.capitalized_types && word.starts_with(|ch: char| ch.is_uppercase()))
+            {
+                TokenKind::Type
+            } else if rest[len..].starts_with('(') || rest[len..].starts_with("!(") {
+                TokenKind::Function
+            } else {
+                TokenKind::Plain
+            };
+            push(kind, pos..pos + len);
+            pos += len;
+            continue;
+        }
+
+        push(TokenKind::Plain, pos..pos + c.len_utf8());
+        pos += c.len_utf8();
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds<'a>(code: &'a str, tokens: &[Token]) -> Vec<(TokenKind, &'a str)> {
+        tokens
+            .iter()
+            .filter(|t| t.kind != TokenKind::Plain)
+            .map(|t| (t.kind, &code[t.range.clone()]))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_rust() {
+        let code = "fn main() {\n    let s: &'a str = \"hi\"; // done\n    f(b'x', 42)\n}";
+        let tokens = tokenize("rust", code);
+        assert_eq!(tokens.first().unwrap().range.start, 0);
+        assert_eq!(tokens.last().unwrap().range.end, code.len());
+        assert!(tokens.windows(2).all(|w| w[0].range.end == w[1].range.start));
+        assert_eq!(
+            kinds(code, &tokens),
+            vec![
+                (TokenKind::Keyword, "fn"),
+                (TokenKind::Function, "main"),
+                (TokenKind::Keyword, "let"),
+                (TokenKind::Type, "str"),
+                (TokenKind::String, "\"hi\""),
+                (TokenKind::Comment, "// done"),
+                (TokenKind::Function, "f"),
+                (TokenKind::String, "'x'"),
+                (TokenKind::Number, "42"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_language_is_plain() {
+        let tokens = tokenize("", "let x = 1;");
+        assert_eq!(
+            tokens,
+            vec![Token {
+                kind: TokenKind::Plain,
+                range: 0..10
+            }]
+        );
+        assert!(is_supported("Python"));
+    }
+}
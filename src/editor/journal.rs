@@ -0,0 +1,62 @@
+// Journal de récupération : copie du document non enregistré, écrite à côté du fichier
+use crate::editor::model::document::Document;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `notes/idées.md` is journaled in `notes/.idées.md.ndown-journal`.
+pub fn journal_path(file: &str) -> PathBuf {
+    let path = Path::new(file);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.ndown-journal"))
+}
+
+pub fn write(doc: &Document, file: &str) -> io::Result<()> {
+    doc.save_to_file(&journal_path(file).to_string_lossy())
+}
+
+pub fn remove(file: &str) {
+    let _ = fs::remove_file(journal_path(file));
+}
+
+/// True when a journal exists and was written after the file was last saved.
+pub fn is_newer_than_file(file: &str) -> bool {
+    let Ok(journal) = fs::metadata(journal_path(file)).and_then(|m| m.modified()) else {
+        return false;
+    };
+    match fs::metadata(file).and_then(|m| m.modified()) {
+        Ok(saved) => journal > saved,
+        Err(_) => true,
+    }
+}
+
+pub fn read(file: &str) -> io::Result<Document> {
+    let content = fs::read_to_string(journal_path(file))?;
+    Ok(Document::from_markdown(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ndown-journal-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("note.md").to_string_lossy().into_owned();
+        assert_eq!(journal_path(&file), dir.join(".note.md.ndown-journal"));
+
+        fs::write(&file, "# Titre\n").unwrap();
+        assert!(!is_newer_than_file(&file));
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        let doc = Document::from_markdown("# Titre\n\nNon enregistré\n");
+        write(&doc, &file).unwrap();
+        assert!(is_newer_than_file(&file));
+        assert_eq!(read(&file).unwrap().blocks.len(), 2);
+
+        remove(&file);
+        assert!(!is_newer_than_file(&file));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,28 +1,219 @@
 use makepad_widgets::*;
 use crate::TOKIO_RUNTIME;
+use crate::file_explorer::ops;
+use std::path::Path;
+use std::process::Command;
 
 pub mod model; // Nouveau module enfant
-use model::block::{Block, BlockType};
-use model::document::Document;
-
+use model::block::{Block, BlockType, InlineStyle};
+use model::document::{Document, OutlineEntry};
+use model::history::{CursorState, EditKind, History};
+use model::merge::merge3;
+use model::parser;
+
+pub mod backup;
+pub mod highlight;
+pub mod journal;
+pub mod tabs;
 pub mod view;
-use view::{DrawParams, EditorView};
+pub mod watcher;
+use tabs::{clamp_cursor, DocTab, TabInfo};
+use view::{DrawParams, EditorView, SyntaxColors};
+use watcher::{FileWatcher, OwnWrites};
+
+// Secondes sans modification avant l'autosauvegarde (ou l'écriture du journal)
+const AUTOSAVE_DELAY: f64 = 3.0;
 
 #[derive(Clone, DefaultNone, Debug)]
 pub enum EditorAction {
     FileLoaded(String),
-    AsyncFileLoaded(String, Vec<Block>),
-    AsyncFileSaved(String),
+    /// Path, parsed document and the text read from the file.
+    AsyncFileLoaded(String, Document, String),
+    /// The document was written to this file, up to this revision, with this text.
+    AsyncFileSaved(String, u64, String),
     AsyncError(String),
+    /// Sent to the App once a save has succeeded.
+    FileSaved(String),
+    SaveFailed(String),
+    /// The document now has (or no longer has) unsaved changes.
+    ModifiedChanged(bool),
+    /// A recovery journal newer than this file was found when loading it.
+    AsyncRecoveryAvailable(String),
+    AsyncJournalLoaded(String, Document),
+    /// Sent to the App: the journal of the open file can be restored.
+    RecoveryAvailable(String),
+    /// Sent by the watcher thread: the open file changed on disk.
+    AsyncFileChanged(String),
+    AsyncDiskContent(String, String),
+    /// Sent to the App: the file changed on disk while it has unsaved edits here.
+    ExternalConflict(String),
+    /// Ctrl+K: the link dialog should open with this text and destination.
+    EditLink { text: String, url: String },
+    OpenLink(String),
+    /// Click on the checkbox of the task item at this block index.
+    ToggleTask(usize),
+    /// Headings of the document, and the index of the one containing the cursor.
+    OutlineChanged {
+        entries: Vec<OutlineEntry>,
+        current: Option<usize>,
+    },
+    /// Open documents, and the index of the one on screen.
+    TabsChanged {
+        tabs: Vec<TabInfo>,
+        active: usize,
+    },
     None,
 }
 
 impl EditorAreaRef {
+    /// Opens the file in a new tab, or shows the tab where it is already open.
     pub fn load_file(&self, cx: &mut Cx, filename: String) {
         if let Some(mut inner) = self.borrow_mut() {
-            inner.load_file_async(cx, filename);
+            inner.open_file(cx, filename);
+        }
+    }
+
+    pub fn select_tab(&self, cx: &mut Cx, idx: usize) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.select_tab(cx, idx);
+        }
+    }
+
+    /// Closes the tab without saving it.
+    pub fn close_tab(&self, cx: &mut Cx, idx: usize) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.close_tab(cx, idx);
+        }
+    }
+
+    pub fn active_tab(&self) -> usize {
+        self.borrow().map_or(0, |inner| inner.active_tab)
+    }
+
+    pub fn tabs(&self) -> Vec<TabInfo> {
+        self.borrow()
+            .map_or_else(Vec::new, |inner| inner.tab_infos())
+    }
+
+    pub fn new_document(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.new_document(cx);
+        }
+    }
+
+    pub fn save(&self) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.save();
+        }
+    }
+
+    pub fn set_autosave(&self, enabled: bool) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.autosave = enabled;
+        }
+    }
+
+    /// Number of previous versions kept in `.ndown/backups` on each save (0 = none).
+    pub fn set_backups(&self, keep: usize) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.backups = keep;
+        }
+    }
+
+    pub fn restore_journal(&self) {
+        if let Some(inner) = self.borrow() {
+            inner.restore_journal();
+        }
+    }
+
+    pub fn discard_journal(&self) {
+        if let Some(inner) = self.borrow() {
+            inner.discard_journal();
+        }
+    }
+
+    /// Drops the local edits and takes the version found on disk.
+    pub fn reload_from_disk(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.reload_from_disk(cx);
+        }
+    }
+
+    /// Keeps the local edits; the next save overwrites the version found on disk.
+    pub fn keep_mine(&self) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.keep_mine();
         }
     }
+
+    /// Merges the local edits with the version found on disk. Returns the number of
+    /// conflicts left between markers in the document.
+    pub fn merge_with_disk(&self, cx: &mut Cx) -> usize {
+        self.borrow_mut()
+            .map_or(0, |mut inner| inner.merge_with_disk(cx))
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.borrow()
+            .is_some_and(|inner| inner.document.is_modified())
+    }
+
+    pub fn current_file(&self) -> Option<String> {
+        self.borrow()?.current_file.clone()
+    }
+
+    pub fn apply_link_edit(&self, cx: &mut Cx, text: &str, url: Option<&str>) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.apply_link_edit(cx, text, url);
+        }
+    }
+
+    pub fn scroll_to_block(&self, cx: &mut Cx, block_idx: usize) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.scroll_to_block(cx, block_idx);
+        }
+    }
+
+    /// Follows a rename or move of the open file (or of a folder containing it).
+    /// Returns the new path of the open file if it changed.
+    pub fn file_moved(&self, from: &Path, to: &Path) -> Option<String> {
+        self.borrow_mut()?.file_moved(from, to)
+    }
+}
+
+// Ouvre une adresse avec l'application par défaut du système
+fn open_external(target: &str) -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = Command::new("xdg-open");
+
+    command.arg(target).spawn().map(|_| ())
+}
+
+// Le fil de surveillance prévient l'éditeur par une action
+fn watch_file(filename: &str) -> FileWatcher {
+    let path = filename.to_string();
+    FileWatcher::new(filename, move || {
+        Cx::post_action(EditorAction::AsyncFileChanged(path.clone()));
+    })
+}
+
+// Nouveau chemin de `file` si lui ou l'un de ses dossiers a été déplacé
+fn moved_path(file: &str, from: &Path, to: &Path) -> Option<String> {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let moved = ops::rebase(&cwd.join(file), from, to)?;
+    // Garde un chemin relatif s'il l'était
+    Some(match moved.strip_prefix(&cwd) {
+        Ok(relative) => relative.to_string_lossy().into_owned(),
+        Err(_) => moved.to_string_lossy().into_owned(),
+    })
 }
 
 live_design! {
@@ -41,6 +232,7 @@ live_design! {
         draw_text_reg: { text_style: <THEME_FONT_REGULAR> { font_size: 12.1 }, color: (NORD_SNOW_2) }
         draw_text_bold: { text_style: <THEME_FONT_BOLD> { font_size: 12.1 }, color: (NORD_SNOW_2) }
         draw_text_italic: { text_style: <THEME_FONT_ITALIC> { font_size: 12.1 }, color: (NORD_SNOW_2) }
+        draw_text_bold_italic: { text_style: <THEME_FONT_BOLD_ITALIC> { font_size: 12.1 }, color: (NORD_SNOW_2) }
         draw_text_code: { text_style: <THEME_FONT_CODE> { font_size: 12.1 }, color: (NORD_AURORA_GREEN) }
 
         draw_text_header1: { text_style: <THEME_FONT_BOLD> { font_size: 29.0 }, color: (NORD_FROST_1) }
@@ -49,6 +241,35 @@ live_design! {
         draw_text_header4: { text_style: <THEME_FONT_BOLD> { font_size: 16.9 }, color: (NORD_FROST_2) }
         draw_text_header5: { text_style: <THEME_FONT_BOLD> { font_size: 14.5 }, color: (NORD_FROST_2) }
         draw_text_quote: { text_style: <THEME_FONT_ITALIC> { font_size: 13.3 }, color: (NORD_AURORA_ORANGE) }
+        draw_text_link: { text_style: <THEME_FONT_REGULAR> { font_size: 12.1 }, color: (NORD_FROST_1) }
+        draw_link_underline: { color: (NORD_FROST_1) }
+        draw_strikethrough: { color: (NORD_SNOW_0) }
+        draw_highlight: { color: #EBCB8B55 }
+        draw_checkbox: {
+            color: (NORD_SNOW_0)
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(1.0, 1.0, self.rect_size.x - 2.0, self.rect_size.y - 2.0, 2.5);
+                sdf.stroke(self.color, 1.2);
+                return sdf.result;
+            }
+        }
+        draw_table_border: { color: (NORD_POLAR_3) }
+        draw_table_header_bg: { color: (NORD_POLAR_1) }
+        draw_checkbox_checked: {
+            color: (NORD_FROST_1)
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                let sz = self.rect_size;
+                sdf.box(1.0, 1.0, sz.x - 2.0, sz.y - 2.0, 2.5);
+                sdf.fill(self.color);
+                sdf.move_to(sz.x * 0.25, sz.y * 0.5);
+                sdf.line_to(sz.x * 0.43, sz.y * 0.7);
+                sdf.line_to(sz.x * 0.75, sz.y * 0.3);
+                sdf.stroke((NORD_POLAR_0), 1.6);
+                return sdf.result;
+            }
+        }
 
         draw_cursor: { color: #ffffff }
         draw_selection: { color: (NORD_POLAR_3) }
@@ -69,6 +290,14 @@ live_design! {
             }
         }
         draw_text_code_header: { text_style: <THEME_FONT_REGULAR> { font_size: 9.0 }, color: (NORD_SNOW_0) }
+        syntax_colors: {
+            keyword: (NORD_FROST_2)
+            type_name: (NORD_FROST_0)
+            function: (NORD_FROST_1)
+            string: (NORD_AURORA_GREEN)
+            number: (NORD_AURORA_PURPLE)
+            comment: #616E88
+        }
 
         animator: {
             blink = {
@@ -95,6 +324,8 @@ pub struct EditorArea {
     #[live]
     draw_text_italic: DrawText,
     #[live]
+    draw_text_bold_italic: DrawText,
+    #[live]
     draw_text_code: DrawText,
     #[live]
     draw_text_header1: DrawText,
@@ -108,6 +339,22 @@ pub struct EditorArea {
     draw_text_header5: DrawText,
     #[live]
     draw_text_quote: DrawText,
+    #[live]
+    draw_text_link: DrawText,
+    #[live]
+    draw_link_underline: DrawColor,
+    #[live]
+    draw_strikethrough: DrawColor,
+    #[live]
+    draw_highlight: DrawColor,
+    #[live]
+    draw_checkbox: DrawColor,
+    #[live]
+    draw_checkbox_checked: DrawColor,
+    #[live]
+    draw_table_border: DrawColor,
+    #[live]
+    draw_table_header_bg: DrawColor,
 
     #[live]
     draw_cursor: DrawColor,
@@ -117,6 +364,8 @@ pub struct EditorArea {
     draw_code_bg: DrawColor,
     #[live]
     draw_text_code_header: DrawText,
+    #[live]
+    syntax_colors: SyntaxColors,
 
     #[animator]
     animator: Animator,
@@ -130,6 +379,8 @@ pub struct EditorArea {
     #[rust]
     document: Document,
     #[rust]
+    history: History,
+    #[rust]
     cursor_block: usize,
     #[rust]
     cursor_char: usize,
@@ -153,6 +404,60 @@ pub struct EditorArea {
     current_file: Option<String>,
     #[rust]
     ignore_next_text_input: bool,
+    // Bloc et plage de caractères du lien en cours d'édition (Ctrl+K)
+    #[rust]
+    link_edit: Option<(usize, usize, usize)>,
+    #[rust]
+    open_link_on_tap: bool,
+    // Le clic en cours peut cocher une tâche (pas un glisser)
+    #[rust]
+    toggle_task_on_tap: bool,
+    // Dernier plan envoyé au panneau de structure
+    #[rust]
+    outline: Vec<OutlineEntry>,
+    #[rust]
+    outline_current: Option<usize>,
+    // Bloc à amener en haut de la vue dès que sa position est connue
+    #[rust]
+    pending_scroll: Option<usize>,
+    // Dernier état "modifié" envoyé à l'App
+    #[rust]
+    reported_modified: bool,
+    // Enregistrement automatique (--autosave), sinon journal de récupération
+    #[rust]
+    autosave: bool,
+    #[rust]
+    autosave_timer: Timer,
+    // Révision pour laquelle le minuteur a été lancé
+    #[rust]
+    timed_revision: u64,
+    #[rust]
+    backups: usize,
+    // Surveillance du fichier ouvert
+    #[rust]
+    watcher: Option<FileWatcher>,
+    // Contenu du fichier au dernier chargement ou enregistrement : base de la fusion
+    #[rust]
+    disk_base: String,
+    // Version trouvée sur le disque alors que le document a des modifications locales
+    #[rust]
+    disk_conflict: Option<String>,
+    // Ce que nos enregistrements ont écrit, pour ne pas les prendre pour un changement extérieur
+    #[rust]
+    own_writes: OwnWrites,
+    // Onglets ouverts ; `None` pour celui affiché, dont l'état est dans les champs ci-dessus
+    #[rust]
+    tabs: Vec<Option<DocTab>>,
+    #[rust]
+    active_tab: usize,
+    // Défilement à rétablir en revenant sur un onglet
+    #[rust]
+    restored_scroll: Option<f64>,
+    // Derniers onglets envoyés à la TopBar
+    #[rust]
+    reported_tabs: Vec<TabInfo>,
+    #[rust]
+    reported_active_tab: usize,
 }
 
 impl LiveHook for EditorArea {
@@ -162,6 +467,7 @@ impl LiveHook for EditorArea {
         self.cursor_char = 0;
         self.blink_timer = cx.start_timeout(0.5);
         self.clipboard = arboard::Clipboard::new().ok();
+        self.tabs = vec![None];
     }
 }
 
@@ -179,68 +485,307 @@ impl EditorArea {
         }
     }
 
+    // Sélection copiée en Markdown, avec une version HTML pour le texte riche.
+    // Sans accès direct au presse-papiers, renvoie le Markdown à y placer.
+    fn copy_selection(&mut self, start: (usize, usize), end: (usize, usize)) -> Option<String> {
+        let markdown = self.document.get_markdown_in_range(start, end);
+        let Some(clipboard) = &mut self.clipboard else {
+            return Some(markdown);
+        };
+        let html = self.document.get_html_in_range(start, end);
+        match clipboard.set_html(html, Some(markdown.clone())) {
+            Ok(()) => None,
+            Err(_) => Some(markdown),
+        }
+    }
+
     fn reset_blink(&mut self, cx: &mut Cx) {
         self.animator_play(cx, ids!(blink.on));
         cx.stop_timer(self.blink_timer);
         self.blink_timer = cx.start_timeout(0.5);
     }
 
+    // Mots au sens Unicode : espaces et ponctuation sont sautés
     fn find_prev_word(&self) -> usize {
-        if self.cursor_char == 0 {
-            return 0;
+        self.document.blocks[self.cursor_block].prev_word_start(self.cursor_char)
+    }
+
+    fn find_next_word(&self) -> usize {
+        self.document.blocks[self.cursor_block].next_word_end(self.cursor_char)
+    }
+
+    fn cursor_state(&self) -> CursorState {
+        CursorState {
+            cursor: (self.cursor_block, self.cursor_char),
+            anchor: self.selection_anchor,
         }
-        let block = &self.document.blocks[self.cursor_block];
-        let text = block.full_text();
+    }
 
-        // Skip whitespace backwards
-        let mut i = self.cursor_char;
-        while i > 0 {
-            if let Some(c) = text.chars().nth(i - 1) {
-                 if !c.is_whitespace() {
-                     break;
-                 }
-            }
-            i -= 1;
+    // Capture les blocs first..=last avant une modification (voir end_edit)
+    fn begin_edit(&mut self, kind: EditKind, first: usize, last: usize) {
+        let cursor = self.cursor_state();
+        self.history.begin(&self.document, kind, first, last, cursor);
+    }
+
+    fn end_edit(&mut self) {
+        let cursor = self.cursor_state();
+        if self.history.end(&self.document, cursor) {
+            self.document.mark_modified();
         }
+    }
 
-        // Skip non-whitespace backwards
-        while i > 0 {
-             if let Some(c) = text.chars().nth(i - 1) {
-                 if c.is_whitespace() {
-                     break;
-                 }
-             }
-             i -= 1;
+    // Blocs touchés par Backspace / Delete à la position courante
+    fn deletion_edit(&self, forward: bool, ctrl: bool) -> (EditKind, usize, usize) {
+        let block = self.cursor_block;
+        if ctrl {
+            return (EditKind::Other, block, block);
+        }
+        if let Some((start, end)) = self.get_selection_range() {
+            if start != end {
+                return (EditKind::Other, start.0, end.0);
+            }
+        }
+        let len = self.document.blocks[block].text_len();
+        if forward && self.cursor_char >= len {
+            (EditKind::Other, block, block + 1)
+        } else if !forward && self.cursor_char == 0 {
+            (EditKind::Other, block.saturating_sub(1), block)
+        } else {
+            (EditKind::Deletion, block, block)
         }
-        i
     }
 
-    fn find_next_word(&self) -> usize {
-        let block = &self.document.blocks[self.cursor_block];
-        let text = block.full_text();
-        let len = block.text_len();
-        let mut i = self.cursor_char;
+    // Tableau : Tab / Maj+Tab et Entrée passent d'une cellule à l'autre (une ligne est
+    // ajoutée en fin de tableau), Ctrl+Entrée ajoute une ligne, Ctrl+Maj+Entrée une colonne,
+    // Ctrl+Maj+Retour arrière et Ctrl+Maj+Suppr retirent la ligne ou la colonne courante.
+    fn handle_table_key(&mut self, cx: &mut Cx, ke: &KeyEvent) -> bool {
+        let block_idx = self.cursor_block;
+        let block = &self.document.blocks[block_idx];
+        let Some((row, col)) = block.table_cell_at(self.cursor_char) else {
+            return false;
+        };
+        let cells = block.table_cells();
+        let (rows, columns) = (cells.len(), cells[0].len());
+        let shift = ke.modifiers.shift;
+        let ctrl = ke.modifiers.control || ke.modifiers.logo;
+
+        let handled = match ke.key_code {
+            KeyCode::Tab | KeyCode::ReturnKey => true,
+            KeyCode::ArrowUp => !shift && row > 0,
+            KeyCode::ArrowDown => !shift && row + 1 < rows,
+            KeyCode::Backspace | KeyCode::Delete => ctrl && shift,
+            _ => false,
+        };
+        if !handled {
+            return false;
+        }
 
-        // Skip whitespace forwards
-        while i < len {
-            if let Some(c) = text.chars().nth(i) {
-                if !c.is_whitespace() {
-                    break;
+        self.history.seal();
+        self.begin_edit(EditKind::Other, block_idx, block_idx);
+        let mut target = (row, col);
+        match ke.key_code {
+            KeyCode::Tab if shift => {
+                if col > 0 {
+                    target = (row, col - 1);
+                } else if row > 0 {
+                    target = (row - 1, columns - 1);
+                }
+            }
+            KeyCode::Tab if col + 1 < columns => target = (row, col + 1),
+            KeyCode::Tab => target = (row + 1, 0),
+            KeyCode::ReturnKey if ctrl && shift => {
+                self.document.insert_table_column(block_idx, col + 1);
+                target = (row, col + 1);
+            }
+            KeyCode::ReturnKey if ctrl => {
+                self.document.insert_table_row(block_idx, row + 1);
+                target = (row + 1, col);
+            }
+            KeyCode::ReturnKey => target = (row + 1, col),
+            KeyCode::ArrowUp => target = (row - 1, col),
+            KeyCode::ArrowDown => target = (row + 1, col),
+            KeyCode::Backspace => {
+                if self.document.remove_table_row(block_idx, row) {
+                    target = (row.min(rows - 2), col);
+                }
+            }
+            KeyCode::Delete => {
+                if self.document.remove_table_column(block_idx, col) {
+                    target = (row, col.min(columns - 2));
                 }
             }
-             i += 1;
+            _ => {}
+        }
+        if target.0 == rows {
+            self.document.insert_table_row(block_idx, rows);
         }
 
-        // Skip non-whitespace forwards
-        while i < len {
-             if let Some(c) = text.chars().nth(i) {
-                if c.is_whitespace() {
-                    break;
+        let cells = self.document.blocks[block_idx].table_cells();
+        let row = target.0.min(cells.len() - 1);
+        self.cursor_char = cells[row][target.1.min(cells[row].len() - 1)].end;
+        self.selection_anchor = None;
+        self.end_edit();
+        self.history.seal();
+        self.invalidate_layout_from(block_idx);
+        self.redraw(cx);
+        true
+    }
+
+    fn undo(&mut self, cx: &mut Cx) {
+        let step = self.history.undo(&mut self.document);
+        self.restore_history_step(cx, step);
+    }
+
+    fn redo(&mut self, cx: &mut Cx) {
+        let step = self.history.redo(&mut self.document);
+        self.restore_history_step(cx, step);
+    }
+
+    fn restore_history_step(&mut self, cx: &mut Cx, step: Option<(CursorState, usize)>) {
+        let Some((state, first_block)) = step else {
+            return;
+        };
+        self.document.mark_modified();
+        let last_block = self.document.blocks.len().saturating_sub(1);
+        self.cursor_block = state.cursor.0.min(last_block);
+        self.cursor_char = state.cursor.1.min(self.document.blocks[self.cursor_block].text_len());
+        self.selection_anchor = state.anchor.filter(|anchor| anchor.0 <= last_block);
+        self.invalidate_layout_from(first_block);
+        self.redraw(cx);
+    }
+
+    // Ctrl+K : le lien sous le curseur, sinon la sélection (sur un seul bloc)
+    fn request_link_edit(&mut self, cx: &mut Cx, scope: &mut Scope) {
+        let (block, start, end) = match self.get_selection_range() {
+            Some((start, end)) if start != end => {
+                if start.0 != end.0 {
+                    return;
                 }
+                (start.0, start.1, end.1)
+            }
+            _ => (self.cursor_block, self.cursor_char, self.cursor_char),
+        };
+        let (start, end, url) = match self.document.link_at(block, start) {
+            Some((link_start, link_end, url))
+                if start == end || (link_start <= start && end <= link_end) =>
+            {
+                (link_start, link_end, url)
+            }
+            _ => (start, end, String::new()),
+        };
+
+        let text = self.document.get_text_in_range((block, start), (block, end));
+        self.link_edit = Some((block, start, end));
+        cx.widget_action(
+            self.widget_uid(),
+            &scope.path,
+            EditorAction::EditLink { text, url },
+        );
+    }
+
+    /// Applies the link dialog result to the range picked by Ctrl+K. `None` removes the link.
+    pub fn apply_link_edit(&mut self, cx: &mut Cx, text: &str, url: Option<&str>) {
+        let Some((block, start, mut end)) = self.link_edit.take() else {
+            return;
+        };
+        if block >= self.document.blocks.len() {
+            return;
+        }
+
+        self.begin_edit(EditKind::Other, block, block);
+        let current = self.document.get_text_in_range((block, start), (block, end));
+        if !text.is_empty() && text != current {
+            self.document.delete_range((block, start), (block, end));
+            end = start + self.document.insert_text_at(block, start, text);
+        }
+        self.document
+            .set_link(block, start, end, url.filter(|url| !url.is_empty()));
+        self.cursor_block = block;
+        self.cursor_char = end;
+        self.selection_anchor = None;
+        self.end_edit();
+
+        self.invalidate_layout_from(block);
+        self.redraw(cx);
+    }
+
+    // Liens relatifs vers un .md : ouverts dans ndown, le reste par le système
+    fn open_link(&mut self, cx: &mut Cx, url: &str) {
+        let is_external = url.contains("://") || url.starts_with("mailto:");
+        if is_external {
+            if let Err(e) = open_external(url) {
+                makepad_widgets::log!("Failed to open {}: {}", url, e);
             }
-            i += 1;
+            return;
+        }
+
+        let path = url.split('#').next().unwrap_or(url);
+        if path.is_empty() {
+            return;
+        }
+        let base = self
+            .current_file
+            .as_deref()
+            .and_then(|file| Path::new(file).parent())
+            .unwrap_or(Path::new(""));
+        let target = base.join(path).to_string_lossy().into_owned();
+
+        if path.ends_with(".md") {
+            self.open_file(cx, target);
+        } else if let Err(e) = open_external(&target) {
+            makepad_widgets::log!("Failed to open {}: {}", target, e);
+        }
+    }
+
+    pub fn file_moved(&mut self, from: &Path, to: &Path) -> Option<String> {
+        for tab in self.tabs.iter_mut().flatten() {
+            let moved = tab
+                .current_file
+                .as_deref()
+                .and_then(|file| moved_path(file, from, to));
+            if let Some(moved) = moved {
+                tab.watcher = Some(watch_file(&moved));
+                tab.current_file = Some(moved);
+            }
+        }
+        let moved = moved_path(self.current_file.as_ref()?, from, to)?;
+        self.current_file = Some(moved.clone());
+        self.watch_current_file();
+        Some(moved)
+    }
+
+    /// Moves the cursor to the start of a block and scrolls it to the top of the view.
+    pub fn scroll_to_block(&mut self, cx: &mut Cx, block_idx: usize) {
+        if block_idx >= self.document.blocks.len() {
+            return;
+        }
+        self.history.seal();
+        self.cursor_block = block_idx;
+        self.cursor_char = 0;
+        self.selection_anchor = None;
+        match self.block_y_offsets.get(block_idx) {
+            Some(&y) => {
+                self.scroll_bars.set_scroll_pos(cx, dvec2(0.0, y));
+            }
+            None => self.pending_scroll = Some(block_idx),
+        }
+        self.redraw(cx);
+    }
+
+    // Prévient le panneau de structure si les titres ou le titre courant ont changé
+    fn update_outline(&mut self) {
+        let outline = self.document.outline();
+        let current = outline
+            .iter()
+            .rposition(|entry| entry.block_idx <= self.cursor_block);
+        if outline != self.outline || current != self.outline_current {
+            self.outline = outline.clone();
+            self.outline_current = current;
+            Cx::post_action(EditorAction::OutlineChanged {
+                entries: outline,
+                current,
+            });
         }
-        i
     }
 
     fn invalidate_layout(&mut self) {
@@ -256,69 +801,344 @@ impl EditorArea {
     pub fn load_file_async(&mut self, _cx: &mut Cx, filename: String) {
         let filename_clone = filename.clone();
         self.current_file = Some(filename.clone());
+        self.own_writes = OwnWrites::default();
 
         TOKIO_RUNTIME.spawn(async move {
-            use std::io::BufRead;
-            if let Ok(file) = std::fs::File::open(&filename_clone) {
-                let reader = std::io::BufReader::new(file);
-                let mut new_blocks = Vec::with_capacity(1024);
-                let mut id_gen = 1000;
-
-                for line_res in reader.lines() {
-                    if let Ok(line) = line_res {
-                        let ty = if line.starts_with("# ") {
-                            BlockType::Heading1
-                        } else if line.starts_with("## ") {
-                            BlockType::Heading2
-                        } else if line.starts_with("### ") {
-                            BlockType::Heading3
-                        } else if line.starts_with("#### ") {
-                            BlockType::Heading4
-                        } else if line.starts_with("##### ") {
-                            BlockType::Heading5
-                        } else if line.starts_with("> ") {
-                            BlockType::Quote
-                        } else {
-                            BlockType::Paragraph
-                        };
+            match std::fs::read_to_string(&filename_clone) {
+                Ok(content) => {
+                    let doc = Document::from_markdown(&content);
+                    Cx::post_action(EditorAction::AsyncFileLoaded(
+                        filename_clone.clone(),
+                        doc,
+                        content,
+                    ));
+                }
+                Err(_) => {
+                    Cx::post_action(EditorAction::AsyncError(format!("Failed to open {}", filename_clone)));
+                }
+            }
+            if journal::is_newer_than_file(&filename_clone) {
+                Cx::post_action(EditorAction::AsyncRecoveryAvailable(filename_clone));
+            }
+        });
+    }
 
-                        let text = if matches!(ty, BlockType::Heading1) {
-                            &line[2..]
-                        } else if matches!(ty, BlockType::Heading2) {
-                            &line[3..]
-                        } else if matches!(ty, BlockType::Heading3) {
-                            &line[4..]
-                        } else if matches!(ty, BlockType::Heading4) {
-                            &line[5..]
-                        } else if matches!(ty, BlockType::Heading5) {
-                            &line[6..]
-                        } else if matches!(ty, BlockType::Quote) {
-                            &line[2..]
-                        } else {
-                            &line
-                        };
+    // Document vide, sans fichier associé
+    pub fn new_document(&mut self, cx: &mut Cx) {
+        self.set_document(Document::from_blocks(vec![Block::new(
+            1,
+            BlockType::Paragraph,
+            "",
+        )]));
+        self.current_file = None;
+        self.watcher = None;
+        self.disk_base.clear();
+        self.disk_conflict = None;
+        self.own_writes = OwnWrites::default();
+        self.redraw(cx);
+    }
 
-                        let block = Block::new(id_gen, ty, text);
-                        id_gen += 1;
-                        new_blocks.push(block);
-                    }
+    // Relance la surveillance si le fichier ouvert a changé
+    fn watch_current_file(&mut self) {
+        let Some(filename) = self.current_file.clone() else {
+            self.watcher = None;
+            return;
+        };
+        if self.watcher.as_ref().is_some_and(|w| w.path() == filename) {
+            return;
+        }
+        self.watcher = Some(watch_file(&filename));
+    }
+
+    fn read_disk_content(&self, filename: String) {
+        TOKIO_RUNTIME.spawn(async move {
+            match std::fs::read_to_string(&filename) {
+                Ok(content) => Cx::post_action(EditorAction::AsyncDiskContent(filename, content)),
+                // Supprimé ou en cours de remplacement : le document reste tel quel
+                Err(e) => makepad_widgets::log!("Failed to read {}: {}", filename, e),
+            }
+        });
+    }
+
+    fn handle_disk_content(&mut self, cx: &mut Cx, scope: &Scope, path: String, content: String) {
+        if self.current_file.as_ref() != Some(&path) {
+            if let Some(tab) = self.background_tab(&path) {
+                tab.disk_changed(content);
+            }
+            return;
+        }
+        if content == self.disk_base {
+            return;
+        }
+        // Notre propre enregistrement (même si sa fin n'est pas encore signalée), ou la
+        // même modification des deux côtés
+        if self.own_writes.is_own(&content) || content == self.document.to_markdown() {
+            self.disk_base = content;
+            return;
+        }
+        if !self.document.is_modified() {
+            self.replace_document(Document::from_markdown(&content));
+            self.disk_base = content;
+            self.redraw(cx);
+            return;
+        }
+        self.disk_conflict = Some(content);
+        cx.widget_action(
+            self.widget_uid(),
+            &scope.path,
+            EditorAction::ExternalConflict(path),
+        );
+    }
+
+    // Remplace le document en gardant le curseur là où il était, si possible
+    fn replace_document(&mut self, doc: Document) {
+        let (block, char_idx) = (self.cursor_block, self.cursor_char);
+        self.set_document(doc);
+        (self.cursor_block, self.cursor_char) = clamp_cursor(&self.document, block, char_idx);
+    }
+
+    // Onglet non affiché ouvert sur ce fichier
+    fn background_tab(&mut self, path: &str) -> Option<&mut DocTab> {
+        self.tabs
+            .iter_mut()
+            .flatten()
+            .find(|tab| tab.current_file.as_deref() == Some(path))
+    }
+
+    fn tab_infos(&self) -> Vec<TabInfo> {
+        self.tabs
+            .iter()
+            .map(|tab| match tab {
+                Some(tab) => tab.info(),
+                None => TabInfo::new(
+                    self.current_file.as_deref(),
+                    &self.document,
+                    self.disk_conflict.is_some(),
+                ),
+            })
+            .collect()
+    }
+
+    // Sort l'état du document affiché pour le ranger dans son onglet
+    fn stash_active_tab(&mut self) -> DocTab {
+        DocTab {
+            document: std::mem::take(&mut self.document),
+            history: std::mem::take(&mut self.history),
+            current_file: self.current_file.take(),
+            cursor_block: self.cursor_block,
+            cursor_char: self.cursor_char,
+            selection_anchor: self.selection_anchor.take(),
+            scroll_y: self.scroll_bars.get_scroll_pos().y,
+            watcher: self.watcher.take(),
+            disk_base: std::mem::take(&mut self.disk_base),
+            disk_conflict: self.disk_conflict.take(),
+            own_writes: std::mem::take(&mut self.own_writes),
+        }
+    }
+
+    fn show_tab(&mut self, tab: DocTab) {
+        self.document = tab.document;
+        self.history = tab.history;
+        self.current_file = tab.current_file;
+        self.cursor_block = tab.cursor_block;
+        self.cursor_char = tab.cursor_char;
+        self.selection_anchor = tab.selection_anchor;
+        self.watcher = tab.watcher;
+        self.disk_base = tab.disk_base;
+        self.disk_conflict = tab.disk_conflict;
+        self.own_writes = tab.own_writes;
+        self.restored_scroll = Some(tab.scroll_y);
+        self.pending_scroll = None;
+        self.link_edit = None;
+        self.is_dragging = false;
+        self.invalidate_layout();
+    }
+
+    /// Shows the tab at `idx`; the one it replaces keeps its cursor, selection,
+    /// history and scroll position.
+    pub fn select_tab(&mut self, cx: &mut Cx, idx: usize) {
+        if idx == self.active_tab {
+            return;
+        }
+        let Some(tab) = self.tabs.get_mut(idx).and_then(Option::take) else {
+            return;
+        };
+        self.history.seal();
+        self.tabs[self.active_tab] = Some(self.stash_active_tab());
+        self.active_tab = idx;
+        self.show_tab(tab);
+        self.redraw(cx);
+    }
+
+    pub fn open_file(&mut self, cx: &mut Cx, filename: String) {
+        if self.current_file.as_ref() == Some(&filename) {
+            return;
+        }
+        let open_in = self.tabs.iter().position(|tab| {
+            tab.as_ref()
+                .is_some_and(|tab| tab.current_file.as_ref() == Some(&filename))
+        });
+        if let Some(idx) = open_in {
+            self.select_tab(cx, idx);
+            return;
+        }
+        // Un document vide et intact laisse sa place au lieu d'ouvrir un onglet
+        if self.current_file.is_some() || self.document.is_modified() {
+            self.history.seal();
+            self.tabs[self.active_tab] = Some(self.stash_active_tab());
+            self.tabs.push(None);
+            self.active_tab = self.tabs.len() - 1;
+            self.show_tab(DocTab::blank());
+        }
+        self.load_file_async(cx, filename);
+        self.redraw(cx);
+    }
+
+    /// Closes a tab without saving it. The last tab is replaced by an empty document.
+    pub fn close_tab(&mut self, cx: &mut Cx, idx: usize) {
+        if idx >= self.tabs.len() {
+            return;
+        }
+        if self.tabs.len() == 1 {
+            self.show_tab(DocTab::blank());
+        } else {
+            self.tabs.remove(idx);
+            if idx < self.active_tab {
+                self.active_tab -= 1;
+            } else if idx == self.active_tab {
+                self.active_tab = idx.min(self.tabs.len() - 1);
+                if let Some(tab) = self.tabs[self.active_tab].take() {
+                    self.show_tab(tab);
                 }
+            }
+        }
+        self.redraw(cx);
+    }
 
-                if new_blocks.is_empty() {
-                    new_blocks.push(Block::new(id_gen, BlockType::Paragraph, ""));
+    pub fn reload_from_disk(&mut self, cx: &mut Cx) {
+        let Some(theirs) = self.disk_conflict.take() else {
+            return;
+        };
+        self.replace_document(Document::from_markdown(&theirs));
+        self.disk_base = theirs;
+        self.discard_journal();
+        self.redraw(cx);
+    }
+
+    pub fn keep_mine(&mut self) {
+        if let Some(theirs) = self.disk_conflict.take() {
+            self.disk_base = theirs;
+        }
+    }
+
+    pub fn merge_with_disk(&mut self, cx: &mut Cx) -> usize {
+        let Some(theirs) = self.disk_conflict.take() else {
+            return 0;
+        };
+        let merged = merge3(&self.disk_base, &self.document.to_markdown(), &theirs);
+        self.replace_document(Document::from_markdown(&merged.text));
+        // Le résultat de la fusion n'est pas encore dans le fichier
+        self.document.mark_modified();
+        self.disk_base = theirs;
+        self.redraw(cx);
+        merged.conflicts
+    }
+
+    pub fn save(&mut self) {
+        // Document sans fichier (--new, nouvel onglet) : une nouvelle note au nom libre
+        // dans le dossier de travail, jamais un fichier existant
+        if self.current_file.is_none() {
+            let root = std::env::current_dir().unwrap_or_default();
+            match ops::create_note(&root) {
+                Ok(path) => {
+                    let path = path.strip_prefix(&root).unwrap_or(&path);
+                    self.current_file = Some(path.to_string_lossy().into_owned());
+                }
+                Err(e) => {
+                    makepad_widgets::log!("Save Error: {}", e);
+                    Cx::post_action(EditorAction::SaveFailed(e.to_string()));
+                    return;
                 }
+            }
+        }
+        let Some(filename) = self.current_file.clone() else {
+            return;
+        };
+        let doc_snapshot = self.document.snapshot();
+        let revision = self.document.revision();
+        let backups = self.backups;
+        let own_writes = self.own_writes.clone();
 
-                Cx::post_action(EditorAction::AsyncFileLoaded(filename_clone, new_blocks));
-            } else {
-                Cx::post_action(EditorAction::AsyncError(format!("Failed to open {}", filename_clone)));
+        TOKIO_RUNTIME.spawn(async move {
+            let root = std::env::current_dir().unwrap_or_default();
+            if let Err(e) = backup::backup_before_save(&root, &filename, backups) {
+                makepad_widgets::log!("Backup Error: {}", e);
+            }
+            let content = doc_snapshot.to_markdown();
+            own_writes.record(&content);
+            match doc_snapshot.save_to_file(&filename) {
+                Ok(_) => {
+                    makepad_widgets::log!("Async Save: Document saved to {}", filename);
+                    journal::remove(&filename);
+                    Cx::post_action(EditorAction::AsyncFileSaved(filename, revision, content));
+                }
+                Err(e) => {
+                    makepad_widgets::log!("Async Save Error: {}", e);
+                    Cx::post_action(EditorAction::SaveFailed(e.to_string()));
+                }
             }
         });
     }
 
+    fn save_or_journal(&mut self) {
+        if !self.document.is_modified() {
+            return;
+        }
+        // Document sans fichier : pas de journal, qui serait pris pour celui d'un autre
+        let Some(filename) = self.current_file.clone() else {
+            return;
+        };
+        if self.autosave {
+            self.save();
+            return;
+        }
+        let doc_snapshot = self.document.snapshot();
+        TOKIO_RUNTIME.spawn(async move {
+            if let Err(e) = journal::write(&doc_snapshot, &filename) {
+                makepad_widgets::log!("Journal Error: {}", e);
+            }
+        });
+    }
+
+    /// Replaces the document with the recovery journal of the open file.
+    pub fn restore_journal(&self) {
+        let Some(filename) = self.current_file.clone() else {
+            return;
+        };
+        TOKIO_RUNTIME.spawn(async move {
+            match journal::read(&filename) {
+                Ok(doc) => Cx::post_action(EditorAction::AsyncJournalLoaded(filename, doc)),
+                Err(e) => Cx::post_action(EditorAction::AsyncError(format!(
+                    "Failed to restore {}: {}",
+                    filename, e
+                ))),
+            }
+        });
+    }
+
+    pub fn discard_journal(&self) {
+        let Some(filename) = self.current_file.clone() else {
+            return;
+        };
+        TOKIO_RUNTIME.spawn(async move { journal::remove(&filename) });
+    }
+
     pub fn set_document(&mut self, doc: Document) {
         self.document = doc;
         self.cursor_block = 0;
         self.cursor_char = 0;
+        self.selection_anchor = None;
+        self.history.clear();
         self.invalidate_layout();
     }
 }
@@ -333,6 +1153,15 @@ impl Widget for EditorArea {
             }
             self.blink_timer = cx.start_timeout(0.5);
         }
+        // Quelques secondes après la dernière modification : autosauvegarde ou journal
+        if self.document.revision() != self.timed_revision {
+            self.timed_revision = self.document.revision();
+            cx.stop_timer(self.autosave_timer);
+            self.autosave_timer = cx.start_timeout(AUTOSAVE_DELAY);
+        }
+        if self.autosave_timer.is_event(event).is_some() {
+            self.save_or_journal();
+        }
         self.animator_handle_event(cx, event);
         self.scroll_bars.handle_event(cx, event, scope);
 
@@ -343,9 +1172,13 @@ impl Widget for EditorArea {
                 self.is_dragging = true;
                 self.deferred_finger_tap = Some(fe.abs);
                 self.selection_anchor = None;
+                self.open_link_on_tap = fe.modifiers.control || fe.modifiers.logo;
+                self.toggle_task_on_tap = true;
+                self.history.seal();
                 self.redraw(cx);
             }
             Hit::FingerMove(fe) => {
+                self.toggle_task_on_tap = false;
                 if self.is_dragging {
                     self.deferred_finger_tap = Some(fe.abs);
                     self.redraw(cx);
@@ -362,10 +1195,10 @@ impl Widget for EditorArea {
                 self.redraw(cx);
             }
 
+            // Réponse vide quand le presse-papiers a déjà reçu le Markdown et le HTML
             Hit::TextCopy(e) => {
                 if let Some((start, end)) = self.get_selection_range() {
-                    let text = self.document.get_text_in_range(start, end);
-                    *e.response.borrow_mut() = Some(text);
+                    *e.response.borrow_mut() = self.copy_selection(start, end);
                 } else {
                     *e.response.borrow_mut() = None;
                 }
@@ -373,12 +1206,13 @@ impl Widget for EditorArea {
 
             Hit::TextCut(e) => {
                 if let Some((start, end)) = self.get_selection_range() {
-                    let text = self.document.get_text_in_range(start, end);
-                    *e.response.borrow_mut() = Some(text);
+                    *e.response.borrow_mut() = self.copy_selection(start, end);
+                    self.begin_edit(EditKind::Other, start.0, end.0);
                     self.document.delete_range(start, end);
                     self.cursor_block = start.0;
                     self.cursor_char = start.1;
                     self.selection_anchor = None;
+                    self.end_edit();
                     self.invalidate_layout_from(start.0);
                     self.redraw(cx);
                 } else {
@@ -392,21 +1226,33 @@ impl Widget for EditorArea {
                 let ctrl = ke.modifiers.control || ke.modifiers.logo;
 
                 if ctrl && ke.key_code == KeyCode::KeyS {
-                    let doc_snapshot = self.document.snapshot();
-                    let filename = self.current_file.clone().unwrap_or_else(|| "story.md".to_string());
-
-                    TOKIO_RUNTIME.spawn(async move {
-                        match doc_snapshot.save_to_file(&filename) {
-                            Ok(_) => {
-                                makepad_widgets::log!("Async Save: Document saved to {}", filename);
-                                Cx::post_action(EditorAction::AsyncFileSaved(filename));
-                            },
-                            Err(e) => {
-                                makepad_widgets::log!("Async Save Error: {}", e);
-                                Cx::post_action(EditorAction::AsyncError(e.to_string()));
-                            }
-                        }
-                    });
+                    self.save();
+                    return;
+                }
+
+                // Ctrl+Tab / Ctrl+Shift+Tab : onglet suivant / précédent
+                if ctrl && ke.key_code == KeyCode::Tab {
+                    let count = self.tabs.len();
+                    let next = if shift {
+                        (self.active_tab + count - 1) % count
+                    } else {
+                        (self.active_tab + 1) % count
+                    };
+                    self.select_tab(cx, next);
+                    return;
+                }
+
+                if ctrl && ke.key_code == KeyCode::KeyZ {
+                    if shift {
+                        self.redo(cx);
+                    } else {
+                        self.undo(cx);
+                    }
+                    return;
+                }
+
+                if ctrl && ke.key_code == KeyCode::KeyK {
+                    self.request_link_edit(cx, scope);
                     return;
                 }
 
@@ -419,28 +1265,24 @@ impl Widget for EditorArea {
 
                 if ctrl && ke.key_code == KeyCode::KeyC {
                     if let Some((start, end)) = self.get_selection_range() {
-                        let text = self.document.get_text_in_range(start, end);
-                        if let Some(clipboard) = &mut self.clipboard {
-                            let _ = clipboard.set_text(text);
-                        } else {
+                        if let Some(text) = self.copy_selection(start, end) {
                             cx.copy_to_clipboard(&text);
                         }
                     }
                     return;
                 }
 
-                if ctrl && ke.key_code == KeyCode::KeyX {
+                if ctrl && !shift && ke.key_code == KeyCode::KeyX {
                     if let Some((start, end)) = self.get_selection_range() {
-                        let text = self.document.get_text_in_range(start, end);
-                        if let Some(clipboard) = &mut self.clipboard {
-                            let _ = clipboard.set_text(text);
-                        } else {
+                        if let Some(text) = self.copy_selection(start, end) {
                             cx.copy_to_clipboard(&text);
                         }
+                        self.begin_edit(EditKind::Other, start.0, end.0);
                         self.document.delete_range(start, end);
                         self.cursor_block = start.0;
                         self.cursor_char = start.1;
                         self.selection_anchor = None;
+                        self.end_edit();
                         self.invalidate_layout_from(start.0);
                         self.redraw(cx);
                     }
@@ -457,6 +1299,11 @@ impl Widget for EditorArea {
                     if let Some(text) = text_opt {
                         if !text.is_empty() {
                             let mut start_block = self.cursor_block;
+                            let (first, last) = self
+                                .get_selection_range()
+                                .map(|(start, end)| (start.0, end.0))
+                                .unwrap_or((self.cursor_block, self.cursor_block));
+                            self.begin_edit(EditKind::Other, first, last);
                             if let Some((start, end)) = self.get_selection_range() {
                                 self.document.delete_range(start, end);
                                 self.cursor_block = start.0;
@@ -467,49 +1314,16 @@ impl Widget for EditorArea {
 
                             // Remove carriage returns
                             let text = text.replace("\r\n", "\n").replace('\r', "\n");
+                            // Ctrl+Shift+V colle en texte brut, ligne par ligne
+                            let position = (self.cursor_block, self.cursor_char);
+                            (self.cursor_block, self.cursor_char) =
+                                if !shift && parser::looks_like_markdown(&text) {
+                                    self.document.insert_markdown(position.0, position.1, &text)
+                                } else {
+                                    self.document.insert_lines(position.0, position.1, &text)
+                                };
 
-                            let mut parts = text.split('\n');
-                            if let Some(first) = parts.next() {
-                                let added = self.document.insert_text_at(
-                                    self.cursor_block,
-                                    self.cursor_char,
-                                    first,
-                                );
-                                self.cursor_char += added;
-                            }
-
-                            for part in parts {
-                                // Split block at cursor
-                                let current_block = &mut self.document.blocks[self.cursor_block];
-                                let rest_text: String =
-                                    current_block.text.chars().skip(self.cursor_char).collect();
-                                let rest_len = rest_text.chars().count();
-
-                                // Truncate current block
-                                let current_len = current_block.text_len();
-                                for _ in 0..rest_len {
-                                    self.document
-                                        .remove_char_at(self.cursor_block, current_len - rest_len);
-                                }
-
-                                // Create new block with rest
-                                let new_block = Block::new(
-                                    self.document.generate_id(),
-                                    BlockType::Paragraph,
-                                    &rest_text,
-                                );
-
-                                self.document
-                                    .blocks
-                                    .insert(self.cursor_block + 1, new_block);
-                                self.cursor_block += 1;
-                                self.cursor_char = 0;
-
-                                let added =
-                                    self.document.insert_text_at(self.cursor_block, 0, part);
-                                self.cursor_char += added;
-                            }
-
+                            self.end_edit();
                             self.invalidate_layout_from(start_block);
                             self.redraw(cx);
                         }
@@ -517,11 +1331,21 @@ impl Widget for EditorArea {
                     return;
                 }
 
-                if ctrl && (ke.key_code == KeyCode::KeyB || ke.key_code == KeyCode::KeyI) {
-                    let style_type = if ke.key_code == KeyCode::KeyB { 0 } else { 1 }; // 0=bold, 1=italic
-                    
-                    makepad_widgets::log!("Ctrl+{} pressed, selection_anchor: {:?}, cursor: ({}, {})", 
-                        if ke.key_code == KeyCode::KeyB { "B" } else { "I" },
+                // Ctrl+B, Ctrl+I, Ctrl+Shift+X (barré), Ctrl+Shift+H (surligné),
+                // Ctrl+Shift+= (exposant), Ctrl+= (indice)
+                let inline_style = match ke.key_code {
+                    _ if !ctrl => None,
+                    KeyCode::KeyB if !shift => Some(InlineStyle::Bold),
+                    KeyCode::KeyI if !shift => Some(InlineStyle::Italic),
+                    KeyCode::KeyX if shift => Some(InlineStyle::Strikethrough),
+                    KeyCode::KeyH if shift => Some(InlineStyle::Highlight),
+                    KeyCode::Equals if shift => Some(InlineStyle::Superscript),
+                    KeyCode::Equals => Some(InlineStyle::Subscript),
+                    _ => None,
+                };
+                if let Some(style) = inline_style {
+                    makepad_widgets::log!("Toggle {:?}, selection_anchor: {:?}, cursor: ({}, {})",
+                        style,
                         self.selection_anchor,
                         self.cursor_block,
                         self.cursor_char
@@ -533,18 +1357,17 @@ impl Widget for EditorArea {
                         makepad_widgets::log!("Selection range: ({}, {}) to ({}, {})", start_blk, start_char, end_blk, end_char);
                         if start_blk == end_blk {
                             // Use toggle_formatting instead of inserting markers
-                            self.document.toggle_formatting(start_blk, start_char, end_char, style_type);
+                            self.begin_edit(EditKind::Other, start_blk, start_blk);
+                            self.document.toggle_formatting(start_blk, start_char, end_char, style);
                             
                             let result_text = self.document.blocks[start_blk].full_text();
-                            makepad_widgets::log!("Ctrl+{}: toggled {} on selection", 
-                                if ke.key_code == KeyCode::KeyB { "B" } else { "I" },
-                                if style_type == 0 { "bold" } else { "italic" }
-                            );
+                            makepad_widgets::log!("Toggled {:?} on selection", style);
                             makepad_widgets::log!("Block text after: '{}'", result_text.escape_debug());
                             makepad_widgets::log!("Block styles: {:?}", self.document.blocks[start_blk].styles);
                             
                             // Clear selection
                             self.selection_anchor = None;
+                            self.end_edit();
                             // Invalidate layout to refresh display
                             self.invalidate_layout_from(start_blk);
                             // Ignore the TextInput event that follows
@@ -553,16 +1376,18 @@ impl Widget for EditorArea {
                     } else {
                         makepad_widgets::log!("No selection detected");
                         // No selection: insert markers and position cursor between them
-                        let marker = if ke.key_code == KeyCode::KeyB { "**" } else { "*" };
+                        let marker = style.marker();
                         let insert_text = format!("{}{}", marker, marker);
+                        self.begin_edit(EditKind::Other, self.cursor_block, self.cursor_block);
                         self.document.insert_text_at(
                             self.cursor_block,
                             self.cursor_char,
                             &insert_text,
                         );
                         self.cursor_char += marker.len();
-                        makepad_widgets::log!("Ctrl+{}: Inserted '{}' (cursor between markers)", 
-                            if ke.key_code == KeyCode::KeyB { "B" } else { "I" },
+                        self.end_edit();
+                        makepad_widgets::log!("Toggle {:?}: Inserted '{}' (cursor between markers)",
+                            style,
                             insert_text
                         );
                         self.ignore_next_text_input = true;
@@ -587,45 +1412,70 @@ impl Widget for EditorArea {
                     }
                 }
 
+                if matches!(
+                    ke.key_code,
+                    KeyCode::ArrowUp | KeyCode::ArrowDown | KeyCode::ArrowLeft | KeyCode::ArrowRight
+                ) {
+                    self.history.seal();
+                }
+
+                if self.handle_table_key(cx, &ke) {
+                    return;
+                }
+
                 if ke.key_code == KeyCode::Tab {
-                    let current_ty = self.document.blocks[self.cursor_block].ty.clone();
-                    if current_ty == BlockType::ListItem || current_ty == BlockType::OrderedListItem
-                    {
+                    if self.document.blocks[self.cursor_block].is_list_item() {
+                        self.begin_edit(EditKind::Other, self.cursor_block, self.cursor_block);
+                        let block = &mut self.document.blocks[self.cursor_block];
                         if shift {
-                            if self.document.blocks[self.cursor_block].indent > 0 {
-                                self.document.blocks[self.cursor_block].indent -= 1;
+                            if block.indent > 0 {
+                                block.set_indent(block.indent - 1);
                                 self.invalidate_layout_from(self.cursor_block);
                             }
-                        } else if self.document.blocks[self.cursor_block].indent < 10 {
+                        } else if block.indent < 10 {
                             // Max indentation
-                            self.document.blocks[self.cursor_block].indent += 1;
+                            block.set_indent(block.indent + 1);
                             self.invalidate_layout_from(self.cursor_block);
                         }
+                        self.end_edit();
                     }
                     self.redraw(cx);
                     return; // Consommer l'événement Tab pour ne pas perdre le focus ou insérer de tab
                 }
 
+                let edit = match ke.key_code {
+                    KeyCode::ReturnKey => {
+                        Some((EditKind::Other, self.cursor_block, self.cursor_block))
+                    }
+                    KeyCode::Backspace => Some(self.deletion_edit(false, ctrl)),
+                    KeyCode::Delete => Some(self.deletion_edit(true, ctrl)),
+                    _ => None,
+                };
+                if let Some((kind, first, last)) = edit {
+                    self.begin_edit(kind, first, last);
+                }
+
                 match ke.key_code {
                     KeyCode::ArrowUp => {
                         if self.cursor_block > 0 {
                             self.cursor_block -= 1;
-                            let len = self.document.blocks[self.cursor_block].text_len();
-                            self.cursor_char = self.cursor_char.min(len);
+                            let block = &self.document.blocks[self.cursor_block];
+                            self.cursor_char = block.grapheme_floor(self.cursor_char);
                         }
                     }
                     KeyCode::ArrowDown => {
                         if self.cursor_block < self.document.blocks.len() - 1 {
                             self.cursor_block += 1;
-                            let len = self.document.blocks[self.cursor_block].text_len();
-                            self.cursor_char = self.cursor_char.min(len);
+                            let block = &self.document.blocks[self.cursor_block];
+                            self.cursor_char = block.grapheme_floor(self.cursor_char);
                         }
                     }
                     KeyCode::ArrowLeft => {
+                        let block = &self.document.blocks[self.cursor_block];
                         if ctrl {
                             self.cursor_char = self.find_prev_word();
                         } else if self.cursor_char > 0 {
-                            self.cursor_char -= 1;
+                            self.cursor_char = block.prev_grapheme(self.cursor_char);
                         } else if self.cursor_block > 0 {
                             self.cursor_block -= 1;
                             self.cursor_char = self.document.blocks[self.cursor_block].text_len();
@@ -636,7 +1486,8 @@ impl Widget for EditorArea {
                         if ctrl {
                             self.cursor_char = self.find_next_word();
                         } else if self.cursor_char < len {
-                            self.cursor_char += 1;
+                            let block = &self.document.blocks[self.cursor_block];
+                            self.cursor_char = block.next_grapheme(self.cursor_char);
                         } else if self.cursor_block < self.document.blocks.len() - 1 {
                             self.cursor_block += 1;
                             self.cursor_char = 0;
@@ -646,41 +1497,55 @@ impl Widget for EditorArea {
                         self.selection_anchor = None;
                         let current_ty = self.document.blocks[self.cursor_block].ty.clone();
                         let current_len = self.document.blocks[self.cursor_block].text_len();
+                        let is_list = self.document.blocks[self.cursor_block].is_list_item();
+
+                        // "```rust" + Return ouvre un bloc de code
+                        if self.document.try_convert_code_fence(self.cursor_block) {
+                            self.cursor_char = 0;
+                            self.end_edit();
+                            self.invalidate_layout_from(self.cursor_block);
+                            self.redraw(cx);
+                            return;
+                        }
+
+                        // "| a | b |" + Return crée un tableau
+                        if let Some(first_cell) = self.document.try_convert_table(self.cursor_block)
+                        {
+                            self.cursor_char = first_cell;
+                            self.end_edit();
+                            self.invalidate_layout_from(self.cursor_block);
+                            self.redraw(cx);
+                            return;
+                        }
 
                         // Shift + Return in CodeBlock inserts a newline
                         if current_ty == BlockType::CodeBlock && shift {
                             self.document.insert_text_at(self.cursor_block, self.cursor_char, "\n");
                             self.cursor_char += 1;
+                            self.end_edit();
                             self.invalidate_layout_from(self.cursor_block);
                             self.redraw(cx);
                             return;
                         }
 
                         // Exit list if empty item
-                        if (current_ty == BlockType::ListItem
-                            || current_ty == BlockType::OrderedListItem)
-                            && current_len == 0
-                        {
-                            if self.document.blocks[self.cursor_block].indent > 0 {
-                                self.document.blocks[self.cursor_block].indent -= 1;
+                        if is_list && current_len == 0 {
+                            let block = &mut self.document.blocks[self.cursor_block];
+                            if block.indent > 0 {
+                                block.set_indent(block.indent - 1);
                             } else {
-                                self.document.blocks[self.cursor_block].ty = BlockType::Paragraph;
+                                block.ty = BlockType::Paragraph;
+                                block.checked = false;
                             }
+                            self.end_edit();
                             self.invalidate_layout_from(self.cursor_block);
                             self.redraw(cx);
                             return;
                         }
 
-                        let (new_ty, new_indent) = if current_ty == BlockType::ListItem {
-                            (
-                                BlockType::ListItem,
-                                self.document.blocks[self.cursor_block].indent,
-                            )
-                        } else if current_ty == BlockType::OrderedListItem {
-                            (
-                                BlockType::OrderedListItem,
-                                self.document.blocks[self.cursor_block].indent,
-                            )
+                        // Une nouvelle tâche commence décochée
+                        let (new_ty, new_indent) = if is_list {
+                            (current_ty, self.document.blocks[self.cursor_block].indent)
                         } else {
                             // Default to Paragraph for new blocks (including after CodeBlock)
                             (BlockType::Paragraph, 0)
@@ -688,6 +1553,14 @@ impl Widget for EditorArea {
 
                         let mut new_block = Block::new(self.document.generate_id(), new_ty, "");
                         new_block.indent = new_indent;
+                        if new_block.is_list_item() {
+                            // Same bullet / delimiter as the item above, in the same quote
+                            let current = &self.document.blocks[self.cursor_block];
+                            new_block.syntax.marker = current.syntax.marker.clone();
+                            new_block.syntax.leading_spaces = current.syntax.leading_spaces;
+                            new_block.syntax.container_prefix =
+                                current.syntax.container_prefix.clone();
+                        }
 
                         self.document
                             .blocks
@@ -717,10 +1590,10 @@ impl Widget for EditorArea {
                                 self.invalidate_layout_from(self.cursor_block);
                             }
                         } else {
-                            let block = &mut self.document.blocks[self.cursor_block];
+                            let block = &self.document.blocks[self.cursor_block];
                             if self.cursor_char < block.text_len() {
                                 self.document
-                                    .remove_char_at(self.cursor_block, self.cursor_char);
+                                    .remove_grapheme_at(self.cursor_block, self.cursor_char);
                                 // Layout might change (wrapping)
                                 self.invalidate_layout_from(self.cursor_block);
                             } else if self.cursor_block < self.document.blocks.len() - 1
@@ -756,17 +1629,20 @@ impl Widget for EditorArea {
                             } else {
                                 self.selection_anchor = None;
                                 if self.cursor_char > 0 {
-                                    if self
-                                        .document
-                                        .remove_char_at(self.cursor_block, self.cursor_char - 1)
-                                    {
-                                        self.cursor_char -= 1;
+                                    let start = self.document.blocks[self.cursor_block]
+                                        .prev_grapheme(self.cursor_char);
+                                    let removed =
+                                        self.document.remove_grapheme_at(self.cursor_block, start);
+                                    if removed > 0 {
+                                        self.cursor_char = start;
                                         self.invalidate_layout_from(self.cursor_block);
                                     }
                                 } else if self.cursor_block > 0 {
                                     let current_type =
                                         self.document.blocks[self.cursor_block].ty.clone();
-                                    if current_type != BlockType::Paragraph {
+                                    if current_type != BlockType::Paragraph
+                                        && current_type != BlockType::Table
+                                    {
                                         self.document.blocks[self.cursor_block].ty =
                                             BlockType::Paragraph;
                                         self.invalidate_layout_from(self.cursor_block);
@@ -780,7 +1656,9 @@ impl Widget for EditorArea {
                                 } else {
                                     let current_type =
                                         self.document.blocks[self.cursor_block].ty.clone();
-                                    if current_type != BlockType::Paragraph {
+                                    if current_type != BlockType::Paragraph
+                                        && current_type != BlockType::Table
+                                    {
                                         self.document.blocks[self.cursor_block].ty =
                                             BlockType::Paragraph;
                                         self.invalidate_layout_from(self.cursor_block);
@@ -788,16 +1666,17 @@ impl Widget for EditorArea {
                                 }
                             }
                         } else if self.cursor_char > 0 {
-                            if self
-                                .document
-                                .remove_char_at(self.cursor_block, self.cursor_char - 1)
-                            {
-                                self.cursor_char -= 1;
+                            let start = self.document.blocks[self.cursor_block]
+                                .prev_grapheme(self.cursor_char);
+                            if self.document.remove_grapheme_at(self.cursor_block, start) > 0 {
+                                self.cursor_char = start;
                                 self.invalidate_layout_from(self.cursor_block);
                             }
                         } else if self.cursor_block > 0 {
                             let current_type = self.document.blocks[self.cursor_block].ty.clone();
-                            if current_type != BlockType::Paragraph {
+                            if current_type != BlockType::Paragraph
+                                && current_type != BlockType::Table
+                            {
                                 self.document.blocks[self.cursor_block].ty = BlockType::Paragraph;
                                 self.invalidate_layout_from(self.cursor_block);
                             } else if let Some(new_char_pos) =
@@ -809,7 +1688,9 @@ impl Widget for EditorArea {
                             }
                         } else {
                             let current_type = self.document.blocks[self.cursor_block].ty.clone();
-                            if current_type != BlockType::Paragraph {
+                            if current_type != BlockType::Paragraph
+                                && current_type != BlockType::Table
+                            {
                                 self.document.blocks[self.cursor_block].ty = BlockType::Paragraph;
                                 self.invalidate_layout_from(self.cursor_block);
                             }
@@ -817,6 +1698,9 @@ impl Widget for EditorArea {
                     }
                     _ => {}
                 }
+                if edit.is_some() {
+                    self.end_edit();
+                }
                 self.redraw(cx);
             }
             Hit::TextInput(te) => {
@@ -839,6 +1723,13 @@ impl Widget for EditorArea {
                     || te.input.contains('\t');
 
                 if !te.input.is_empty() && is_valid_input {
+                    match self.get_selection_range() {
+                        Some((start, end)) if start != end => {
+                            self.begin_edit(EditKind::Other, start.0, end.0)
+                        }
+                        _ => self.begin_edit(EditKind::Typing, self.cursor_block, self.cursor_block),
+                    }
+
                     let mut wrapped = false;
                     if let Some(((start_blk, start_char), (end_blk, end_char))) =
                         self.get_selection_range()
@@ -888,15 +1779,19 @@ impl Widget for EditorArea {
                             self.document.try_convert_block(self.cursor_block)
                         {
                             self.cursor_char = self.cursor_char.saturating_sub(removed_chars);
-                        }
-
-                        if te.input == " "
-                            && self.document.apply_inline_formatting(self.cursor_block)
-                        {
-                            self.cursor_char = self.document.blocks[self.cursor_block].text_len();
+                        } else if te.input == " " {
+                            // Marqueurs fermés juste avant l'espace tapé
+                            self.cursor_char -= self
+                                .document
+                                .apply_inline_formatting(self.cursor_block, self.cursor_char - 1);
                         }
                         self.invalidate_layout_from(self.cursor_block);
                     }
+                    self.end_edit();
+                    // Un mot par étape d'annulation
+                    if te.input.chars().all(char::is_whitespace) {
+                        self.history.seal();
+                    }
                     self.redraw(cx);
                 }
             }
@@ -908,21 +1803,85 @@ impl Widget for EditorArea {
             for action in actions {
                 let editor_action: EditorAction = action.cast();
                 match editor_action {
-                    EditorAction::AsyncFileLoaded(path, blocks) => {
-                        self.document.blocks = blocks;
-                        self.cursor_block = 0;
-                        self.cursor_char = 0;
-                        self.current_file = Some(path.clone());
-                        self.invalidate_layout();
-                        self.redraw(cx);
+                    EditorAction::AsyncFileLoaded(path, doc, content) => {
+                        if self.current_file.as_ref() == Some(&path) {
+                            self.set_document(doc);
+                            self.disk_base = content;
+                            self.disk_conflict = None;
+                            self.watch_current_file();
+                            self.redraw(cx);
+                        } else if let Some(tab) = self.background_tab(&path) {
+                            // Onglet quitté avant la fin du chargement
+                            *tab = DocTab {
+                                document: doc,
+                                current_file: Some(path.clone()),
+                                watcher: Some(watch_file(&path)),
+                                disk_base: content,
+                                ..DocTab::default()
+                            };
+                        }
                         cx.widget_action(self.widget_uid(), &scope.path, EditorAction::FileLoaded(path));
                     }
-                    EditorAction::AsyncFileSaved(path) => {
+                    EditorAction::AsyncFileSaved(path, revision, content) => {
                         makepad_widgets::log!("Successfully saved to {}", path);
+                        if self.current_file.as_ref() == Some(&path) {
+                            self.document.mark_saved(revision);
+                            self.disk_base = content;
+                            self.disk_conflict = None;
+                            self.watch_current_file();
+                            self.redraw(cx);
+                        } else if let Some(tab) = self.background_tab(&path) {
+                            tab.document.mark_saved(revision);
+                            tab.disk_base = content;
+                            tab.disk_conflict = None;
+                            self.redraw(cx);
+                        }
+                        cx.widget_action(self.widget_uid(), &scope.path, EditorAction::FileSaved(path));
+                    }
+                    EditorAction::SaveFailed(err) => {
+                        makepad_widgets::log!("Save failed: {}", err);
+                    }
+                    EditorAction::AsyncRecoveryAvailable(path) => {
+                        if self.current_file.as_ref() == Some(&path) {
+                            cx.widget_action(
+                                self.widget_uid(),
+                                &scope.path,
+                                EditorAction::RecoveryAvailable(path),
+                            );
+                        }
+                    }
+                    EditorAction::AsyncJournalLoaded(path, doc) => {
+                        if self.current_file.as_ref() == Some(&path) {
+                            self.set_document(doc);
+                            // Le contenu restauré n'est pas encore dans le fichier
+                            self.document.mark_modified();
+                            self.redraw(cx);
+                        }
+                    }
+                    EditorAction::AsyncFileChanged(path) => {
+                        if self.current_file.as_ref() == Some(&path)
+                            || self.background_tab(&path).is_some()
+                        {
+                            self.read_disk_content(path);
+                        }
+                    }
+                    EditorAction::AsyncDiskContent(path, content) => {
+                        self.handle_disk_content(cx, scope, path, content);
                     }
                     EditorAction::AsyncError(err) => {
                         makepad_widgets::log!("Async Error: {}", err);
                     }
+                    EditorAction::OpenLink(url) => {
+                        self.open_link(cx, &url);
+                    }
+                    EditorAction::ToggleTask(block_idx) => {
+                        self.history.seal();
+                        self.begin_edit(EditKind::Other, block_idx, block_idx);
+                        self.document.toggle_task(block_idx);
+                        self.end_edit();
+                        self.invalidate_layout_from(block_idx);
+                        self.redraw(cx);
+                    }
                     _ => {}
                 }
             }
@@ -946,6 +1905,7 @@ impl Widget for EditorArea {
             draw_text_reg: &mut self.draw_text_reg,
             draw_text_bold: &mut self.draw_text_bold,
             draw_text_italic: &mut self.draw_text_italic,
+            draw_text_bold_italic: &mut self.draw_text_bold_italic,
             draw_text_code: &mut self.draw_text_code,
             draw_text_header1: &mut self.draw_text_header1,
             draw_text_header2: &mut self.draw_text_header2,
@@ -953,10 +1913,19 @@ impl Widget for EditorArea {
             draw_text_header4: &mut self.draw_text_header4,
             draw_text_header5: &mut self.draw_text_header5,
             draw_text_quote: &mut self.draw_text_quote,
+            draw_text_link: &mut self.draw_text_link,
+            draw_link_underline: &mut self.draw_link_underline,
+            draw_strikethrough: &mut self.draw_strikethrough,
+            draw_highlight: &mut self.draw_highlight,
+            draw_checkbox: &mut self.draw_checkbox,
+            draw_checkbox_checked: &mut self.draw_checkbox_checked,
+            draw_table_border: &mut self.draw_table_border,
+            draw_table_header_bg: &mut self.draw_table_header_bg,
             draw_cursor: &mut self.draw_cursor,
             draw_selection: &mut self.draw_selection,
             draw_code_bg: &mut self.draw_code_bg,
             draw_text_code_header: &mut self.draw_text_code_header,
+            syntax_colors: &self.syntax_colors,
         };
 
         let is_cache_valid = !self.block_y_offsets.is_empty()
@@ -981,7 +1950,17 @@ impl Widget for EditorArea {
 
         if let Some(hit) = hit_res {
             self.cursor_block = hit.block_idx;
-            self.cursor_char = hit.char_idx;
+            // Jamais au milieu d'un graphème
+            self.cursor_char = self.document.blocks[hit.block_idx].grapheme_floor(hit.char_idx);
+            if std::mem::take(&mut self.toggle_task_on_tap) && hit.on_checkbox {
+                Cx::post_action(EditorAction::ToggleTask(hit.block_idx));
+            }
+            if std::mem::take(&mut self.open_link_on_tap) {
+                // Ctrl+clic sur un lien
+                if let Some((_, _, url)) = self.document.link_at(hit.block_idx, hit.char_idx) {
+                    Cx::post_action(EditorAction::OpenLink(url));
+                }
+            }
             if self.is_dragging && self.selection_anchor.is_none() {
                 self.selection_anchor = Some((self.cursor_block, self.cursor_char));
             }
@@ -991,6 +1970,31 @@ impl Widget for EditorArea {
         cx.turtle_mut().set_used(rect.size.x, used_height);
         cx.end_turtle_with_area(&mut self.area);
 
+        if let Some(block_idx) = self.pending_scroll.take() {
+            if let Some(&y) = self.block_y_offsets.get(block_idx) {
+                self.scroll_bars.set_scroll_pos(cx, dvec2(0.0, y));
+                self.redraw(cx);
+            }
+        }
+        if let Some(y) = self.restored_scroll.take() {
+            self.scroll_bars.set_scroll_pos(cx, dvec2(0.0, y));
+            self.redraw(cx);
+        }
+        self.update_outline();
+        let tabs = self.tab_infos();
+        if tabs != self.reported_tabs || self.active_tab != self.reported_active_tab {
+            self.reported_tabs = tabs.clone();
+            self.reported_active_tab = self.active_tab;
+            Cx::post_action(EditorAction::TabsChanged {
+                tabs,
+                active: self.active_tab,
+            });
+        }
+        if self.document.is_modified() != self.reported_modified {
+            self.reported_modified = self.document.is_modified();
+            Cx::post_action(EditorAction::ModifiedChanged(self.reported_modified));
+        }
+
         self.deferred_finger_tap = None;
         DrawStep::done()
     }
@@ -1,6 +1,12 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::io::{self, Write};
+use std::ops::Range;
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
-#[derive(Clone, Debug, PartialEq)]
+use super::text::BlockText;
+
+#[derive(Clone, Debug, PartialEq, Hash)]
 pub enum BlockType {
     Paragraph,
     Heading1,
@@ -11,20 +17,266 @@ pub enum BlockType {
     Quote,
     ListItem,
     OrderedListItem,
+    TaskItem,
     CodeBlock,
+    // Tableau GFM : lignes séparées par `\n` dans le texte, cellules par `\t`.
+    // La ligne 0 est l'en-tête.
+    Table,
 }
 
-#[derive(Clone, Debug, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColumnAlign {
+    #[default]
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Debug, Copy, Default, PartialEq)]
 pub struct StyleBits {
     pub is_bold: bool,
     pub is_italic: bool,
     pub is_code: bool,
+    pub is_strikethrough: bool,
+    pub is_highlight: bool,
+    pub is_superscript: bool,
+    pub is_subscript: bool,
+    /// Image (`![alt](src)`): the text is the alt text, the span's `link` the source.
+    pub is_image: bool,
+    /// The newlines of the span are hard line breaks (`\` or two spaces before them).
+    pub is_hard_break: bool,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InlineStyle {
+    Bold,
+    Italic,
+    Code,
+    Strikethrough,
+    Highlight,
+    Superscript,
+    Subscript,
+}
+
+// Ordre d'ouverture des marqueurs : le code est toujours le plus intérieur,
+// rien n'est interprété entre deux backticks
+const MARKER_ORDER: [InlineStyle; 7] = [
+    InlineStyle::Highlight,
+    InlineStyle::Superscript,
+    InlineStyle::Subscript,
+    InlineStyle::Bold,
+    InlineStyle::Italic,
+    InlineStyle::Strikethrough,
+    InlineStyle::Code,
+];
+
+/// HTML tag written instead of the marker where the marker would not be recognised
+/// (`x<em>:</em>`, `<mark>=</mark>`), and read back as the style.
+pub const HTML_TAGS: [(InlineStyle, &str); 6] = [
+    (InlineStyle::Bold, "strong"),
+    (InlineStyle::Italic, "em"),
+    (InlineStyle::Strikethrough, "del"),
+    (InlineStyle::Highlight, "mark"),
+    (InlineStyle::Superscript, "sup"),
+    (InlineStyle::Subscript, "sub"),
+];
+
+impl InlineStyle {
+    /// Markdown delimiter written on both sides of the styled text.
+    pub fn marker(self) -> &'static str {
+        match self {
+            InlineStyle::Bold => "**",
+            InlineStyle::Italic => "*",
+            InlineStyle::Code => "`",
+            InlineStyle::Strikethrough => "~~",
+            InlineStyle::Highlight => "==",
+            InlineStyle::Superscript => "^",
+            InlineStyle::Subscript => "~",
+        }
+    }
+}
+
+impl StyleBits {
+    pub fn has(&self, style: InlineStyle) -> bool {
+        match style {
+            InlineStyle::Bold => self.is_bold,
+            InlineStyle::Italic => self.is_italic,
+            InlineStyle::Code => self.is_code,
+            InlineStyle::Strikethrough => self.is_strikethrough,
+            InlineStyle::Highlight => self.is_highlight,
+            InlineStyle::Superscript => self.is_superscript,
+            InlineStyle::Subscript => self.is_subscript,
+        }
+    }
+
+    pub fn set(&mut self, style: InlineStyle, on: bool) {
+        let bit = match style {
+            InlineStyle::Bold => &mut self.is_bold,
+            InlineStyle::Italic => &mut self.is_italic,
+            InlineStyle::Code => &mut self.is_code,
+            InlineStyle::Strikethrough => &mut self.is_strikethrough,
+            InlineStyle::Highlight => &mut self.is_highlight,
+            InlineStyle::Superscript => &mut self.is_superscript,
+            InlineStyle::Subscript => &mut self.is_subscript,
+        };
+        *bit = on;
+    }
+}
+
+/// Text of an image without alt text, so that it keeps a place in the block.
+pub const IMAGE_PLACEHOLDER: char = '\u{FFFC}';
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct StyleSpan {
     pub len: usize,
     pub style: StyleBits,
+    /// Destination of the link this span is part of.
+    pub link: Option<String>,
+}
+
+// Destination telle qu'écrite entre parenthèses : <...> si elle contient des espaces
+fn link_destination(url: &str) -> String {
+    if url.is_empty() || url.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+fn close_marker<E>(
+    style: InlineStyle,
+    code_close: &str,
+    as_html: &mut Vec<InlineStyle>,
+    emit: &mut impl FnMut(&str) -> Result<(), E>,
+) -> Result<(), E> {
+    if let Some(i) = as_html.iter().position(|s| *s == style) {
+        as_html.remove(i);
+        return emit(&format!("</{}>", html_tag(style)));
+    }
+    if style == InlineStyle::Code {
+        emit(code_close)
+    } else {
+        emit(style.marker())
+    }
+}
+
+fn html_tag(style: InlineStyle) -> &'static str {
+    HTML_TAGS
+        .iter()
+        .find(|(s, _)| *s == style)
+        .map_or("", |(_, tag)| tag)
+}
+
+// Ponctuation au sens de CommonMark, symboles compris
+fn is_punctuation(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace()
+}
+
+// Écrit `text` en insérant un `\` devant les caractères marqués dans `escapes` (les
+// blancs marqués sont retirés), et `hard_break` devant chaque saut de ligne s'il en faut un
+fn write_escaped<E>(
+    text: &str,
+    escapes: &[bool],
+    hard_break: Option<&str>,
+    emit: &mut impl FnMut(&str) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut from = 0;
+    for ((i, c), &escape) in text.char_indices().zip(escapes) {
+        let (before, next) = match (c, hard_break) {
+            (' ' | '\t' | '\n', _) if escape => ("", i + 1),
+            _ if escape => ("\\", i),
+            ('\n', Some(hard_break)) => (hard_break, i),
+            _ => continue,
+        };
+        emit(&text[from..i])?;
+        emit(before)?;
+        from = next;
+    }
+    emit(&text[from..])
+}
+
+// Délimiteurs d'un code inline : plus de backticks que la plus longue suite du contenu,
+// et un espace de chaque côté si le contenu commence ou finit par un backtick.
+fn code_span_fences(code: &str) -> (String, String) {
+    let mut fence = 1;
+    while code.split(|c| c != '`').any(|run| run.len() == fence) {
+        fence += 1;
+    }
+    let fence = "`".repeat(fence);
+    let pad = code.starts_with('`')
+        || code.ends_with('`')
+        || (code.starts_with(' ') && code.ends_with(' ') && !code.bytes().all(|b| b == b' '));
+    if pad {
+        (format!("{fence} "), format!(" {fence}"))
+    } else {
+        (fence.clone(), fence)
+    }
+}
+
+// `~` ouvert avant `start` et refermé avant `end` : (peut ouvrir, peut fermer) pour
+// chacun des deux. pulldown-cmark le traite comme `_`, qui ne s'ouvre ni ne se ferme
+// au milieu d'un mot. Les caractères échappés ou en code comptent comme la
+// ponctuation écrite devant eux.
+fn tilde_flanks(
+    chars: &[(char, StyleBits, Option<&str>)],
+    escapes: &[bool],
+    start: usize,
+    end: usize,
+    early: bool,
+) -> ((bool, bool), (bool, bool)) {
+    let written = |i: usize| {
+        chars.get(i).map(|&(c, style, _)| {
+            if (escapes[i] && !c.is_whitespace()) || style.is_code {
+                '\\'
+            } else {
+                c
+            }
+        })
+    };
+    let mut last = end;
+    while last > start + 1 && chars[last - 1].0.is_whitespace() {
+        last -= 1;
+    }
+    let flanking = |before: Option<char>, after: Option<char>| {
+        let space = |c: Option<char>| c.is_none_or(char::is_whitespace);
+        let punct = |c: Option<char>| c.is_some_and(is_punctuation);
+        let left = !space(after) && (!punct(after) || space(before) || punct(before));
+        let right = !space(before) && (!punct(before) || space(after) || punct(after));
+        (
+            left && (!right || punct(before)),
+            right && (!left || punct(after)),
+        )
+    };
+    let before = start.checked_sub(1).and_then(|p| chars.get(p)).map(|c| c.0);
+    let after = if early { Some('~') } else { written(end) };
+    (
+        flanking(before, written(start)),
+        flanking(written(last - 1), after),
+    )
+}
+
+// `<` suivi d'une adresse (`<https://...>`, `<a@b.c>`) : lien automatique
+fn is_autolink_start(rest: &str) -> bool {
+    let Some(close) = rest.find('>') else {
+        return false;
+    };
+    let inner = &rest[..close];
+    !inner.is_empty()
+        && !inner.contains(|c: char| c.is_whitespace() || c == '<')
+        && inner.contains([':', '@'])
+}
+
+// `&` suivi d'une entité (`&amp;`, `&#123;`, `&#x1F;`)
+fn is_entity_start(rest: &str) -> bool {
+    let Some((semi, _)) = rest.char_indices().take(33).find(|&(_, c)| c == ';') else {
+        return false;
+    };
+    match &rest.as_bytes()[..semi] {
+        [b'#', b'x' | b'X', hex @ ..] => !hex.is_empty() && hex.iter().all(u8::is_ascii_hexdigit),
+        [b'#', digits @ ..] => !digits.is_empty() && digits.iter().all(u8::is_ascii_digit),
+        name => !name.is_empty() && name.iter().all(u8::is_ascii_alphanumeric),
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -33,13 +285,66 @@ pub struct BlockLayoutCache {
     pub width: f64,
 }
 
+// Syntaxe Markdown d'origine, relevée au chargement pour réécrire le fichier à l'identique
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlockSyntax {
+    /// Marker as written: bullet (`-`, `+`, `*`), ordered delimiter (`.`, `)`),
+    /// ATX hashes, quote marker (`> `) or code fence.
+    pub marker: Option<String>,
+    /// Number written in front of an ordered list item.
+    pub number: Option<u64>,
+    /// Underline of a setext heading (`===` or `---`).
+    pub setext_underline: Option<String>,
+    /// Closing sequence of an ATX heading as written after the text (` #`).
+    pub atx_closing: Option<String>,
+    /// `>` of the quotes holding a block that is not a quote itself (`> ` before a
+    /// list item or a heading), repeated on each of its lines.
+    pub container_prefix: Option<String>,
+    /// Spaces written before the marker. Cleared when the indent changes.
+    pub leading_spaces: Option<usize>,
+    /// Source between the previous block and this one (blank lines, `>` lines).
+    pub separator: Option<String>,
+    /// Inline source as written, keyed by the `content_hash` it was parsed into.
+    pub source: Option<(u64, String)>,
+    /// Text is Markdown written as-is, without escaping (HTML block, link definition).
+    pub raw: bool,
+    /// Hard line break as written before the newline (`\` or spaces).
+    pub hard_break: Option<String>,
+}
+
+impl BlockSyntax {
+    /// Prefix of the lines after the first one: the `>` of `container_prefix` are
+    /// kept, list markers become blanks (`> - ` gives `>   `).
+    pub fn continuation_prefix(&self) -> String {
+        let prefix = self.container_prefix.as_deref().unwrap_or("");
+        prefix
+            .chars()
+            .map(|c| {
+                if c == '>' || c.is_whitespace() {
+                    c
+                } else {
+                    ' '
+                }
+            })
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Block {
     pub id: u64,
     pub ty: BlockType,
-    pub text: String,
+    pub text: BlockText,
     pub indent: u8,
     pub styles: Vec<StyleSpan>,
+    /// Info string of a fenced code block as written after the fence
+    /// (```` ```rust ````), empty otherwise.
+    pub info: String,
+    /// Checkbox state of a task item (`- [x]`).
+    pub checked: bool,
+    /// Column alignments of a table, empty for other blocks.
+    pub columns: Vec<ColumnAlign>,
+    pub syntax: BlockSyntax,
     pub layout_cache: Option<BlockLayoutCache>,
     pub is_dirty: bool,
 }
@@ -49,88 +354,677 @@ impl Block {
         Self {
             id,
             ty,
-            text: text.to_string(),
+            text: text.into(),
             indent: 0,
             styles: vec![StyleSpan {
                 len: text.chars().count(),
                 style: StyleBits::default(),
+                link: None,
             }],
+            info: String::new(),
+            checked: false,
+            columns: Vec::new(),
+            syntax: BlockSyntax::default(),
             layout_cache: None,
             is_dirty: true,
         }
     }
 
     pub fn text_len(&self) -> usize {
-        self.text.chars().count()
+        self.text.len_chars()
     }
 
     pub fn full_text(&self) -> &str {
         &self.text
     }
 
-    pub fn write_markdown_to(&self, buf: &mut String) {
-        let mut char_iter = self.text.chars();
+    /// Start of the grapheme cluster before `char_idx`: an emoji with its modifiers,
+    /// a flag or a letter with its combining accents is crossed in one step.
+    pub fn prev_grapheme(&self, char_idx: usize) -> usize {
+        let byte = self.text.char_to_byte(char_idx);
+        let mut cursor = GraphemeCursor::new(byte, self.text.len(), true);
+        match cursor.prev_boundary(&self.text, 0) {
+            Ok(Some(byte)) => self.text.byte_to_char(byte),
+            _ => 0,
+        }
+    }
+
+    /// End of the grapheme cluster after `char_idx`.
+    pub fn next_grapheme(&self, char_idx: usize) -> usize {
+        let byte = self.text.char_to_byte(char_idx);
+        let mut cursor = GraphemeCursor::new(byte, self.text.len(), true);
+        match cursor.next_boundary(&self.text, 0) {
+            Ok(Some(byte)) => self.text.byte_to_char(byte),
+            _ => self.text_len(),
+        }
+    }
+
+    /// `char_idx` moved back to the start of the grapheme cluster it falls in.
+    pub fn grapheme_floor(&self, char_idx: usize) -> usize {
+        let byte = self.text.char_to_byte(char_idx);
+        let mut cursor = GraphemeCursor::new(byte, self.text.len(), true);
+        if cursor.is_boundary(&self.text, 0).unwrap_or(true) {
+            return self.text.byte_to_char(byte);
+        }
+        self.prev_grapheme(char_idx)
+    }
+
+    // Mots au sens Unicode : segments qui contiennent une lettre ou un chiffre
+    fn is_word(segment: &str) -> bool {
+        segment.chars().any(char::is_alphanumeric)
+    }
+
+    /// Start of the word before `char_idx` (Ctrl+Left), skipping spaces and punctuation.
+    pub fn prev_word_start(&self, char_idx: usize) -> usize {
+        let byte = self.text.char_to_byte(char_idx);
+        self.text[..byte]
+            .split_word_bound_indices()
+            .rev()
+            .find(|(_, segment)| Self::is_word(segment))
+            .map_or(0, |(start, _)| self.text.byte_to_char(start))
+    }
+
+    /// End of the word after `char_idx` (Ctrl+Right).
+    pub fn next_word_end(&self, char_idx: usize) -> usize {
+        let byte = self.text.char_to_byte(char_idx);
+        self.text[byte..]
+            .split_word_bound_indices()
+            .find(|(_, segment)| Self::is_word(segment))
+            .map_or_else(
+                || self.text_len(),
+                |(start, segment)| self.text.byte_to_char(byte + start + segment.len()),
+            )
+    }
+
+    // Langage du bloc de code : premier mot de l'info string
+    pub fn language(&self) -> &str {
+        self.info.split_whitespace().next().unwrap_or("")
+    }
+
+    pub fn is_list_item(&self) -> bool {
+        matches!(
+            self.ty,
+            BlockType::ListItem | BlockType::OrderedListItem | BlockType::TaskItem
+        )
+    }
+
+    /// Char ranges of the cells of a table block, row by row.
+    pub fn table_cells(&self) -> Vec<Vec<Range<usize>>> {
+        let mut rows = vec![Vec::new()];
+        let mut start = 0;
+        for (i, c) in self.text.chars().enumerate() {
+            if c == '\t' || c == '\n' {
+                rows.last_mut().unwrap().push(start..i);
+                start = i + 1;
+                if c == '\n' {
+                    rows.push(Vec::new());
+                }
+            }
+        }
+        rows.last_mut().unwrap().push(start..self.text_len());
+        rows
+    }
+
+    /// Row and column of the table cell holding `char_idx` (cell ends included).
+    pub fn table_cell_at(&self, char_idx: usize) -> Option<(usize, usize)> {
+        if self.ty != BlockType::Table {
+            return None;
+        }
+        self.table_cells()
+            .iter()
+            .enumerate()
+            .find_map(|(row, cells)| {
+                cells
+                    .iter()
+                    .position(|cell| cell.start <= char_idx && char_idx <= cell.end)
+                    .map(|col| (row, col))
+            })
+    }
+
+    // Markdown d'une cellule de tableau : les `|` sont échappés, même dans le code
+    pub fn cell_markdown(&self, range: Range<usize>) -> String {
+        let mut cell = Block::new(self.id, BlockType::Paragraph, "");
+        cell.text = self
+            .text
+            .chars()
+            .skip(range.start)
+            .take(range.len())
+            .collect();
+        let mut pos = 0;
+        let mut styles = Vec::new();
         for span in &self.styles {
-            if span.style.is_code {
-                buf.push('`');
+            let start = pos.max(range.start);
+            let end = (pos + span.len).min(range.end);
+            if start < end {
+                styles.push(StyleSpan {
+                    len: end - start,
+                    ..span.clone()
+                });
             }
-            if span.style.is_bold {
-                buf.push_str("**");
+            pos += span.len;
+        }
+        if !styles.is_empty() {
+            cell.styles = styles;
+        }
+        cell.to_markdown().replace('|', "\\|")
+    }
+
+    pub fn set_indent(&mut self, indent: u8) {
+        if self.indent != indent {
+            self.indent = indent;
+            self.syntax.leading_spaces = None;
+            self.mark_dirty();
+        }
+    }
+
+    // Même contenu, sans tenir compte de l'id ni du cache de mise en page
+    pub fn same_content(&self, other: &Block) -> bool {
+        self.ty == other.ty
+            && self.indent == other.indent
+            && self.text == other.text
+            && self.styles == other.styles
+            && self.info == other.info
+            && self.checked == other.checked
+            && self.columns == other.columns
+            && self.syntax == other.syntax
+    }
+
+    // Empreinte du contenu : si elle change, la source d'origine n'est plus valable
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.ty.hash(&mut hasher);
+        self.indent.hash(&mut hasher);
+        self.to_markdown().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // Source inline d'origine, si le bloc n'a pas été modifié depuis le chargement
+    pub fn original_source(&self) -> Option<&str> {
+        match &self.syntax.source {
+            Some((hash, source)) if *hash == self.content_hash() => Some(source),
+            _ => None,
+        }
+    }
+
+    // Caractères à précéder d'un `\` pour qu'ils restent du texte au rechargement
+    fn escaped_chars(&self) -> Vec<bool> {
+        let text = self.text.as_str();
+        let len = self.text.len_chars();
+        let mut escape = vec![false; len];
+        // Un bloc brut (HTML, définition de lien) ou un filet `---` s'écrit tel quel
+        if self.ty == BlockType::CodeBlock
+            || self.syntax.raw
+            || (self.ty == BlockType::Paragraph && self.text == "---")
+        {
+            return escape;
+        }
+
+        let mut code = Vec::with_capacity(len);
+        let mut in_link = Vec::with_capacity(len);
+        let mut span_last = Vec::with_capacity(len);
+        for span in &self.styles {
+            for n in 0..span.len {
+                code.push(span.style.is_code);
+                in_link.push(span.link.is_some());
+                span_last.push(n + 1 == span.len);
+            }
+        }
+        let is_code = |i: usize| code.get(i).copied().unwrap_or(false);
+
+        let mut last = None;
+        for (i, (byte, c)) in text.char_indices().enumerate() {
+            let prev = last.replace(c);
+            if is_code(i) {
+                continue;
             }
-            if span.style.is_italic {
-                buf.push('*');
+            let after = &text[byte + c.len_utf8()..];
+            let next = after.chars().next();
+            escape[i] = match c {
+                '*' | '`' | '~' | '^' | '[' => true,
+                ']' => in_link.get(i).copied().unwrap_or(false),
+                // `!` juste avant un lien en ferait une image
+                '!' => {
+                    in_link.get(i + 1).copied().unwrap_or(false)
+                        && !in_link.get(i).copied().unwrap_or(false)
+                }
+                // Le span suivant, ou un marqueur fermant écrit avant les blancs, peut
+                // commencer par un caractère échappable
+                '\\' => {
+                    span_last.get(i).copied().unwrap_or(true)
+                        || next.is_none_or(|n| n.is_ascii_punctuation() || n.is_whitespace())
+                }
+                // `_` au milieu d'un mot n'est jamais de l'emphase
+                '_' => {
+                    !(prev.is_some_and(char::is_alphanumeric)
+                        && next.is_some_and(char::is_alphanumeric))
+                }
+                // Deux `=` se suivent dans le texte une fois les marqueurs retirés
+                '=' => next == Some('='),
+                // Lien automatique, balise (`<em>`, `</a>`), commentaire ou déclaration
+                '<' => {
+                    is_autolink_start(after)
+                        || next.is_some_and(|n| {
+                            n.is_ascii_alphabetic() || matches!(n, '/' | '!' | '?')
+                        })
+                }
+                '&' => is_entity_start(after),
+                _ => false,
+            };
+        }
+
+        // Début de ligne : titres, citations, listes, soulignements setext
+        let mut line_start = 0;
+        for (line_no, line) in text.split('\n').enumerate() {
+            let line_len = line.chars().count();
+            // Blancs en bordure de ligne : retirés au chargement, donc pas écrits. Une
+            // ligne de blancs part avec un saut de ligne, pour ne pas couper le bloc.
+            let blank = |n: usize, c: u8| matches!(c, b' ' | b'\t') && !is_code(line_start + n);
+            let bytes = line.as_bytes();
+            let indent = (0..bytes.len()).take_while(|&n| blank(n, bytes[n])).count();
+            // Blancs d'un octet : en fin de ligne, octets et caractères se correspondent
+            let trail = (indent..bytes.len())
+                .rev()
+                .take_while(|&b| blank(line_len - (bytes.len() - b), bytes[b]))
+                .count();
+            for n in (0..indent).chain(line_len - trail..line_len) {
+                escape[line_start + n] = true;
             }
-            for _ in 0..span.len {
-                if let Some(c) = char_iter.next() {
-                    buf.push(c);
+            if indent > 0 && indent == line_len {
+                let end = line_start + line_len;
+                if end < len {
+                    escape[end] = true;
+                } else if line_start > 0 {
+                    escape[line_start - 1] = true;
                 }
             }
-            if span.style.is_italic {
-                buf.push('*');
+            let first = line_start + indent;
+            let rest = &bytes[indent..bytes.len() - trail];
+            line_start += line_len + 1;
+            if rest.is_empty() || is_code(first) {
+                continue;
             }
-            if span.style.is_bold {
-                buf.push_str("**");
+            // Les marqueurs de début de ligne sont en ASCII : `rest` se lit en octets
+            let after = |n: usize| rest.get(n).is_none_or(|&c| c == b' ' || c == b'\t');
+            let only = |marker: u8| rest.iter().all(|&c| c == marker || c == b' ');
+            // Ligne de séparation d'un tableau sous la précédente (`|-`, `-|-`, `:-:`)
+            escape[first] |= line_no > 0
+                && rest.contains(&b'-')
+                && rest
+                    .iter()
+                    .all(|c| matches!(c, b'|' | b'-' | b':' | b' ' | b'\t'));
+            match rest[0] {
+                b'#' => {
+                    let hashes = rest.iter().take_while(|&&c| c == b'#').count();
+                    escape[first] |= hashes <= 6 && after(hashes);
+                }
+                b'>' => escape[first] = true,
+                // Soulignement setext, ou filet (`---`, `- --` après la puce)
+                b'-' => {
+                    let bullet = self.ty == BlockType::ListItem
+                        && self.syntax.marker.as_deref().is_none_or(|m| m == "-");
+                    let dashes = rest.iter().filter(|&&c| c == b'-').count();
+                    escape[first] |=
+                        after(1) || (only(b'-') && (line_no > 0 || dashes >= 3 || bullet))
+                }
+                b'+' => escape[first] |= after(1),
+                b'=' => escape[first] |= line_no > 0 && only(b'='),
+                b'0'..=b'9' => {
+                    let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+                    if digits <= 9
+                        && matches!(rest.get(digits), Some(b'.' | b')'))
+                        && !is_code(first + digits)
+                        && after(digits + 1)
+                    {
+                        escape[first + digits] = true;
+                    }
+                }
+                _ => {}
             }
-            if span.style.is_code {
-                buf.push('`');
+        }
+
+        // `# Titre #` : les `#` finaux d'un titre ATX seraient pris pour une clôture
+        if self.syntax.setext_underline.is_none()
+            && matches!(
+                self.ty,
+                BlockType::Heading1
+                    | BlockType::Heading2
+                    | BlockType::Heading3
+                    | BlockType::Heading4
+                    | BlockType::Heading5
+            )
+        {
+            let trimmed = text.trim_end();
+            let text_end = len - text[trimmed.len()..].chars().count();
+            let hashes = trimmed.bytes().rev().take_while(|&c| c == b'#').count();
+            let start = text_end - hashes;
+            if hashes > 0
+                && (start == 0 || self.text.char_at(start - 1) == Some(' '))
+                && !is_code(start)
+            {
+                escape[start] = true;
             }
         }
+        escape
     }
 
-    // Version Streaming I/O (Zero allocation for big strings)
-    pub fn write_markdown_to_writer<W: Write>(&self, w: &mut W) -> io::Result<()> {
-        let mut char_iter = self.text.chars();
+    // Un marqueur ouvert avant le caractère `start`, puis refermé avant `end`,
+    // serait-il reconnu ? (`x*:*` ne l'est pas, `===x==` non plus.) `punct_after` : un
+    // autre marqueur s'ouvre juste après celui-ci.
+    fn marker_flanks(
+        &self,
+        chars: &[(char, StyleBits, Option<&str>)],
+        escapes: &[bool],
+        style: InlineStyle,
+        start: usize,
+        end: usize,
+        punct_after: bool,
+    ) -> bool {
+        let Some(&(first, first_style, first_link)) = chars.get(start) else {
+            return true;
+        };
+        let starts_apart = |i: usize| {
+            escapes[i]
+                || chars[i].1.is_code
+                || chars[i].2 != i.checked_sub(1).and_then(|p| chars[p].2)
+        };
+        let prev = start.checked_sub(1).map(|p| chars[p].0);
+        let opens = !(punct_after
+            || is_punctuation(first)
+            || escapes[start]
+            || first_style.is_code
+            || (first_link.is_some() && starts_apart(start)))
+            || prev.is_none_or(|c| c.is_whitespace() || is_punctuation(c));
 
-        for span in &self.styles {
-            if span.style.is_code {
-                w.write_all(b"`")?;
+        // Refermé avant la fin du style : un autre marqueur suit
+        let early = chars.get(end).is_some_and(|c| c.1.has(style));
+        let run_end = end;
+        let mut end = end;
+        while end > start && chars[end - 1].0.is_whitespace() && !chars[end - 1].1.is_code {
+            end -= 1;
+        }
+        let (last, last_style, _) = chars[end.max(start + 1) - 1];
+
+        // `~` collé à `~~` : une suite de trois, ni barré ni indice
+        if style == InlineStyle::Subscript
+            && chars[start.saturating_sub(1)..(end + 1).min(chars.len())]
+                .iter()
+                .any(|c| c.1.is_strikethrough)
+        {
+            return false;
+        }
+        if matches!(
+            style,
+            InlineStyle::Highlight | InlineStyle::Superscript | InlineStyle::Subscript
+        ) {
+            // Repérés après coup dans le texte : pas collés au même caractère ni à un
+            // code, pas de blanc en bordure, ni dans un exposant ou un indice
+            let m = style.marker().chars().next();
+            let inner = &chars[start..end.max(start + 1)];
+            let next = chars.get(end).map(|c| c.0);
+            let found = ![prev, Some(first), Some(last), next].contains(&m)
+                && end == run_end
+                && !first.is_whitespace()
+                && !first_style.is_code
+                && !last_style.is_code
+                && (style == InlineStyle::Highlight || !inner.iter().any(|c| c.0.is_whitespace()));
+            if style != InlineStyle::Subscript {
+                return found;
             }
-            if span.style.is_bold {
-                w.write_all(b"**")?;
+            // `~` passe d'abord par pulldown-cmark, comme un barré : reconnu par lui aux
+            // deux bouts, ou ignoré aux deux
+            let (opener, closer) = tilde_flanks(chars, escapes, start, end, early);
+            let inert = opener == (false, false) && closer == (false, false);
+            return found && ((opener.0 && closer.1) || inert);
+        }
+        // Un autre style qui s'arrête là se referme peut-être avant celui-ci
+        let others_end = MARKER_ORDER.iter().any(|&s| {
+            s != style && last_style.has(s) && !chars.get(end).is_some_and(|c| c.1.has(s))
+        });
+        let apart = |i: usize| {
+            chars
+                .get(i)
+                .is_none_or(|&(c, ..)| c.is_whitespace() || is_punctuation(c) || starts_apart(i))
+        };
+        let closes = !(is_punctuation(last) || others_end) || early || apart(end);
+        if style == InlineStyle::Strikethrough {
+            let (opener, closer) = tilde_flanks(chars, escapes, start, end, early);
+            return opens && closes && opener.0 && closer.1;
+        }
+        opens && closes
+    }
+
+    // Écrit le texte et ses marqueurs morceau par morceau. Les styles restent ouverts
+    // d'un span à l'autre (`**a *b***`) au lieu d'être refermés à chaque span;
+    // un lien les referme tous, pour qu'ils ne chevauchent pas ses crochets.
+    // Les espaces en bordure passent à l'extérieur des marqueurs : `** a **` ne serait
+    // pas reconnu comme du gras.
+    fn write_inline<E>(&self, emit: &mut impl FnMut(&str) -> Result<(), E>) -> Result<(), E> {
+        let escapes = self.escaped_chars();
+        let mut chars = Vec::with_capacity(escapes.len());
+        let mut text_chars = self.text.chars();
+        for span in &self.styles {
+            for c in text_chars.by_ref().take(span.len) {
+                chars.push((c, span.style, span.link.as_deref()));
             }
-            if span.style.is_italic {
-                w.write_all(b"*")?;
+        }
+        let mut open: Vec<InlineStyle> = Vec::new();
+        // Styles ouverts par une balise HTML plutôt que par leur marqueur
+        let mut as_html: Vec<InlineStyle> = Vec::new();
+        // Backticks fermant le code en cours (leur nombre dépend du contenu)
+        let mut code_close = String::new();
+        let mut prev_link = (None, false);
+        let mut byte = 0;
+        let mut char_idx = 0;
+        // Espaces de fin du span précédent, écrits après les marqueurs fermants
+        let mut trailing = "";
+        let mut trailing_char = 0;
+        let mut spans = self.styles.iter().enumerate().peekable();
+
+        while let Some((span_idx, span)) = spans.next() {
+            let end = self.text[byte..]
+                .char_indices()
+                .nth(span.len)
+                .map_or(self.text.len(), |(i, _)| byte + i);
+            let mut text = &self.text[byte..end];
+            let mut text_char = char_idx;
+            byte = end;
+            char_idx += span.len;
+
+            let link = span.link.as_deref();
+            let image = span.style.is_image;
+            let starts_link = link.is_some() && (link, image) != prev_link;
+            let literal = span.style.is_code;
+            // Un span fait uniquement d'espaces n'ouvre aucun style
+            let mut style = span.style;
+            if !literal && text.trim().is_empty() && !starts_link {
+                for s in MARKER_ORDER {
+                    style.set(s, style.has(s) && open.contains(&s));
+                }
             }
 
-            let mut b = [0; 4]; // Max utf8 char len
-            for _ in 0..span.len {
-                if let Some(c) = char_iter.next() {
-                    let s = c.encode_utf8(&mut b);
-                    w.write_all(s.as_bytes())?;
+            // Referme le premier style qui n'est plus actif et tout ce qui a été ouvert après
+            let mut close_to = open
+                .iter()
+                .position(|s| !style.has(*s))
+                .unwrap_or(open.len());
+            if starts_link {
+                close_to = 0;
+            }
+            let missing = MARKER_ORDER
+                .iter()
+                .any(|s| style.has(*s) && !open[..close_to].contains(s));
+            if missing {
+                // Aucun marqueur ne peut s'ouvrir à l'intérieur d'un code
+                if let Some(code) = open[..close_to]
+                    .iter()
+                    .position(|s| *s == InlineStyle::Code)
+                {
+                    close_to = code;
                 }
             }
+            // Refermé pour être rouvert : le marqueur se collerait aux marqueurs fermants
+            let mut reopened = Vec::new();
+            // Dernier marqueur fermant écrit : un marqueur du même caractère collé à lui
+            // formerait une seule suite (`*` + `**`)
+            let mut closed_with = None;
+            while open.len() > close_to {
+                let s = open.pop().unwrap();
+                if style.has(s) && !starts_link {
+                    reopened.push(s);
+                }
+                closed_with = (!as_html.contains(&s))
+                    .then(|| s.marker().chars().next())
+                    .flatten();
+                close_marker(s, &code_close, &mut as_html, emit)?;
+            }
+            if !trailing.is_empty() {
+                closed_with = None;
+            }
+            // Blancs seuls : ajoutés aux blancs en attente, écrits après les marqueurs
+            // fermants suivants
+            if !literal && !span.style.is_hard_break && link.is_none() && text.trim().is_empty() {
+                trailing = &self.text[byte - text.len() - trailing.len()..byte];
+                prev_link = (None, false);
+                continue;
+            }
+            write_escaped(trailing, &escapes[trailing_char..], None, emit)?;
 
-            if span.style.is_italic {
-                w.write_all(b"*")?;
+            if starts_link {
+                emit(if image { "![" } else { "[" })?;
             }
-            if span.style.is_bold {
-                w.write_all(b"**")?;
+            let hard_break = span
+                .style
+                .is_hard_break
+                .then(|| self.syntax.hard_break.as_deref().unwrap_or("\\"));
+            if !literal && hard_break.is_none() && (missing || starts_link) {
+                let content = text.trim_start();
+                let lead = &text[..text.len() - content.len()];
+                write_escaped(lead, &escapes[text_char..], None, emit)?;
+                text_char += lead.chars().count();
+                if !lead.is_empty() {
+                    closed_with = None;
+                }
+                text = content;
             }
-            if span.style.is_code {
-                w.write_all(b"`")?;
+            let opening: Vec<InlineStyle> = MARKER_ORDER
+                .into_iter()
+                .filter(|s| style.has(*s) && !open.contains(s))
+                .collect();
+            for (n, &s) in opening.iter().enumerate() {
+                if s == InlineStyle::Code {
+                    // Le code peut continuer sur les spans suivants de même style
+                    let len: usize = self.styles[span_idx..]
+                        .iter()
+                        .take_while(|next| next.style == span.style && next.link == span.link)
+                        .map(|next| next.len)
+                        .sum();
+                    let code: String = self.text[byte - text.len()..].chars().take(len).collect();
+                    let (open_fence, close_fence) = code_span_fences(&code);
+                    emit(&open_fence)?;
+                    code_close = close_fence;
+                } else {
+                    let marker = s.marker();
+                    let punct_after = opening[n + 1..]
+                        .iter()
+                        .any(|next| next.marker().chars().next() != marker.chars().next());
+                    // Refermé à la fin du style, ou avant : à la fin d'un style ouvert
+                    // avant lui, ou au début d'un lien
+                    let run_end = |s: InlineStyle| {
+                        (text_char..chars.len())
+                            .find(|&i| !chars[i].1.has(s))
+                            .unwrap_or(chars.len())
+                    };
+                    let link_end = (text_char..chars.len())
+                        .find(|&i| (chars[i].2, chars[i].1.is_image) != (link, image))
+                        .unwrap_or(chars.len());
+                    let end = open
+                        .iter()
+                        .chain(&opening[..n])
+                        .map(|&outer| run_end(outer))
+                        .fold(run_end(s).min(link_end), usize::min);
+                    let tag = html_tag(s);
+                    let glued = n == 0 && !starts_link && closed_with == marker.chars().next();
+                    // Pourrait aussi fermer, alors qu'un marqueur du même caractère est
+                    // ouvert : pris pour sa fin
+                    let can_close = text_char.checked_sub(1).is_some_and(|p| {
+                        !chars[p].0.is_whitespace()
+                            && (!is_punctuation(chars[p].0)
+                                || chars.get(text_char).is_none_or(|c| is_punctuation(c.0)))
+                    });
+                    let ambiguous = can_close
+                        && open[..open.len() - n].iter().any(|o| {
+                            !as_html.contains(o)
+                                && o.marker().chars().next() == marker.chars().next()
+                        });
+                    if !tag.is_empty()
+                        && (reopened.contains(&s)
+                            || glued
+                            || ambiguous
+                            || !self.marker_flanks(
+                                &chars,
+                                &escapes,
+                                s,
+                                text_char,
+                                end,
+                                punct_after,
+                            ))
+                    {
+                        emit(&format!("<{tag}>"))?;
+                        as_html.push(s);
+                    } else {
+                        emit(marker)?;
+                    }
+                }
+                open.push(s);
+            }
+
+            let ends_link = link.is_some()
+                && spans
+                    .peek()
+                    .map(|(_, s)| (s.link.as_deref(), s.style.is_image))
+                    != Some((link, image));
+            // Image sans texte alternatif : `![](src)`
+            if image && text.chars().eq([IMAGE_PLACEHOLDER]) {
+                text = "";
+            }
+            // Un saut de ligne forcé s'écrit à sa place, pas après les marqueurs fermants
+            let content = if literal || open.is_empty() || hard_break.is_some() {
+                text
+            } else {
+                text.trim_end()
+            };
+            write_escaped(content, &escapes[text_char..], hard_break, emit)?;
+            trailing = &text[content.len()..];
+            trailing_char = text_char + content.chars().count();
+
+            if let (true, Some(url)) = (ends_link, link) {
+                while let Some(s) = open.pop() {
+                    close_marker(s, &code_close, &mut as_html, emit)?;
+                }
+                write_escaped(trailing, &escapes[trailing_char..], None, emit)?;
+                trailing = "";
+                emit("](")?;
+                emit(&link_destination(url))?;
+                emit(")")?;
             }
+            prev_link = (link, image);
+        }
+        while let Some(s) = open.pop() {
+            close_marker(s, &code_close, &mut as_html, emit)?;
         }
-        Ok(())
+        write_escaped(trailing, &escapes[trailing_char..], None, emit)
+    }
+
+    pub fn write_markdown_to(&self, buf: &mut String) {
+        let _ = self.write_inline(&mut |s: &str| {
+            buf.push_str(s);
+            Ok::<(), std::convert::Infallible>(())
+        });
+    }
+
+    // Version Streaming I/O (Zero allocation for big strings)
+    pub fn write_markdown_to_writer<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_inline(&mut |s: &str| w.write_all(s.as_bytes()))
     }
 
     pub fn to_markdown(&self) -> String {
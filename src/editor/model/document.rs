@@ -1,13 +1,477 @@
-use crate::editor::model::block::{Block, BlockType, StyleBits, StyleSpan};
-use std::fs::File;
+use crate::editor::model::block::{
+    Block, BlockType, ColumnAlign, InlineStyle, StyleBits, StyleSpan,
+};
+use crate::editor::model::html;
+use crate::editor::model::parser;
+use std::fs::{self, File};
 use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Répète un préfixe après chaque saut de ligne (lignes de continuation)
+struct LinePrefixWriter<'a, W: Write> {
+    inner: &'a mut W,
+    prefix: &'a [u8],
+}
+
+impl<W: Write> Write for LinePrefixWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut start = 0;
+        for (i, &b) in buf.iter().enumerate() {
+            if b == b'\n' {
+                self.inner.write_all(&buf[start..=i])?;
+                self.inner.write_all(self.prefix)?;
+                start = i + 1;
+            }
+        }
+        self.inner.write_all(&buf[start..])?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// La clôture doit être plus longue que toute suite de ` dans le code
+fn code_fence_for(code: &str) -> String {
+    fence_of(code, '`')
+}
+
+fn fence_of(code: &str, fence_char: char) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in code.chars() {
+        if c == fence_char {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+    fence_char.to_string().repeat(longest.max(2) + 1)
+}
+
+fn is_heading(ty: &BlockType) -> bool {
+    matches!(
+        ty,
+        BlockType::Heading1
+            | BlockType::Heading2
+            | BlockType::Heading3
+            | BlockType::Heading4
+            | BlockType::Heading5
+    )
+}
+
+fn heading_level(ty: &BlockType) -> usize {
+    match ty {
+        BlockType::Heading1 => 1,
+        BlockType::Heading2 => 2,
+        BlockType::Heading3 => 3,
+        BlockType::Heading4 => 4,
+        BlockType::Heading5 => 5,
+        _ => 0,
+    }
+}
+
+// Écrit dans une citation : une citation, ou un bloc précédé de ses `>`
+fn is_quoted(block: &Block) -> bool {
+    block.ty == BlockType::Quote
+        || block
+            .syntax
+            .container_prefix
+            .as_deref()
+            .is_some_and(|p| p.contains('>'))
+}
+
+// Filet (`---`, `***`, `___`)
+fn is_rule(block: &Block) -> bool {
+    block.ty == BlockType::Paragraph && block.text == "---" && !block.syntax.raw
+}
+
+// Espacement avant un bloc : celui d'origine s'il reste valide, sinon une ligne vide
+// (aucune entre deux éléments de liste)
+fn block_separator<'a>(prev: Option<&Block>, block: &'a Block) -> &'a str {
+    let default = match prev {
+        None => "",
+        Some(p) if p.is_list_item() && block.is_list_item() => "\n",
+        Some(_) => "\n\n",
+    };
+    let Some(sep) = block.syntax.separator.as_deref() else {
+        return default;
+    };
+    if !sep.chars().all(|c| matches!(c, '\n' | '\r' | ' ' | '\t' | '>')) {
+        return default;
+    }
+    let Some(prev) = prev else {
+        return if sep.contains('>') { default } else { sep };
+    };
+    if !sep.ends_with('\n') {
+        return default;
+    }
+    if sep.contains('>') && !(is_quoted(prev) && is_quoted(block)) {
+        return default;
+    }
+    // Without a blank line the block would be merged into the previous one
+    let has_blank_line = sep.matches('\n').count() > 1;
+    // Un filet `---` sous un paragraphe le soulignerait
+    let dash_rule = block.original_source().unwrap_or("---").starts_with('-');
+    let can_interrupt = block.is_list_item()
+        || is_heading(&block.ty)
+        || is_heading(&prev.ty)
+        || block.ty == BlockType::CodeBlock
+        || prev.ty == BlockType::CodeBlock
+        || (block.ty == BlockType::Quote && !is_quoted(prev))
+        || (is_rule(block) && !(dash_rule && prev.ty == BlockType::Paragraph))
+        || is_rule(prev)
+        || block.syntax.raw
+        || prev.syntax.raw;
+    if has_blank_line || can_interrupt {
+        sep
+    } else {
+        default
+    }
+}
+
+fn block_indent(prev: Option<&Block>, block: &Block) -> String {
+    match block.syntax.leading_spaces {
+        // 4+ spaces outside of a list would turn the block into code
+        Some(n) if n < 4 || block.is_list_item() || prev.is_some_and(|p| p.is_list_item()) => {
+            " ".repeat(n)
+        }
+        _ if block.is_list_item() => "  ".repeat(block.indent as usize),
+        _ => String::new(),
+    }
+}
+
+fn block_prefix(block: &Block, number: u64) -> String {
+    let marker = block.syntax.marker.as_deref();
+    match block.ty {
+        BlockType::Heading1
+        | BlockType::Heading2
+        | BlockType::Heading3
+        | BlockType::Heading4
+        | BlockType::Heading5 => {
+            if setext_underline(block).is_some() {
+                return String::new();
+            }
+            let level = heading_level(&block.ty);
+            match marker {
+                // Heading5 also holds level 6 headings
+                Some(m)
+                    if m.bytes().all(|b| b == b'#')
+                        && (m.len() == level || (level == 5 && m.len() == 6)) =>
+                {
+                    format!("{m} ")
+                }
+                _ => format!("{} ", "#".repeat(level)),
+            }
+        }
+        BlockType::Quote => match marker {
+            Some(m) if m.starts_with('>') && m.trim_start_matches(['>', ' ']).is_empty() => {
+                m.to_string()
+            }
+            _ => "> ".to_string(),
+        },
+        BlockType::ListItem => match marker {
+            Some(m @ ("-" | "+" | "*")) => format!("{m} "),
+            _ => "- ".to_string(),
+        },
+        BlockType::OrderedListItem => match marker {
+            Some(m @ ("." | ")")) => format!("{number}{m} "),
+            _ => format!("{number}. "),
+        },
+        BlockType::TaskItem => {
+            let check = if block.checked { 'x' } else { ' ' };
+            match (marker, block.syntax.number) {
+                // Tâche dans une liste numérotée (`1. [ ]`) : numéro tel qu'écrit
+                (Some(m @ ("." | ")")), Some(n)) => format!("{n}{m} [{check}] "),
+                (Some(m @ ("-" | "+" | "*")), _) => format!("{m} [{check}] "),
+                _ => format!("- [{check}] "),
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+fn setext_underline(block: &Block) -> Option<&str> {
+    let underline = block.syntax.setext_underline.as_deref()?;
+    let valid = match block.ty {
+        BlockType::Heading1 => underline.bytes().all(|b| b == b'='),
+        BlockType::Heading2 => underline.bytes().all(|b| b == b'-'),
+        _ => false,
+    };
+    (valid && !underline.is_empty()).then_some(underline)
+}
+
+// Retrait d'un bloc de code indenté : au moins 4 colonnes d'espaces ou de tabulations
+fn is_code_indent(marker: &str) -> bool {
+    let mut columns = 0;
+    for b in marker.bytes() {
+        columns = match b {
+            b' ' => columns + 1,
+            b'\t' => columns + 4 - columns % 4,
+            _ => return false,
+        };
+    }
+    columns >= 4
+}
+
+// Clôture ` #` d'un titre ATX : des `#` précédés d'un blanc, ou des blancs seuls
+fn atx_closing(block: &Block) -> Option<&str> {
+    let closing = block.syntax.atx_closing.as_deref()?;
+    let hashes = closing.trim();
+    let valid = is_heading(&block.ty)
+        && !block.text.is_empty()
+        && closing.starts_with([' ', '\t'])
+        && hashes.bytes().all(|b| b == b'#');
+    valid.then_some(closing)
+}
+
+fn write_code_block<W: Write>(writer: &mut W, block: &Block, indent: &str) -> std::io::Result<()> {
+    let marker = block.syntax.marker.as_deref().unwrap_or("");
+    if is_code_indent(marker) && block.info.is_empty() {
+        // Bloc de code indenté
+        let prefix = format!("{indent}{marker}");
+        writer.write_all(marker.as_bytes())?;
+        let mut line_writer = LinePrefixWriter {
+            inner: writer,
+            prefix: prefix.as_bytes(),
+        };
+        return line_writer.write_all(block.text.as_bytes());
+    }
+
+    let fence = if block.info.contains('`') {
+        // Une info string ne peut pas contenir de ` après une clôture en backticks
+        if marker.starts_with('~') && is_valid_fence(marker, &block.text) {
+            marker.to_string()
+        } else {
+            fence_of(&block.text, '~')
+        }
+    } else if is_valid_fence(marker, &block.text) {
+        marker.to_string()
+    } else {
+        code_fence_for(&block.text)
+    };
+    writer.write_all(fence.as_bytes())?;
+    writer.write_all(block.info.as_bytes())?;
+    writer.write_all(b"\n")?;
+    writer.write_all(indent.as_bytes())?;
+    let mut line_writer = LinePrefixWriter {
+        inner: writer,
+        prefix: indent.as_bytes(),
+    };
+    line_writer.write_all(block.text.as_bytes())?;
+    writer.write_all(b"\n")?;
+    writer.write_all(indent.as_bytes())?;
+    writer.write_all(fence.as_bytes())
+}
+
+// Colonnes alignées : chaque cellule est complétée à la largeur de sa colonne
+fn write_table<W: Write>(writer: &mut W, block: &Block, indent: &str) -> std::io::Result<()> {
+    let rows: Vec<Vec<String>> = block
+        .table_cells()
+        .into_iter()
+        .map(|cells| {
+            cells
+                .into_iter()
+                .map(|cell| block.cell_markdown(cell).trim().to_string())
+                .collect()
+        })
+        .collect();
+    let columns = block.columns.len().max(1);
+    let mut widths = vec![3; columns];
+    for row in &rows {
+        for (col, cell) in row.iter().enumerate().take(columns) {
+            widths[col] = widths[col].max(cell.chars().count());
+        }
+    }
+
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b"\n")?;
+            writer.write_all(indent.as_bytes())?;
+        }
+        let cells: Vec<String> = (0..columns)
+            .map(|col| {
+                let cell = row.get(col).map(String::as_str).unwrap_or("");
+                let pad = widths[col] - cell.chars().count();
+                match block.columns.get(col) {
+                    Some(ColumnAlign::Right) => format!("{}{cell}", " ".repeat(pad)),
+                    Some(ColumnAlign::Center) => {
+                        format!("{}{cell}{}", " ".repeat(pad / 2), " ".repeat(pad - pad / 2))
+                    }
+                    _ => format!("{cell}{}", " ".repeat(pad)),
+                }
+            })
+            .collect();
+        write_table_row(writer, &cells)?;
+
+        if i == 0 {
+            writer.write_all(b"\n")?;
+            writer.write_all(indent.as_bytes())?;
+            let delimiters: Vec<String> = (0..columns)
+                .map(|col| {
+                    let w = widths[col];
+                    match block.columns.get(col) {
+                        Some(ColumnAlign::Left) => format!(":{}", "-".repeat(w - 1)),
+                        Some(ColumnAlign::Right) => format!("{}:", "-".repeat(w - 1)),
+                        Some(ColumnAlign::Center) => format!(":{}:", "-".repeat(w - 2)),
+                        _ => "-".repeat(w),
+                    }
+                })
+                .collect();
+            write_table_row(writer, &delimiters)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_table_row<W: Write>(writer: &mut W, cells: &[String]) -> std::io::Result<()> {
+    writer.write_all(b"|")?;
+    for cell in cells {
+        writer.write_all(format!(" {cell} |").as_bytes())?;
+    }
+    Ok(())
+}
+
+fn is_valid_fence(fence: &str, code: &str) -> bool {
+    let Some(c) = fence.chars().next() else {
+        return false;
+    };
+    // Seule une ligne faite de la clôture (ou plus longue) et de blancs fermerait le
+    // bloc : ```` ```rust ```` n'est pas une clôture
+    let closes = |line: &str| {
+        let line = line.trim_start();
+        let run = line.chars().take_while(|&f| f == c).count();
+        run >= fence.len() && line[run..].trim().is_empty()
+    };
+    (c == '`' || c == '~')
+        && fence.len() >= 3
+        && fence.chars().all(|f| f == c)
+        && !code.lines().any(closes)
+}
+
+// Fusionne les spans voisins de même style et de même lien
+fn merge_adjacent_spans(styles: &mut Vec<StyleSpan>) {
+    let mut merged: Vec<StyleSpan> = Vec::with_capacity(styles.len());
+    for span in styles.drain(..) {
+        match merged.last_mut() {
+            Some(last) if last.style == span.style && last.link == span.link => {
+                last.len += span.len
+            }
+            _ => merged.push(span),
+        }
+    }
+    *styles = merged;
+}
+
+// Coupe les spans à `at` : ceux d'avant restent, ceux d'après sont renvoyés
+fn split_styles(styles: &mut Vec<StyleSpan>, at: usize) -> Vec<StyleSpan> {
+    let mut pos = 0;
+    let split = styles.iter().position(|span| {
+        pos += span.len;
+        at < pos
+    });
+    let mut tail = match split {
+        Some(i) => styles.split_off(i),
+        None => Vec::new(),
+    };
+    let head_len = at - styles.iter().map(|span| span.len).sum::<usize>();
+    if head_len > 0 {
+        let mut head = tail[0].clone();
+        head.len = head_len;
+        tail[0].len -= head_len;
+        styles.push(head);
+    }
+    for spans in [&mut *styles, &mut tail] {
+        if spans.is_empty() {
+            spans.push(StyleSpan {
+                len: 0,
+                style: StyleBits::default(),
+                link: None,
+            });
+        }
+    }
+    tail
+}
+
+// Spans de deux textes mis bout à bout ; un span vide ne sert que dans un bloc vide
+fn join_styles(styles: &mut Vec<StyleSpan>, tail: Vec<StyleSpan>) {
+    styles.extend(tail);
+    if styles.iter().any(|span| span.len > 0) {
+        styles.retain(|span| span.len > 0);
+    } else {
+        styles.truncate(1);
+    }
+    merge_adjacent_spans(styles);
+}
+
+// Marqueurs reconnus à la saisie, les doubles avant les simples
+const INLINE_MARKERS: [InlineStyle; 6] = [
+    InlineStyle::Bold,
+    InlineStyle::Strikethrough,
+    InlineStyle::Highlight,
+    InlineStyle::Italic,
+    InlineStyle::Superscript,
+    InlineStyle::Subscript,
+];
+
+fn marker_at(chars: &[char], at: usize, marker: &str) -> bool {
+    marker
+        .chars()
+        .enumerate()
+        .all(|(n, m)| chars.get(at + n) == Some(&m))
+}
+
+// Début de mot : un marqueur ouvrant doit suivre un espace, une ponctuation ou le début
+fn is_word_start(chars: &[char], at: usize) -> bool {
+    at == 0 || chars[at - 1].is_whitespace() || chars[at - 1].is_ascii_punctuation()
+}
+
+// `[texte](destination)` ouvert à `start` : position du `]`, destination, position de la `)`
+fn find_link(chars: &[char], start: usize) -> Option<(usize, String, usize)> {
+    let close = start + 1 + chars[start + 1..].iter().position(|&c| c == ']')?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let dest_start = close + 2;
+    if chars.get(dest_start) == Some(&'<') {
+        let gt = dest_start + 1 + chars[dest_start + 1..].iter().position(|&c| c == '>')?;
+        if chars.get(gt + 1) != Some(&')') {
+            return None;
+        }
+        return Some((close, chars[dest_start + 1..gt].iter().collect(), gt + 1));
+    }
+    let end = dest_start + chars[dest_start..].iter().position(|&c| c == ')')?;
+    let dest: String = chars[dest_start..end].iter().collect();
+    if dest.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((close, dest, end))
+}
+
+/// A heading shown in the outline panel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineEntry {
+    pub block_idx: usize,
+    /// 1 for `Heading1` up to 5 for `Heading5`.
+    pub level: usize,
+    /// Indentation in the outline: a skipped level does not indent twice.
+    pub depth: usize,
+    pub title: String,
+}
 
 #[derive(Clone, Debug)]
 pub struct Document {
     pub blocks: Vec<Block>,
+    /// Newlines written after the last block.
+    pub trailing_newlines: usize,
     next_id: u64,
-    temp_markdown_buf: String,
     temp_char_buf: Vec<char>,
+    // Incrémenté à chaque modification ; `saved_revision` est celle écrite sur le disque
+    revision: u64,
+    saved_revision: u64,
 }
 
 impl Default for Document {
@@ -22,9 +486,11 @@ impl Default for Document {
                 ),
                 Block::new(3, BlockType::Quote, "Essayez de taper # titre ou **gras**."),
             ],
+            trailing_newlines: 1,
             next_id: 4,
-            temp_markdown_buf: String::with_capacity(1024),
             temp_char_buf: Vec::with_capacity(1024),
+            revision: 0,
+            saved_revision: 0,
         }
     }
 }
@@ -34,6 +500,29 @@ impl Document {
         Self::default()
     }
 
+    // Liste des titres, imbriqués selon leur niveau
+    pub fn outline(&self) -> Vec<OutlineEntry> {
+        let mut parents: Vec<usize> = Vec::new();
+        let mut entries = Vec::new();
+        for (block_idx, block) in self.blocks.iter().enumerate() {
+            let level = heading_level(&block.ty);
+            if level == 0 {
+                continue;
+            }
+            while parents.last().is_some_and(|&l| l >= level) {
+                parents.pop();
+            }
+            entries.push(OutlineEntry {
+                block_idx,
+                level,
+                depth: parents.len(),
+                title: block.text.replace('\n', " "),
+            });
+            parents.push(level);
+        }
+        entries
+    }
+
     pub fn generate_id(&mut self) -> u64 {
         let id = self.next_id;
         self.next_id += 1;
@@ -44,58 +533,197 @@ impl Document {
     pub fn snapshot(&self) -> Self {
         Self {
             blocks: self.blocks.clone(),
+            trailing_newlines: self.trailing_newlines,
             next_id: self.next_id,
-            temp_markdown_buf: String::new(), // Pas d'allocation inutile
-            temp_char_buf: Vec::new(),
+            temp_char_buf: Vec::new(), // Pas d'allocation inutile
+            revision: self.revision,
+            saved_revision: self.saved_revision,
         }
     }
 
-    // Streaming Save (Memory efficient)
+    // Construit un document à partir de Markdown (CommonMark)
+    pub fn from_markdown(source: &str) -> Self {
+        let mut blocks = parser::parse_markdown(source, 1);
+        if blocks.is_empty() {
+            blocks.push(Block::new(1, BlockType::Paragraph, ""));
+        }
+        let mut doc = Self::from_blocks(blocks);
+        doc.trailing_newlines = source.bytes().rev().take_while(|&b| b == b'\n').count();
+        doc
+    }
+
+    pub fn from_blocks(blocks: Vec<Block>) -> Self {
+        let next_id = blocks.iter().map(|b| b.id).max().unwrap_or(0) + 1;
+        Self {
+            blocks,
+            trailing_newlines: 1,
+            next_id,
+            temp_char_buf: Vec::with_capacity(1024),
+            revision: 0,
+            saved_revision: 0,
+        }
+    }
+
+    /// True when the document has changes that are not on disk.
+    pub fn is_modified(&self) -> bool {
+        self.revision != self.saved_revision
+    }
+
+    /// Records a change made to the document.
+    pub fn mark_modified(&mut self) {
+        self.revision += 1;
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Called when `revision` has been written to disk. Changes made since then keep
+    /// the document modified.
+    pub fn mark_saved(&mut self, revision: u64) {
+        self.saved_revision = revision;
+    }
+
+    // Streaming Save (Memory efficient). Écrit un fichier temporaire dans le même dossier,
+    // le synchronise puis le renomme : une interruption laisse l'ancien fichier intact.
     pub fn save_to_file(&self, filename: &str) -> std::io::Result<()> {
-        let file = File::create(filename)?;
-        let mut writer = BufWriter::new(file);
+        // Un lien symbolique reste un lien : on remplace sa cible
+        let target = fs::canonicalize(filename).unwrap_or_else(|_| PathBuf::from(filename));
+        let dir = match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = target.file_name().unwrap_or_default().to_string_lossy();
+        static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+        let tmp = dir.join(format!(
+            ".{name}.{}-{}.ndown-tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let written = (|| {
+            let file = File::create(&tmp)?;
+            if let Ok(meta) = fs::metadata(&target) {
+                file.set_permissions(meta.permissions())?;
+            }
+            let mut writer = BufWriter::new(file);
+            self.write_markdown(&mut writer)?;
+            writer.flush()?;
+            writer.get_ref().sync_all()
+        })();
+        if let Err(e) = written.and_then(|_| fs::rename(&tmp, &target)) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        // Rend le renommage durable
+        #[cfg(unix)]
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+        Ok(())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = Vec::new();
+        // Écrire dans un Vec ne peut pas échouer
+        let _ = self.write_markdown(&mut out);
+        String::from_utf8_lossy(&out).into_owned()
+    }
 
-        let mut ordered_list_counter = 1;
+    pub fn write_markdown<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        // Per nesting level: (last number written, numbers still as written in the source)
+        let mut ordered_counters: Vec<Option<(u64, bool)>> = Vec::new();
 
         for (i, block) in self.blocks.iter().enumerate() {
-            // Reset counter if not in an ordered list
-            if block.ty != BlockType::OrderedListItem {
-                ordered_list_counter = 1;
-            }
-
-            let prefix = match block.ty {
-                BlockType::Heading1 => "# ".to_string(),
-                BlockType::Heading2 => "## ".to_string(),
-                BlockType::Heading3 => "### ".to_string(),
-                BlockType::Heading4 => "#### ".to_string(),
-                BlockType::Heading5 => "##### ".to_string(),
-                BlockType::Quote => "> ".to_string(),
-                BlockType::ListItem => "- ".to_string(),
-                BlockType::OrderedListItem => {
-                    let p = format!("{ordered_list_counter}. ");
-                    ordered_list_counter += 1;
-                    p
+            let prev = i.checked_sub(1).map(|p| &self.blocks[p]);
+            writer.write_all(block_separator(prev, block).as_bytes())?;
+
+            let level = block.indent as usize;
+            if !block.is_list_item() {
+                ordered_counters.clear();
+            } else {
+                ordered_counters.resize(level + 1, None);
+                if block.ty != BlockType::OrderedListItem {
+                    ordered_counters[level] = None;
                 }
-                _ => "".to_string(),
+            }
+
+            let number = if block.ty == BlockType::OrderedListItem {
+                // Numbers are kept as written until an item without one (new item) shows up,
+                // the rest of the list is then renumbered.
+                let (n, as_written) = match (ordered_counters[level], block.syntax.number) {
+                    (None, Some(n)) => (n, true),
+                    (None, None) => (1, false),
+                    (Some((_, true)), Some(n)) => (n, true),
+                    (Some((last, _)), _) => (last + 1, false),
+                };
+                ordered_counters[level] = Some((n, as_written));
+                n
+            } else {
+                0
             };
 
-            if (block.ty == BlockType::ListItem || block.ty == BlockType::OrderedListItem)
-                && block.indent > 0
-            {
-                for _ in 0..block.indent {
-                    writer.write_all(b"  ")?;
+            // Préfixe des conteneurs (`> `, `- ` d'un élément) puis retrait ; sur les
+            // lignes suivantes, les marqueurs de liste deviennent des blancs
+            let container = block.syntax.container_prefix.as_deref().unwrap_or("");
+            writer.write_all(container.as_bytes())?;
+            let indent = block_indent(prev, block);
+            writer.write_all(indent.as_bytes())?;
+            let line_indent = format!("{}{indent}", block.syntax.continuation_prefix());
+
+            if block.ty == BlockType::CodeBlock {
+                write_code_block(writer, block, &line_indent)?;
+                continue;
+            }
+            if block.ty == BlockType::Table {
+                match block.original_source() {
+                    Some(source) => writer.write_all(source.as_bytes())?,
+                    None => write_table(writer, block, &line_indent)?,
                 }
+                continue;
             }
 
+            let mut prefix = block_prefix(block, number);
+            // Élément vide : pas d'espace après la puce, sauf s'il était écrit
+            if block.text.is_empty()
+                && matches!(block.ty, BlockType::ListItem | BlockType::OrderedListItem)
+            {
+                prefix.truncate(prefix.trim_end().len());
+            }
             writer.write_all(prefix.as_bytes())?;
 
-            block.write_markdown_to_writer(&mut writer)?;
+            if let Some(source) = block.original_source() {
+                writer.write_all(source.as_bytes())?;
+            } else {
+                // Continuation lines keep the quote marker / list indentation. Raw text
+                // already holds its own.
+                let continuation = match block.ty {
+                    _ if block.syntax.raw => indent.clone(),
+                    BlockType::Quote => format!("{}{}", line_indent, prefix),
+                    BlockType::ListItem | BlockType::OrderedListItem | BlockType::TaskItem => {
+                        format!("{}{}", line_indent, " ".repeat(prefix.chars().count()))
+                    }
+                    _ => line_indent.clone(),
+                };
+                let mut line_writer = LinePrefixWriter {
+                    inner: writer,
+                    prefix: continuation.as_bytes(),
+                };
+                block.write_markdown_to_writer(&mut line_writer)?;
+            }
 
-            if i < self.blocks.len() - 1 {
-                writer.write_all(b"\n\n")?;
+            if let Some(underline) = setext_underline(block) {
+                writer.write_all(b"\n")?;
+                writer.write_all(line_indent.as_bytes())?;
+                writer.write_all(underline.as_bytes())?;
+            } else if let Some(closing) = atx_closing(block) {
+                writer.write_all(closing.as_bytes())?;
             }
         }
-        writer.flush()?;
+
+        for _ in 0..self.trailing_newlines {
+            writer.write_all(b"\n")?;
+        }
         Ok(())
     }
 
@@ -105,15 +733,8 @@ impl Document {
         }
         let block = &mut self.blocks[block_idx];
 
-        let removed = if block.ty == BlockType::Paragraph {
-            if block.text.starts_with("```") {
-                block.ty = BlockType::CodeBlock;
-                block.text.replace_range(0..3, "");
-                if let Some(first) = block.styles.first_mut() {
-                    first.len = first.len.saturating_sub(3);
-                }
-                Some(3)
-            } else if block.text.starts_with("# ") {
+        let mut removed = if block.ty == BlockType::Paragraph {
+            if block.text.starts_with("# ") {
                 block.ty = BlockType::Heading1;
                 block.text.replace_range(0..2, "");
                 if let Some(first) = block.styles.first_mut() {
@@ -157,17 +778,11 @@ impl Document {
                 Some(2)
             } else {
                 // Check for List Item with indentation
-                let mut space_count = 0;
-                let chars: Vec<char> = block.text.chars().collect();
-                while space_count < chars.len() && chars[space_count] == ' ' {
-                    space_count += 1;
-                }
+                // Seuls les premiers octets comptent : le préfixe est en ASCII
+                let bytes = block.text.as_bytes();
+                let space_count = bytes.iter().take_while(|&&b| b == b' ').count();
 
-                if space_count < chars.len()
-                    && chars[space_count] == '-'
-                    && space_count + 1 < chars.len()
-                    && chars[space_count + 1] == ' '
-                {
+                if bytes[space_count..].starts_with(b"- ") {
                     block.ty = BlockType::ListItem;
                     block.indent = (space_count / 2) as u8; // Assuming 2 spaces per indent
                     let remove_count = space_count + 2; // spaces + "- "
@@ -178,16 +793,12 @@ impl Document {
                     Some(remove_count)
                 } else {
                     // Check for Ordered List Item (1. )
-                    let mut digit_end = space_count;
-                    while digit_end < chars.len() && chars[digit_end].is_ascii_digit() {
-                        digit_end += 1;
-                    }
-                    if digit_end > space_count
-                        && digit_end < chars.len()
-                        && chars[digit_end] == '.'
-                        && digit_end + 1 < chars.len()
-                        && chars[digit_end + 1] == ' '
-                    {
+                    let digit_end = space_count
+                        + bytes[space_count..]
+                            .iter()
+                            .take_while(|b| b.is_ascii_digit())
+                            .count();
+                    if digit_end > space_count && bytes[digit_end..].starts_with(b". ") {
                         block.ty = BlockType::OrderedListItem;
                         block.indent = (space_count / 2) as u8;
                         let remove_count = digit_end + 2; // spaces + digits + ". "
@@ -205,6 +816,26 @@ impl Document {
             None
         };
 
+        // "[ ] " ou "[x] " en tête d'un élément de liste : case à cocher
+        if block.ty == BlockType::ListItem {
+            let checked = if block.text.starts_with("[ ] ") {
+                Some(false)
+            } else if block.text.starts_with("[x] ") || block.text.starts_with("[X] ") {
+                Some(true)
+            } else {
+                None
+            };
+            if let Some(checked) = checked {
+                block.ty = BlockType::TaskItem;
+                block.checked = checked;
+                block.text.replace_range(0..4, "");
+                if let Some(first) = block.styles.first_mut() {
+                    first.len = first.len.saturating_sub(4);
+                }
+                removed = Some(removed.unwrap_or(0) + 4);
+            }
+        }
+
         // Handle closing code block
         if block.ty == BlockType::CodeBlock {
             if block.text.starts_with("```") {
@@ -214,180 +845,311 @@ impl Document {
                     first.len = first.len.saturating_sub(3);
                 }
                 block.mark_dirty();
+                self.mark_modified();
                 return Some(3);
             }
         }
 
         if removed.is_some() {
             block.mark_dirty();
+            self.mark_modified();
         }
         removed
     }
 
-    pub fn apply_inline_formatting(&mut self, block_idx: usize) -> bool {
-        if block_idx >= self.blocks.len() {
+    /// Checks or unchecks a task item. Returns false if the block is not a task.
+    pub fn toggle_task(&mut self, block_idx: usize) -> bool {
+        match self.blocks.get_mut(block_idx) {
+            Some(block) if block.ty == BlockType::TaskItem => {
+                block.checked = !block.checked;
+                block.mark_dirty();
+                self.mark_modified();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Turns a paragraph holding only an opening fence (```` ```rust ````) into an empty
+    /// code block, keeping the info string. Called when Enter is pressed.
+    pub fn try_convert_code_fence(&mut self, block_idx: usize) -> bool {
+        let Some(block) = self.blocks.get_mut(block_idx) else {
+            return false;
+        };
+        if block.ty != BlockType::Paragraph {
+            return false;
+        }
+        let Some(info) = block.text.trim_end().strip_prefix("```") else {
+            return false;
+        };
+        if info.contains('`') {
             return false;
         }
 
-        {
-            let block = &self.blocks[block_idx];
-            let has_active_styles = block
-                .styles
-                .iter()
-                .any(|s| s.style.is_bold || s.style.is_italic || s.style.is_code);
-            let text_has_markers = block.text.contains('*') || block.text.contains('`');
-
-            if !has_active_styles && !text_has_markers {
-                return false;
-            }
+        block.info = info.trim().to_string();
+        block.ty = BlockType::CodeBlock;
+        block.text.clear();
+        block.styles = vec![StyleSpan {
+            len: 0,
+            style: StyleBits::default(),
+            link: None,
+        }];
+        block.mark_dirty();
+        self.mark_modified();
+        true
+    }
+
+    /// Turns a paragraph holding a single pipe row (`| a | b |`) into a table with that
+    /// header and one empty row. Called when Enter is pressed. Returns the char index of
+    /// the first cell of the new row.
+    pub fn try_convert_table(&mut self, block_idx: usize) -> Option<usize> {
+        let block = self.blocks.get_mut(block_idx)?;
+        if block.ty != BlockType::Paragraph {
+            return None;
+        }
+        let row = block.text.trim();
+        let inner = row.strip_prefix('|')?.strip_suffix('|')?;
+        if inner.is_empty() || row.contains('\n') {
+            return None;
+        }
+        let header: Vec<&str> = inner.split('|').map(str::trim).collect();
+
+        let mut text = header.join("\t");
+        let first_cell = text.chars().count() + 1;
+        text.push('\n');
+        text.push_str(&"\t".repeat(header.len() - 1));
+        block.ty = BlockType::Table;
+        block.columns = vec![ColumnAlign::None; header.len()];
+        block.styles = vec![StyleSpan {
+            len: text.chars().count(),
+            style: StyleBits::default(),
+            link: None,
+        }];
+        block.text = text.into();
+        block.mark_dirty();
+        self.mark_modified();
+        Some(first_cell)
+    }
+
+    /// Inserts an empty row before `row`. Row 0 is the header, so `row` is at least 1.
+    pub fn insert_table_row(&mut self, block_idx: usize, row: usize) -> bool {
+        let Some(block) = self.blocks.get(block_idx) else {
+            return false;
+        };
+        if block.ty != BlockType::Table {
+            return false;
+        }
+        let rows = block.table_cells();
+        let empty = "\t".repeat(rows[0].len() - 1);
+        let row = row.clamp(1, rows.len());
+        if row < rows.len() {
+            self.insert_raw(block_idx, rows[row][0].start, &format!("{empty}\n"));
+        } else {
+            let end = block.text_len();
+            self.insert_raw(block_idx, end, &format!("\n{empty}"));
+        }
+        true
+    }
+
+    /// Removes a body row. The header row cannot be removed.
+    pub fn remove_table_row(&mut self, block_idx: usize, row: usize) -> bool {
+        let Some(block) = self.blocks.get(block_idx) else {
+            return false;
+        };
+        let rows = block.table_cells();
+        if block.ty != BlockType::Table || row == 0 || row >= rows.len() {
+            return false;
         }
+        // Le saut de ligne qui précède la ligne part avec elle
+        let start = rows[row - 1].last().unwrap().end;
+        let end = rows[row].last().unwrap().end;
+        self.remove_text(block_idx, start, end);
+        true
+    }
 
-        self.temp_markdown_buf.clear();
-        self.blocks[block_idx].write_markdown_to(&mut self.temp_markdown_buf);
-        let text = &self.temp_markdown_buf;
-        log::info!("apply_inline_formatting: text = '{}'", text);
+    /// Inserts an empty column before `col` (`col` past the last column appends one).
+    pub fn insert_table_column(&mut self, block_idx: usize, col: usize) -> bool {
+        let Some(block) = self.blocks.get(block_idx) else {
+            return false;
+        };
+        if block.ty != BlockType::Table {
+            return false;
+        }
+        let rows = block.table_cells();
+        let col = col.min(rows[0].len());
+        for cells in rows.iter().rev() {
+            match cells.get(col) {
+                Some(cell) => self.insert_raw(block_idx, cell.start, "\t"),
+                None => self.insert_raw(block_idx, cells.last().unwrap().end, "\t"),
+            };
+        }
+        let columns = &mut self.blocks[block_idx].columns;
+        columns.insert(col.min(columns.len()), ColumnAlign::None);
+        true
+    }
 
-        if !text.contains('*') && !text.contains('`') {
+    /// Removes a column, with its cells. The last column cannot be removed.
+    pub fn remove_table_column(&mut self, block_idx: usize, col: usize) -> bool {
+        let Some(block) = self.blocks.get(block_idx) else {
+            return false;
+        };
+        let rows = block.table_cells();
+        if block.ty != BlockType::Table || rows[0].len() < 2 || col >= rows[0].len() {
             return false;
         }
+        for cells in rows.iter().rev() {
+            // La cellule et la tabulation qui la sépare de sa voisine
+            let range = if col > 0 {
+                cells[col - 1].end..cells[col].end
+            } else {
+                cells[0].start..cells[1].start
+            };
+            self.remove_text(block_idx, range.start, range.end);
+        }
+        let columns = &mut self.blocks[block_idx].columns;
+        if col < columns.len() {
+            columns.remove(col);
+        }
+        true
+    }
 
-        self.temp_char_buf.clear();
-        self.temp_char_buf.extend(text.chars());
-        let chars = &self.temp_char_buf;
-        let len = chars.len();
+    /// Turns the Markdown typed just before `end` (`*a*`, `**a**`, `` `a` ``, `[a](url)`...)
+    /// into styles. Only a closing marker ending at `end`, after a word, with its opening
+    /// marker at the start of a word, is converted: `2*3*4` and markers already in the
+    /// text stay as typed. Returns the number of characters removed before `end`.
+    pub fn apply_inline_formatting(&mut self, block_idx: usize, end: usize) -> usize {
+        if block_idx >= self.blocks.len() || self.blocks[block_idx].ty == BlockType::CodeBlock {
+            return 0;
+        }
+        if self.blocks[block_idx].text.starts_with("```") {
+            // Clôture de code en cours de saisie, convertie à l'appui sur Entrée
+            return 0;
+        }
 
-        let mut new_styles: Vec<StyleSpan> = Vec::new();
-        let mut new_text = String::with_capacity(text.len());
+        let mut removed = 0;
+        // `***a***` : le gras puis l'italique
+        while let Some(n) = self.apply_closing_marker(block_idx, end - removed) {
+            removed += n;
+        }
+        if removed > 0 {
+            log::info!(
+                "apply_inline_formatting: '{}', styles = {:?}",
+                self.blocks[block_idx].text,
+                self.blocks[block_idx].styles
+            );
+        }
+        removed
+    }
 
-        let mut i = 0;
-        let mut is_bold = false;
-        let mut is_italic = false;
-        let is_code = false;
-        let mut changed = false;
-        let mut pending_len = 0;
+    // Convertit une seule paire de marqueurs fermée à `end`, renvoie les caractères retirés
+    fn apply_closing_marker(&mut self, block_idx: usize, end: usize) -> Option<usize> {
+        let block = &self.blocks[block_idx];
+        self.temp_char_buf.clear();
+        self.temp_char_buf.extend(block.text.chars());
+        let chars = &self.temp_char_buf;
+        if end == 0 || end > chars.len() {
+            return None;
+        }
 
-        let mut push_segment = |count: usize, b: bool, it: bool, c: bool| {
-            if count == 0 {
-                return;
-            }
-            if let Some(last) = new_styles.last_mut() {
-                if last.style.is_bold == b && last.style.is_italic == it && last.style.is_code == c
-                {
-                    last.len += count;
-                    return;
-                }
-            }
-            new_styles.push(StyleSpan {
-                len: count,
-                style: StyleBits {
-                    is_bold: b,
-                    is_italic: it,
-                    is_code: c,
-                },
-            });
+        // Caractères déjà en code : aucun marqueur n'y est reconnu
+        let mut in_code = Vec::with_capacity(chars.len());
+        for span in &block.styles {
+            in_code.extend(std::iter::repeat_n(span.style.is_code, span.len));
+        }
+        // Dans un tableau, les marqueurs ne débordent pas de la cellule
+        let floor = if block.ty == BlockType::Table {
+            (0..end)
+                .rev()
+                .find(|&p| matches!(chars[p], '\t' | '\n'))
+                .map_or(0, |p| p + 1)
+        } else {
+            0
+        };
+        let is_text = |from: usize, to: usize| {
+            from >= floor && (from..to).all(|i| !in_code.get(i).copied().unwrap_or(false))
         };
 
-        while i < len {
-            if !is_code && chars[i] == '`' {
-                let mut j = i + 1;
-                while j < len && chars[j] != '`' {
-                    j += 1;
-                }
-                if j < len {
-                    push_segment(pending_len, is_bold, is_italic, is_code);
-                    pending_len = 0;
-                    for &c in chars.iter().take(j).skip(i + 1) {
-                        new_text.push(c);
-                    }
-                    push_segment(j - (i + 1), false, false, true);
-                    i = j + 1;
-                    changed = true;
-                    continue;
+        if chars[end - 1] == ')' {
+            let link = (0..end)
+                .rev()
+                .filter(|&p| chars[p] == '[' && is_text(p, end))
+                .find_map(|p| {
+                    find_link(chars, p)
+                        .filter(|l| l.2 == end - 1)
+                        .map(|l| (p, l))
+                });
+            if let Some((open, (close, dest, _))) = link {
+                if close > open + 1 {
+                    self.set_link(block_idx, open + 1, close, Some(&dest));
+                    self.delete_range((block_idx, close), (block_idx, end));
+                    self.delete_range((block_idx, open), (block_idx, open + 1));
+                    return Some(end - close + 1);
                 }
             }
+            return None;
+        }
 
-            if !is_code && i + 1 < len && chars[i] == '*' && chars[i + 1] == '*' {
-                let mut has_closing = false;
-                if !is_bold {
-                    let mut k = i + 2;
-                    while k + 1 < len {
-                        if chars[k] == '*' && chars[k + 1] == '*' {
-                            has_closing = true;
-                            break;
-                        }
-                        k += 1;
-                    }
-                } else {
-                    has_closing = true;
-                }
-
-                if has_closing {
-                    push_segment(pending_len, is_bold, is_italic, is_code);
-                    pending_len = 0;
-                    is_bold = !is_bold;
-                    i += 2;
-                    changed = true;
-                    continue;
-                }
-                // If not valid bold, fall through to check single * for italic
+        if chars[end - 1] == '`' && is_text(end - 1, end) {
+            let open = (0..end - 1).rev().find(|&p| chars[p] == '`')?;
+            if open + 1 == end - 1 || !is_text(open, end) {
+                return None;
             }
+            self.set_style(block_idx, open + 1, end - 1, InlineStyle::Code, true);
+            self.delete_range((block_idx, end - 1), (block_idx, end));
+            self.delete_range((block_idx, open), (block_idx, open + 1));
+            return Some(2);
+        }
 
-            if !is_code && chars[i] == '*' {
-                let mut has_closing = false;
-                if !is_italic {
-                    let mut k = i + 1;
-                    while k < len {
-                        if chars[k] == '*' {
-                            has_closing = true;
-                            break;
-                        }
-                        k += 1;
-                    }
-                } else {
-                    has_closing = true;
-                }
-
-                if has_closing {
-                    push_segment(pending_len, is_bold, is_italic, is_code);
-                    pending_len = 0;
-                    is_italic = !is_italic;
-                    i += 1;
-                    changed = true;
-                    continue;
-                }
+        for inline in INLINE_MARKERS {
+            let marker = inline.marker();
+            let len = marker.len();
+            if end < len + 2
+                || !marker_at(chars, end - len, marker)
+                || !is_text(end - len, end)
+                || chars[end - len - 1].is_whitespace()
+            {
+                continue;
+            }
+            let close = end - len;
+            let open = (0..close - len).rev().find(|&p| {
+                marker_at(chars, p, marker)
+                    && is_text(p, p + len)
+                    && is_word_start(chars, p)
+                    && !chars[p + len].is_whitespace()
+            });
+            if let Some(open) = open {
+                self.set_style(block_idx, open + len, close, inline, true);
+                self.delete_range((block_idx, close), (block_idx, end));
+                self.delete_range((block_idx, open), (block_idx, open + len));
+                return Some(2 * len);
             }
-
-            new_text.push(chars[i]);
-            pending_len += 1;
-            i += 1;
         }
+        None
+    }
 
-        push_segment(pending_len, is_bold, is_italic, is_code);
-
-        if changed {
-            log::info!("Result: new_text = '{}', is_bold={}, is_italic={}", new_text, new_styles.iter().any(|s| s.style.is_bold), new_styles.iter().any(|s| s.style.is_italic));
-            let block = &mut self.blocks[block_idx];
-            block.text = new_text;
-            block.styles = new_styles;
-            block.mark_dirty();
+    pub fn insert_text_at(&mut self, block_idx: usize, char_idx: usize, text: &str) -> usize {
+        // Une tabulation ou un saut de ligne casserait la grille d'un tableau
+        if self
+            .blocks
+            .get(block_idx)
+            .is_some_and(|b| b.ty == BlockType::Table)
+            && text.contains(['\t', '\r', '\n'])
+        {
+            let text = text.replace(['\t', '\r', '\n'], " ");
+            return self.insert_raw(block_idx, char_idx, &text);
         }
-
-        changed
+        self.insert_raw(block_idx, char_idx, text)
     }
 
-    pub fn insert_text_at(&mut self, block_idx: usize, char_idx: usize, text: &str) -> usize {
+    fn insert_raw(&mut self, block_idx: usize, char_idx: usize, text: &str) -> usize {
         if block_idx >= self.blocks.len() {
             return 0;
         }
+        self.mark_modified();
         let block = &mut self.blocks[block_idx];
         block.mark_dirty();
 
-        let byte_idx = block
-            .text
-            .char_indices()
-            .nth(char_idx)
-            .map(|(i, _)| i)
-            .unwrap_or(block.text.len());
+        let byte_idx = block.text.char_to_byte(char_idx);
         block.text.insert_str(byte_idx, text);
 
         let added_len = text.chars().count();
@@ -395,7 +1157,11 @@ impl Document {
         let mut inserted_style = false;
 
         for span in &mut block.styles {
-            if char_idx <= current_idx + span.len {
+            let span_end = current_idx + span.len;
+            // Le texte tapé juste après un lien, une image ou un saut de ligne forcé
+            // n'en fait pas partie
+            let closed = span.link.is_some() || span.style.is_hard_break;
+            if char_idx < span_end || (char_idx == span_end && !closed) {
                 span.len += added_len;
                 inserted_style = true;
                 break;
@@ -404,13 +1170,20 @@ impl Document {
         }
 
         if !inserted_style {
-            if let Some(last) = block.styles.last_mut() {
-                last.len += added_len;
-            } else {
-                block.styles.push(StyleSpan {
-                    len: added_len,
-                    style: StyleBits::default(),
-                });
+            match block.styles.last_mut() {
+                Some(last) if last.link.is_none() && !last.style.is_hard_break => {
+                    last.len += added_len
+                }
+                last => {
+                    let mut style = last.map(|s| s.style).unwrap_or_default();
+                    style.is_image = false;
+                    style.is_hard_break = false;
+                    block.styles.push(StyleSpan {
+                        len: added_len,
+                        style,
+                        link: None,
+                    });
+                }
             }
         }
 
@@ -418,21 +1191,42 @@ impl Document {
     }
 
     pub fn remove_char_at(&mut self, block_idx: usize, char_idx: usize) -> bool {
+        // Les séparateurs de cellules ne s'effacent pas
+        if self.blocks.get(block_idx).is_some_and(|b| {
+            b.ty == BlockType::Table && matches!(b.text.char_at(char_idx), Some('\t' | '\n'))
+        }) {
+            return false;
+        }
+        self.remove_char(block_idx, char_idx)
+    }
+
+    /// Removes the whole grapheme cluster starting at `char_idx`. Returns the number
+    /// of chars removed.
+    pub fn remove_grapheme_at(&mut self, block_idx: usize, char_idx: usize) -> usize {
+        let Some(block) = self.blocks.get(block_idx) else {
+            return 0;
+        };
+        let end = block.next_grapheme(char_idx);
+        if end <= char_idx || !self.remove_char_at(block_idx, char_idx) {
+            return 0;
+        }
+        for _ in char_idx + 1..end {
+            self.remove_char(block_idx, char_idx);
+        }
+        end - char_idx
+    }
+
+    fn remove_char(&mut self, block_idx: usize, char_idx: usize) -> bool {
         if block_idx >= self.blocks.len() {
             return false;
         }
         let block = &mut self.blocks[block_idx];
 
-        if char_idx >= block.text.chars().count() {
+        if char_idx >= block.text_len() {
             return false;
         }
 
-        let byte_idx = block
-            .text
-            .char_indices()
-            .nth(char_idx)
-            .map(|(i, _)| i)
-            .unwrap();
+        let byte_idx = block.text.char_to_byte(char_idx);
         block.text.remove(byte_idx);
         block.mark_dirty();
 
@@ -456,10 +1250,12 @@ impl Document {
                 block.styles.push(StyleSpan {
                     len: 0,
                     style: StyleBits::default(),
+                    link: None,
                 });
             }
         }
 
+        self.mark_modified();
         true
     }
 
@@ -474,122 +1270,247 @@ impl Document {
             return false;
         }
 
-        self.insert_text_at(block_idx, end, marker);
-
-        self.insert_text_at(block_idx, start, marker);
-
-        self.apply_inline_formatting(block_idx)
+        let style = match marker {
+            "*" | "_" => InlineStyle::Italic,
+            "`" => InlineStyle::Code,
+            _ => {
+                self.insert_text_at(block_idx, end, marker);
+                self.insert_text_at(block_idx, start, marker);
+                return false;
+            }
+        };
+        self.toggle_formatting(block_idx, start, end, style);
+        true
     }
 
     pub fn merge_block_with_prev(&mut self, block_idx: usize) -> Option<usize> {
         if block_idx == 0 || block_idx >= self.blocks.len() {
             return None;
         }
-        let block = self.blocks.remove(block_idx);
-        let prev_block = &mut self.blocks[block_idx - 1];
-        let offset = prev_block.text_len();
-        prev_block.text.push_str(&block.text);
-        prev_block.styles.extend(block.styles);
-        prev_block.mark_dirty();
+        if self.blocks[block_idx - 1..=block_idx]
+            .iter()
+            .any(|b| b.ty == BlockType::Table)
+        {
+            return None;
+        }
+        self.join_blocks(block_idx - 1)
+    }
+
+    /// Splits the block at `char_idx`: the text after it moves, with its styles, to a
+    /// new block of type `ty` inserted right after. Returns the index of the new block.
+    pub fn split_block(&mut self, block_idx: usize, char_idx: usize, ty: BlockType) -> usize {
+        let mut new_block = Block::new(self.generate_id(), ty, "");
+        let block = &mut self.blocks[block_idx];
+        let char_idx = char_idx.min(block.text_len());
+        let byte_idx = block.text.char_to_byte(char_idx);
+        new_block.text = block.text.split_off(byte_idx);
+        new_block.styles = split_styles(&mut block.styles, char_idx);
+        block.mark_dirty();
+        self.blocks.insert(block_idx + 1, new_block);
+        self.mark_modified();
+        block_idx + 1
+    }
+
+    /// Appends the block after `block_idx` to it, with its styles, and removes it.
+    /// Returns the char index where the appended text starts.
+    pub fn join_blocks(&mut self, block_idx: usize) -> Option<usize> {
+        if block_idx + 1 >= self.blocks.len() {
+            return None;
+        }
+        let next = self.blocks.remove(block_idx + 1);
+        let block = &mut self.blocks[block_idx];
+        let offset = block.text_len();
+        block.text.push_str(&next.text);
+        join_styles(&mut block.styles, next.styles);
+        block.mark_dirty();
+        self.mark_modified();
         Some(offset)
     }
 
+    /// Inserts text that may span several lines: the first line goes into the block,
+    /// each following line becomes a paragraph, and the text after `char_idx` ends up
+    /// after the last line. Returns the position at the end of the inserted text.
+    pub fn insert_lines(
+        &mut self,
+        block_idx: usize,
+        char_idx: usize,
+        text: &str,
+    ) -> (usize, usize) {
+        // Un bloc de code garde les retours à la ligne, un tableau les remplace
+        let ty = &self.blocks[block_idx].ty;
+        if !text.contains('\n') || matches!(ty, BlockType::CodeBlock | BlockType::Table) {
+            let added = self.insert_text_at(block_idx, char_idx, text);
+            return (block_idx, char_idx + added);
+        }
+        let mut lines = text.split('\n');
+        let first = lines.next().unwrap_or_default();
+        let tail_idx = self.split_block(block_idx, char_idx, BlockType::Paragraph);
+        self.insert_text_at(block_idx, char_idx, first);
+
+        let paragraphs: Vec<Block> = lines
+            .map(|line| Block::new(self.generate_id(), BlockType::Paragraph, line))
+            .collect();
+        let last_idx = tail_idx + paragraphs.len() - 1;
+        let end = paragraphs[paragraphs.len() - 1].text_len();
+        self.blocks.splice(tail_idx..tail_idx, paragraphs);
+        // Le texte qui suivait la position rejoint la dernière ligne, avec ses styles
+        self.join_blocks(last_idx);
+        (last_idx, end)
+    }
+
+    /// Inserts Markdown as typed blocks with their styles. The first block merges into
+    /// the one at `block_idx`, and the text after `char_idx` joins the last one.
+    /// Returns the position at the end of the inserted text.
+    pub fn insert_markdown(
+        &mut self,
+        block_idx: usize,
+        char_idx: usize,
+        source: &str,
+    ) -> (usize, usize) {
+        let ty = &self.blocks[block_idx].ty;
+        if matches!(ty, BlockType::CodeBlock | BlockType::Table) {
+            return self.insert_lines(block_idx, char_idx, source);
+        }
+        let blocks = parser::parse_markdown(source, self.next_id);
+        if blocks.is_empty() {
+            return (block_idx, char_idx);
+        }
+        self.next_id += blocks.len() as u64;
+        // Un bloc de code ou un tableau ne reçoit pas de texte d'un autre bloc
+        let mergeable = |b: &Block| !matches!(b.ty, BlockType::CodeBlock | BlockType::Table);
+        let merge_first = mergeable(&blocks[0]);
+        let merge_last = mergeable(&blocks[blocks.len() - 1]);
+
+        let count = blocks.len();
+        let tail_idx = self.split_block(block_idx, char_idx, BlockType::Paragraph);
+        self.blocks.splice(tail_idx..tail_idx, blocks);
+        let mut last_idx = tail_idx + count - 1;
+        let mut end = self.blocks[last_idx].text_len();
+
+        // La suite du bloc rejoint le dernier bloc collé
+        if merge_last {
+            self.join_blocks(last_idx);
+        } else if self.blocks[last_idx + 1].text.is_empty() {
+            self.blocks.remove(last_idx + 1);
+        }
+        // Le premier bloc collé rejoint le début du bloc, ou le remplace s'il est vide
+        let head = &self.blocks[block_idx];
+        if head.text.is_empty() && head.ty == BlockType::Paragraph {
+            self.blocks.remove(block_idx);
+            last_idx -= 1;
+        } else if merge_first {
+            let offset = self.join_blocks(block_idx).unwrap_or(0);
+            if last_idx == tail_idx {
+                end += offset;
+            }
+            last_idx -= 1;
+        }
+        (last_idx, end)
+    }
+
     pub fn delete_range(&mut self, start: (usize, usize), end: (usize, usize)) -> (usize, usize) {
         let (start_blk, start_char) = start;
         let (end_blk, end_char) = end;
 
         if start_blk == end_blk {
-            if start_char == end_char {
-                return (start_blk, start_char);
-            }
-            let block = &mut self.blocks[start_blk];
-            
-            // Calculate byte offsets for efficient removal
-            let start_byte = block
-                .text
-                .char_indices()
-                .nth(start_char)
-                .map(|(i, _)| i)
-                .unwrap_or(block.text.len());
-            let end_byte = block
-                .text
-                .char_indices()
-                .nth(end_char)
-                .map(|(i, _)| i)
-                .unwrap_or(block.text.len());
-
-            block.text.replace_range(start_byte..end_byte, "");
-            block.mark_dirty();
+            self.remove_chars(start_blk, start_char, end_char);
+            return (start_blk, start_char);
+        }
+        // Fin du premier bloc, début du dernier, blocs du milieu : chacun en une fois
+        let first_len = self.blocks[start_blk].text_len();
+        self.remove_chars(start_blk, start_char, first_len);
+        self.remove_chars(end_blk, 0, end_char);
+        self.blocks.drain(start_blk + 1..end_blk);
+        self.mark_modified();
+        let keeps_table = self.blocks[start_blk].ty == BlockType::Table
+            || self
+                .blocks
+                .get(start_blk + 1)
+                .is_some_and(|b| b.ty == BlockType::Table);
+        if !keeps_table {
+            self.join_blocks(start_blk);
+        }
+        (start_blk, start_char)
+    }
+
+    /// Removes the chars between `start_char` and `end_char` of one block in a single
+    /// pass, styles included.
+    pub fn remove_chars(&mut self, block_idx: usize, start_char: usize, end_char: usize) {
+        if start_char >= end_char {
+            return;
+        }
+        if self.blocks[block_idx].ty == BlockType::Table {
+            // Le contenu des cellules est effacé, la grille reste
+            let cells = self.blocks[block_idx].table_cells();
+            for cell in cells.iter().flatten().rev() {
+                let start = cell.start.max(start_char);
+                let end = cell.end.min(end_char);
+                if start < end {
+                    self.remove_text(block_idx, start, end);
+                }
+            }
+            return;
+        }
+        self.remove_text(block_idx, start_char, end_char);
+    }
+
+    // Retire une plage de caractères et ses styles en une fois, séparateurs compris
+    fn remove_text(&mut self, block_idx: usize, start_char: usize, end_char: usize) {
+        self.mark_modified();
+        let block = &mut self.blocks[block_idx];
+
+        // Calculate byte offsets for efficient removal
+        let start_byte = block.text.char_to_byte(start_char);
+        let end_byte = block.text.char_to_byte(end_char);
+
+        block.text.replace_range(start_byte..end_byte, "");
+        block.mark_dirty();
 
-            // Rebuild styles efficiently
-            let mut new_styles = Vec::with_capacity(block.styles.len());
-            let mut current_idx = 0;
+        // Rebuild styles efficiently
+        let mut new_styles = Vec::with_capacity(block.styles.len());
+        let mut current_idx = 0;
 
-            for span in &block.styles {
-                let span_start = current_idx;
-                let span_end = current_idx + span.len;
+        for span in &block.styles {
+            let span_start = current_idx;
+            let span_end = current_idx + span.len;
 
-                if span_end <= start_char {
-                    // Span is entirely before deleted range
-                    new_styles.push(span.clone());
-                } else if span_start >= end_char {
-                    // Span is entirely after deleted range
-                    new_styles.push(span.clone());
+            if span_end <= start_char {
+                // Span is entirely before deleted range
+                new_styles.push(span.clone());
+            } else if span_start >= end_char {
+                // Span is entirely after deleted range
+                new_styles.push(span.clone());
+            } else {
+                // Span overlaps with deleted range
+                let keep_start = if span_start < start_char {
+                    start_char - span_start
                 } else {
-                    // Span overlaps with deleted range
-                    let keep_start = if span_start < start_char {
-                        start_char - span_start
-                    } else {
-                        0
-                    };
-                    let keep_end = if span_end > end_char {
-                        span_end - end_char
-                    } else {
-                        0
-                    };
-                    
-                    let new_len = keep_start + keep_end;
-                    if new_len > 0 {
-                        let mut new_span = span.clone();
-                        new_span.len = new_len;
-                        new_styles.push(new_span);
-                    }
-                }
-                current_idx += span.len;
-            }
+                    0
+                };
+                let keep_end = if span_end > end_char {
+                    span_end - end_char
+                } else {
+                    0
+                };
 
-            if new_styles.is_empty() {
-                new_styles.push(StyleSpan {
-                    len: 0,
-                    style: StyleBits::default(),
-                });
-            }
-            block.styles = new_styles;
-            
-            (start_blk, start_char)
-        } else {
-            let first_len = self.blocks[start_blk].text_len();
-            for _ in start_char..first_len {
-                self.remove_char_at(start_blk, start_char);
-            }
-            for _ in 0..end_char {
-                self.remove_char_at(end_blk, 0);
-            }
-            if end_blk > start_blk + 1 {
-                let to_remove = end_blk - start_blk - 1;
-                for _ in 0..to_remove {
-                    self.blocks.remove(start_blk + 1);
+                let new_len = keep_start + keep_end;
+                if new_len > 0 {
+                    let mut new_span = span.clone();
+                    new_span.len = new_len;
+                    new_styles.push(new_span);
                 }
             }
-            if start_blk + 1 < self.blocks.len() {
-                let next_block = self.blocks.remove(start_blk + 1);
-                let prev = &mut self.blocks[start_blk];
-                prev.text.push_str(&next_block.text);
-                prev.styles.extend(next_block.styles);
-                prev.mark_dirty();
-            }
-            (start_blk, start_char)
+            current_idx += span.len;
+        }
+
+        if new_styles.is_empty() {
+            new_styles.push(StyleSpan {
+                len: 0,
+                style: StyleBits::default(),
+                link: None,
+            });
         }
+        block.styles = new_styles;
     }
 
     pub fn toggle_formatting(
@@ -597,102 +1518,102 @@ impl Document {
         block_idx: usize,
         start: usize,
         end: usize,
-        style_type: u8,
+        style: InlineStyle,
     ) {
         if block_idx >= self.blocks.len() {
             return;
         }
 
-        self.split_span_at(block_idx, end);
-        self.split_span_at(block_idx, start);
-
-        let block = &mut self.blocks[block_idx];
-
         let mut all_match = true;
         let mut current_idx = 0;
-        let mut start_idx = None;
-        let mut end_idx = None;
-
-        for (i, span) in block.styles.iter().enumerate() {
+        for span in &self.blocks[block_idx].styles {
             let s_start = current_idx;
             let s_end = current_idx + span.len;
-
-            if s_end > start && s_start < end {
-                if start_idx.is_none() {
-                    start_idx = Some(i);
-                }
-                end_idx = Some(i);
-
-                let is_on = match style_type {
-                    0 => span.style.is_bold,
-                    1 => span.style.is_italic,
-                    2 => span.style.is_code,
-                    _ => false,
-                };
-                if !is_on {
-                    all_match = false;
-                }
+            if s_end > start && s_start < end && !span.style.has(style) {
+                all_match = false;
             }
             current_idx += span.len;
         }
 
-        if let (Some(first), Some(last)) = (start_idx, end_idx) {
-            let turn_on = !all_match;
-            for i in first..=last {
-                if turn_on {
-                    // Exclusive mode: Disable others when enabling one
-                    match style_type {
-                        0 => {
-                            // Bold
-                            block.styles[i].style.is_bold = true;
-                            block.styles[i].style.is_italic = false;
-                            block.styles[i].style.is_code = false;
-                        }
-                        1 => {
-                            // Italic
-                            block.styles[i].style.is_bold = false;
-                            block.styles[i].style.is_italic = true;
-                            block.styles[i].style.is_code = false;
-                        }
-                        2 => {
-                            // Code
-                            block.styles[i].style.is_bold = false;
-                            block.styles[i].style.is_italic = false;
-                            block.styles[i].style.is_code = true;
-                        }
-                        _ => {}
-                    }
-                } else {
-                    // Toggling off: Just disable the target style
-                    match style_type {
-                        0 => block.styles[i].style.is_bold = false,
-                        1 => block.styles[i].style.is_italic = false,
-                        2 => block.styles[i].style.is_code = false,
-                        _ => {}
-                    }
-                }
+        self.set_style(block_idx, start, end, style, !all_match);
+    }
+
+    /// Turns `style` on or off for the characters `start..end` of a block.
+    pub fn set_style(
+        &mut self,
+        block_idx: usize,
+        start: usize,
+        end: usize,
+        style: InlineStyle,
+        on: bool,
+    ) {
+        if block_idx >= self.blocks.len() {
+            return;
+        }
+
+        self.split_span_at(block_idx, end);
+        self.split_span_at(block_idx, start);
+
+        let block = &mut self.blocks[block_idx];
+        let mut current_idx = 0;
+        for span in &mut block.styles {
+            if current_idx + span.len > start && current_idx < end {
+                span.style.set(style, on);
             }
+            current_idx += span.len;
         }
 
         block.mark_dirty();
 
-        let mut new_styles = Vec::new();
-        if !block.styles.is_empty() {
-            let mut current = block.styles[0].clone();
-            for next in block.styles.iter().skip(1) {
-                if current.style.is_bold == next.style.is_bold
-                    && current.style.is_italic == next.style.is_italic
-                    && current.style.is_code == next.style.is_code
-                {
-                    current.len += next.len;
-                } else {
-                    new_styles.push(current);
-                    current = next.clone();
-                }
+        merge_adjacent_spans(&mut block.styles);
+        self.mark_modified();
+    }
+
+    /// Sets the link of the characters `start..end` of a block, or removes it with `None`.
+    pub fn set_link(&mut self, block_idx: usize, start: usize, end: usize, url: Option<&str>) {
+        if block_idx >= self.blocks.len() || start >= end {
+            return;
+        }
+
+        self.split_span_at(block_idx, end);
+        self.split_span_at(block_idx, start);
+
+        let block = &mut self.blocks[block_idx];
+        let mut current_idx = 0;
+        for span in &mut block.styles {
+            if current_idx >= start && current_idx + span.len <= end {
+                span.link = url.map(String::from);
             }
-            new_styles.push(current);
-            block.styles = new_styles;
+            current_idx += span.len;
+        }
+        block.mark_dirty();
+        merge_adjacent_spans(&mut block.styles);
+        self.mark_modified();
+    }
+
+    /// Link at a character position: its char range in the block and its destination.
+    pub fn link_at(&self, block_idx: usize, char_idx: usize) -> Option<(usize, usize, String)> {
+        let block = self.blocks.get(block_idx)?;
+        let mut ranges = Vec::with_capacity(block.styles.len());
+        let mut pos = 0;
+        for span in &block.styles {
+            ranges.push((pos, pos + span.len, span.link.as_deref()));
+            pos += span.len;
+        }
+
+        let i = ranges.iter().position(|&(start, end, link)| {
+            link.is_some() && start <= char_idx && char_idx <= end
+        })?;
+        let url = ranges[i].2;
+        let mut first = i;
+        while first > 0 && ranges[first - 1].2 == url {
+            first -= 1;
+        }
+        let mut last = i;
+        while last + 1 < ranges.len() && ranges[last + 1].2 == url {
+            last += 1;
         }
+        Some((ranges[first].0, ranges[last].1, url?.to_string()))
     }
 
     fn split_span_at(&mut self, block_idx: usize, char_pos: usize) {
@@ -722,15 +1643,61 @@ impl Document {
         }
     }
 
+    // Blocs de la sélection, coupés à ses bornes
+    fn blocks_in_range(&self, start: (usize, usize), end: (usize, usize)) -> Vec<Block> {
+        let (start_blk, start_char) = start;
+        let (end_blk, end_char) = end;
+        let mut doc = Document::from_blocks(self.blocks[start_blk..=end_blk].to_vec());
+        let last = doc.blocks.len() - 1;
+        let last_len = doc.blocks[last].text_len();
+        doc.remove_chars(last, end_char, last_len);
+        doc.remove_chars(0, 0, start_char);
+
+        let first = &mut doc.blocks[0];
+        first.syntax.separator = None;
+        // Une partie d'un seul bloc se copie sans sa syntaxe de bloc ; du code reste du code
+        if start_blk == end_blk && (start_char > 0 || end_char < last_len) {
+            if first.ty == BlockType::CodeBlock {
+                for span in &mut first.styles {
+                    span.style.set(InlineStyle::Code, true);
+                }
+            }
+            if first.ty != BlockType::Table {
+                first.ty = BlockType::Paragraph;
+                first.checked = false;
+                first.set_indent(0);
+            }
+            first.syntax.container_prefix = None;
+        }
+        // Les listes copiées commencent au premier niveau
+        let lists = doc.blocks.iter().filter(|b| b.is_list_item());
+        let min_indent = lists.map(|b| b.indent).min().unwrap_or(0);
+        for block in &mut doc.blocks {
+            block.set_indent(block.indent.saturating_sub(min_indent));
+        }
+        doc.blocks
+    }
+
+    /// Markdown of a selection: block syntax and inline styles as they would be saved.
+    pub fn get_markdown_in_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let mut doc = Document::from_blocks(self.blocks_in_range(start, end));
+        doc.trailing_newlines = 0;
+        doc.to_markdown()
+    }
+
+    /// HTML of a selection, for applications that paste rich text.
+    pub fn get_html_in_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        html::blocks_to_html(&self.blocks_in_range(start, end))
+    }
+
     pub fn get_text_in_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
         let (start_blk, start_char) = start;
         let (end_blk, end_char) = end;
 
         if start_blk == end_blk {
             let text = &self.blocks[start_blk].text;
-            let chars: Vec<char> = text.chars().collect();
-            if start_char < chars.len() && end_char <= chars.len() {
-                return chars[start_char..end_char].iter().collect();
+            if start_char < text.len_chars() && end_char <= text.len_chars() {
+                return text.slice_chars(start_char..end_char).to_string();
             }
             return String::new();
         }
@@ -739,9 +1706,8 @@ impl Document {
 
         // First block
         let text = &self.blocks[start_blk].text;
-        let chars: Vec<char> = text.chars().collect();
-        if start_char < chars.len() {
-            result.push_str(&chars[start_char..].iter().collect::<String>());
+        if start_char < text.len_chars() {
+            result.push_str(text.slice_chars(start_char..text.len_chars()));
         }
         result.push('\n');
 
@@ -753,9 +1719,8 @@ impl Document {
 
         // Last block
         let text = &self.blocks[end_blk].text;
-        let chars: Vec<char> = text.chars().collect();
-        if end_char <= chars.len() {
-            result.push_str(&chars[..end_char].iter().collect::<String>());
+        if end_char <= text.len_chars() {
+            result.push_str(text.slice_chars(0..end_char));
         }
 
         result
@@ -769,35 +1734,560 @@ mod tests {
     #[test]
     fn test_apply_inline_formatting_italic() {
         let mut doc = Document::default();
-        doc.blocks[0].text = "*text*".to_string();
+        doc.blocks[0].text = "*text*".into();
         doc.blocks[0].styles.clear();
         doc.blocks[0].styles.push(StyleSpan {
             len: 6,
             style: StyleBits::default(),
+            link: None,
         });
 
-        let changed = doc.apply_inline_formatting(0);
+        let removed = doc.apply_inline_formatting(0, 6);
         
-        assert!(changed);
+        assert_eq!(removed, 2);
         assert_eq!(doc.blocks[0].text, "text"); // Expect markers stripped?
         assert!(doc.blocks[0].styles[0].style.is_italic);
     }
 
+    #[test]
+    fn test_markers_need_word_boundaries() {
+        let mut doc = Document::from_markdown(r"2\*3\*4 and a_b");
+        doc.insert_text_at(0, 13, " x ***y***");
+        assert_eq!(doc.apply_inline_formatting(0, 4), 0);
+        assert_eq!(doc.apply_inline_formatting(0, 23), 6);
+        assert_eq!(doc.blocks[0].text, "2*3*4 and a_b x y");
+        assert_eq!(doc.blocks[0].to_markdown(), "2\\*3\\*4 and a_b x ***y***");
+    }
+
     #[test]
     fn test_toggle_formatting_italic() {
         let mut doc = Document::default();
-        doc.blocks[0].text = "text".to_string();
+        doc.blocks[0].text = "text".into();
         doc.blocks[0].styles.clear();
         doc.blocks[0].styles.push(StyleSpan {
             len: 4,
             style: StyleBits::default(),
+            link: None,
         });
 
         // Toggle Italic on "text"
-        doc.toggle_formatting(0, 0, 4, 1);
+        doc.toggle_formatting(0, 0, 4, InlineStyle::Italic);
 
         assert_eq!(doc.blocks[0].text, "text");
         assert!(doc.blocks[0].styles[0].style.is_italic);
         assert!(!doc.blocks[0].styles[0].style.is_bold);
     }
+
+    #[test]
+    fn test_extended_inline_styles() {
+        let mut doc = Document::from_markdown("a ==hot== ~~old~~ take");
+        assert_eq!(doc.blocks[0].text, "a hot old take");
+        doc.insert_text_at(0, 14, " ^now^");
+        assert_eq!(doc.apply_inline_formatting(0, 20), 2);
+        assert_eq!(doc.blocks[0].text, "a hot old take now");
+
+        doc.toggle_formatting(0, 10, 14, InlineStyle::Strikethrough);
+        doc.toggle_formatting(0, 6, 9, InlineStyle::Strikethrough);
+        assert_eq!(doc.blocks[0].to_markdown(), "a ==hot== old ~~take~~ ^now^");
+    }
+
+    #[test]
+    fn test_combined_styles_survive_reload() {
+        let mut doc = Document::from_markdown("one two three four");
+        doc.toggle_formatting(0, 0, 13, InlineStyle::Bold);
+        doc.toggle_formatting(0, 4, 13, InlineStyle::Italic);
+        doc.toggle_formatting(0, 8, 13, InlineStyle::Code);
+        assert_eq!(doc.blocks[0].to_markdown(), "**one *two `three`*** four");
+
+        let reloaded = Document::from_markdown(&doc.blocks[0].to_markdown());
+        assert_eq!(reloaded.blocks[0].text, doc.blocks[0].text);
+        assert_eq!(reloaded.blocks[0].styles, doc.blocks[0].styles);
+    }
+
+    #[test]
+    fn test_links() {
+        let mut doc = Document::from_markdown("see [the **docs**](https://example.com) now");
+        assert_eq!(doc.blocks[0].text, "see the docs now");
+        assert_eq!(
+            doc.link_at(0, 6),
+            Some((4, 12, "https://example.com".to_string()))
+        );
+
+        // Typed right after the link: not part of it
+        doc.insert_text_at(0, 12, "!");
+        doc.set_link(0, 0, 3, Some("notes/a b.md"));
+        assert_eq!(
+            doc.blocks[0].to_markdown(),
+            "[see](<notes/a b.md>) [the **docs**](https://example.com)! now"
+        );
+
+        doc.set_link(0, 0, 3, None);
+        doc.blocks[0].text = "a [typed](x.md) link".into();
+        doc.blocks[0].styles = vec![StyleSpan {
+            len: 20,
+            style: StyleBits::default(),
+            link: None,
+        }];
+        assert_eq!(doc.apply_inline_formatting(0, 15), 8);
+        assert_eq!(doc.blocks[0].text, "a typed link");
+        assert_eq!(doc.link_at(0, 3), Some((2, 7, "x.md".to_string())));
+    }
+
+    // Générateur pseudo-aléatoire (xorshift) : des essais variés mais reproductibles
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n.max(1) as u64) as usize
+        }
+    }
+
+    // Style de chaque caractère visible : les blancs n'en portent pas en Markdown
+    fn visible_styles(block: &Block) -> Vec<(char, StyleBits)> {
+        let mut chars = block.text.chars();
+        let mut styles = Vec::new();
+        for span in &block.styles {
+            for c in chars.by_ref().take(span.len) {
+                if !c.is_whitespace() {
+                    styles.push((c, span.style));
+                }
+            }
+        }
+        styles
+    }
+
+    // Texte relu : sans les blancs en bordure de ligne hors code, ni les lignes qui
+    // n'en avaient que
+    fn without_edge_blanks(block: &Block) -> String {
+        let mut chars = block.text.chars();
+        let mut written: Vec<(char, bool)> = Vec::new();
+        for span in &block.styles {
+            let code = span.style.is_code;
+            written.extend(chars.by_ref().take(span.len).map(|c| (c, code)));
+        }
+        let blank = |&(c, code): &(char, bool)| (c == ' ' || c == '\t') && !code;
+        let lines: Vec<String> = written
+            .split(|&(c, _)| c == '\n')
+            .filter(|line| line.is_empty() || !line.iter().all(blank))
+            .map(|line| {
+                let start = line.iter().take_while(|c| blank(c)).count();
+                let end = line.len() - line.iter().rev().take_while(|c| blank(c)).count();
+                line[start..end].iter().map(|&(c, _)| c).collect()
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    #[test]
+    fn test_typed_text_round_trips() {
+        let samples = [
+            r"2*3*4, **not bold**, a_b, _x_, __y__",
+            r"`tick` and ``two``, ~~no~~ ~sub~, ^sup^, ==mark== but a === b",
+            r"[not](a link) [ref] ![img](x.png) a\b \*already\* end\",
+            r"<https://example.com> <a@b.c> <b>tag</b> <em>x</em> &amp; &#35; &#x41; AT&T;",
+            "# not a heading\n> not a quote\n- not a list\n+ nor this\n1. nor this\n2) nor this",
+            "setext\n===\nand\n---",
+            "#hashtag -dash +plus 3.14 trailing #",
+            "x:(< \n;é",
+        ];
+        let alphabet: Vec<char> = "ab é  *_`~^=[]()!<>&#-+.:;\\|1\n\t".chars().collect();
+        let styles = [
+            InlineStyle::Bold,
+            InlineStyle::Italic,
+            InlineStyle::Strikethrough,
+            InlineStyle::Code,
+            InlineStyle::Highlight,
+            InlineStyle::Superscript,
+            InlineStyle::Subscript,
+        ];
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for round in 0..3000 {
+            let ty = [
+                BlockType::Paragraph,
+                BlockType::ListItem,
+                BlockType::Quote,
+                BlockType::Heading2,
+            ][round % 4]
+                .clone();
+            let mut text: String = match samples.get(round / 4) {
+                Some(sample) => sample.to_string(),
+                None => (0..rng.below(16))
+                    .map(|_| alphabet[rng.below(alphabet.len())])
+                    .collect(),
+            };
+            if ty == BlockType::Heading2 {
+                text = text.replace('\n', " ");
+            }
+            // Pas de ligne vide dans un paragraphe : Entrée en commence un autre
+            while text.contains("\n\n") {
+                text = text.replace("\n\n", "\n");
+            }
+            text = text.trim_matches('\n').to_string();
+            // Tapé, puis mis en forme par endroits
+            let mut doc = Document::from_blocks(vec![Block::new(1, ty.clone(), "")]);
+            doc.insert_text_at(0, 0, &text);
+            for _ in 0..rng.below(4) {
+                let len = doc.blocks[0].text_len();
+                let start = rng.below(len + 1);
+                let end = start + rng.below(len - start + 1);
+                let style = styles[rng.below(styles.len())];
+                // Un code inline ne passe pas à la ligne
+                let multiline = text
+                    .chars()
+                    .skip(start)
+                    .take(end - start)
+                    .any(|c| c == '\n');
+                if style == InlineStyle::Code && multiline {
+                    continue;
+                }
+                doc.toggle_formatting(0, start, end, style);
+            }
+            let saved = doc.to_markdown();
+
+            let reloaded = Document::from_markdown(&saved);
+            assert_eq!(reloaded.blocks.len(), 1, "{saved:?}");
+            assert_eq!(reloaded.blocks[0].ty, ty, "{saved:?}");
+            assert_eq!(
+                reloaded.blocks[0].text,
+                without_edge_blanks(&doc.blocks[0]),
+                "{saved:?}"
+            );
+            assert_eq!(
+                visible_styles(&reloaded.blocks[0]),
+                visible_styles(&doc.blocks[0]),
+                "{saved:?}"
+            );
+        }
+
+        // `:` en italique après un mot : `x*:*` ne serait pas reconnu
+        let mut doc = Document::from_blocks(vec![Block::new(1, BlockType::Paragraph, "")]);
+        doc.insert_text_at(0, 0, "_x: ");
+        doc.toggle_formatting(0, 2, 3, InlineStyle::Italic);
+        assert_eq!(doc.to_markdown(), "\\_x<em>:</em>\n");
+
+        // Blancs en bordure retirés, sans entités dans le texte
+        let mut doc = Document::from_blocks(vec![Block::new(1, BlockType::Paragraph, "")]);
+        doc.insert_text_at(0, 0, "  indented\n \nHello ");
+        assert_eq!(doc.to_markdown(), "indented\nHello\n");
+    }
+
+    #[test]
+    fn test_task_items() {
+        let mut doc = Document::from_markdown("- [ ] todo\n- [x] done\n\n1. [ ] first\n");
+        assert_eq!(doc.blocks[0].ty, BlockType::TaskItem);
+        assert_eq!(doc.blocks[0].text, "todo");
+        assert!(!doc.blocks[0].checked);
+        assert!(doc.blocks[1].checked);
+        assert!(doc.toggle_task(0));
+        assert!(doc.toggle_task(1));
+        assert!(!doc.toggle_task(3));
+        let mut out = Vec::new();
+        doc.write_markdown(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "- [x] todo\n- [ ] done\n\n1. [ ] first\n"
+        );
+
+        // "- " puis "[ ] " tapés au début d'un paragraphe
+        let mut doc = Document::from_blocks(vec![Block::new(1, BlockType::Paragraph, "")]);
+        doc.insert_text_at(0, 0, "- ");
+        assert_eq!(doc.try_convert_block(0), Some(2));
+        doc.insert_text_at(0, 0, "[x] ");
+        assert_eq!(doc.try_convert_block(0), Some(4));
+        assert_eq!(doc.blocks[0].ty, BlockType::TaskItem);
+        assert!(doc.blocks[0].checked);
+        assert_eq!(doc.blocks[0].text, "");
+    }
+
+    #[test]
+    fn test_tables() {
+        let source = "| Name | Qty |\n|:--|--:|\n| **a** \\| b | 1 |\n| `c\\|d` |\n\nafter\n";
+        let mut doc = Document::from_markdown(source);
+        assert_eq!(doc.blocks.len(), 2);
+        let table = &doc.blocks[0];
+        assert_eq!(table.ty, BlockType::Table);
+        assert_eq!(table.columns, vec![ColumnAlign::Left, ColumnAlign::Right]);
+        assert_eq!(table.text, "Name\tQty\na | b\t1\nc|d\t");
+        assert_eq!(table.table_cell_at(11), Some((1, 0)));
+        assert_eq!(round_trip(source), source);
+
+        // La grille résiste à l'effacement
+        assert!(!doc.remove_char_at(0, 4));
+        doc.delete_range((0, 2), (0, 11));
+        assert_eq!(doc.blocks[0].text, "Na\t\n| b\t1\nc|d\t");
+        doc.insert_text_at(0, 2, "me\tX");
+        assert!(doc.insert_table_column(0, 1));
+        assert!(doc.insert_table_row(0, 1));
+        assert!(doc.remove_table_row(0, 3));
+        assert!(!doc.remove_table_row(0, 0));
+        let mut out = Vec::new();
+        doc.write_markdown(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "| Name X |     |     |\n\
+             | :----- | --- | --: |\n\
+             |        |     |     |\n\
+             | \\| b   |     |   1 |\n\nafter\n"
+        );
+        assert!(doc.remove_table_column(0, 1));
+        assert_eq!(doc.blocks[0].columns.len(), 2);
+        assert_eq!(doc.blocks[0].text, "Name X\t\n\t\n| b\t1");
+
+        let mut doc = Document::from_markdown("| a | b |\n");
+        assert_eq!(doc.try_convert_table(0), Some(4));
+        assert_eq!(doc.blocks[0].text, "a\tb\n\t");
+    }
+
+    #[test]
+    fn test_code_span_with_backticks() {
+        let mut doc = Document::from_markdown("run ``a `b` c`` and `` `x` ``");
+        assert_eq!(doc.blocks[0].text, "run a `b` c and `x`");
+        doc.insert_text_at(0, 0, "!");
+        assert_eq!(
+            doc.blocks[0].to_markdown(),
+            "!run ``a `b` c`` and `` `x` ``"
+        );
+    }
+
+    fn round_trip(source: &str) -> String {
+        let doc = Document::from_markdown(source);
+        let mut out = Vec::new();
+        doc.write_markdown(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_unmodified_document_saves_identically() {
+        let sources = [
+            "# Title\n\nSome _text_ and __bold__\non two lines\n",
+            "Title\n=====\n\nSub\n---\n\n###### Six\n",
+            "* a\n* b\n  continued\n\n   + nested\n\n1) one\n2) two\n",
+            "3. three\n4. four\n\n1. one\n1. one\n1. one\n",
+            "> q1\n>\n> q2 *x*  \n> y\n\n\n\npara\n",
+            "~~~\ncode ```\n~~~\n\n    indented\n    code\n\n***\n",
+            "```rust\nfn main() {}\n```\n\n~~~ sh `x`\nls\n~~~\n",
+            "- item\n\n  inner paragraph\n- tight\n\n\n",
+            "no trailing newline",
+            "a [link](<with space>) and [ref][r]\n\n[r]: https://example.com\n",
+            "> - a\n> - b\n",
+            "> # Title\n> text\n",
+            "> ```\n> code\n> ```\n",
+            "Text\n***\nMore\n",
+            "# H #",
+            "- item\n  > quoted",
+            "\tcode\n\tmore\n",
+            "-\n",
+            "- - a\n",
+            "- # Title\n",
+            "- > q\n",
+            "1. ```\n   code\n   ```\n",
+            "- 1.\n\n- 1.\n\n- \n\n1. one\n",
+            "```md\n```rust\n```\n",
+        ];
+        for source in sources {
+            assert_eq!(round_trip(source), source);
+        }
+    }
+
+    #[test]
+    fn test_images_and_hard_breaks() {
+        let mut doc =
+            Document::from_markdown("Voir ![un *chat*](chat.png) et ![](vide.png)\nfin  \nsuite\n");
+        assert_eq!(doc.blocks[0].text, "Voir un chat et \u{FFFC}\nfin\nsuite");
+        assert!(doc.blocks[0].styles[1].style.is_image);
+        assert_eq!(doc.blocks[0].styles[1].link.as_deref(), Some("chat.png"));
+
+        // Réécrits après une modification, sans que le texte tapé à côté en fasse partie
+        doc.insert_text_at(0, 12, "s");
+        doc.insert_text_at(0, 22, ".");
+        assert_eq!(
+            doc.blocks[0].to_markdown(),
+            "Voir ![un *chat*](chat.png)s et ![](vide.png)\nfin.  \nsuite"
+        );
+        let mut doc = Document::from_markdown("a\\\nb\nc\n");
+        doc.insert_text_at(0, 5, "!");
+        assert_eq!(doc.blocks[0].to_markdown(), "a\\\nb\nc!");
+    }
+
+    #[test]
+    fn test_edited_block_is_rewritten() {
+        let mut doc = Document::from_markdown("* a\n* b\n\n2) x\n");
+        doc.insert_text_at(1, 1, "c");
+        doc.blocks[2].set_indent(1);
+        let mut out = Vec::new();
+        doc.write_markdown(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "* a\n* bc\n\n  2) x\n");
+    }
+
+    #[test]
+    fn test_grapheme_editing() {
+        // e + accent combinant, pouce avec couleur de peau, drapeau
+        let mut doc = Document::from_markdown("e\u{301}👍🏽🇫🇷 l'été, déjà-vu\n");
+        let block = &doc.blocks[0];
+        assert_eq!(block.next_grapheme(0), 2);
+        assert_eq!(block.next_grapheme(2), 4);
+        assert_eq!(block.prev_grapheme(6), 4);
+        assert_eq!(block.grapheme_floor(5), 4);
+        assert_eq!(block.next_word_end(6), 12);
+        assert_eq!(block.prev_word_start(19), 14);
+        assert_eq!(block.next_word_end(12), 18);
+
+        assert_eq!(doc.remove_grapheme_at(0, 4), 2);
+        assert_eq!(doc.remove_grapheme_at(0, 0), 2);
+        assert_eq!(doc.blocks[0].text, "👍🏽 l'été, déjà-vu");
+    }
+
+    #[test]
+    fn test_split_and_join_blocks() {
+        let mut doc = Document::from_markdown("un **gras** texte\n");
+        // Coupé au milieu du gras : chaque moitié le garde
+        assert_eq!(doc.split_block(0, 5, BlockType::Paragraph), 1);
+        assert_eq!(doc.blocks[0].text, "un gr");
+        assert_eq!(doc.blocks[1].text, "as texte");
+        assert_eq!(doc.blocks[0].to_markdown(), "un **gr**");
+        assert_eq!(doc.blocks[1].to_markdown(), "**as** texte");
+        assert_eq!(doc.join_blocks(0), Some(5));
+        assert_eq!(doc.blocks[0].to_markdown(), "un **gras** texte");
+
+        // Collage de plusieurs lignes : la suite du bloc reste après la dernière
+        let end = doc.insert_lines(0, 3, "a\nb\nc");
+        assert_eq!(end, (2, 1));
+        let texts: Vec<&str> = doc.blocks.iter().map(|b| b.text.as_str()).collect();
+        assert_eq!(texts, ["un a", "b", "cgras texte"]);
+        assert_eq!(doc.blocks[2].to_markdown(), "c**gras** texte");
+
+        // Effacement sur plusieurs blocs en une passe
+        assert_eq!(doc.delete_range((0, 2), (2, 3)), (0, 2));
+        assert_eq!(doc.blocks.len(), 1);
+        assert_eq!(doc.blocks[0].to_markdown(), "un**as** texte");
+    }
+
+    #[test]
+    fn test_paste_markdown() {
+        let mut doc = Document::from_markdown("avant après\n");
+        let end = doc.insert_markdown(0, 6, "**gras** x\n\n# Titre\n\n- un\n- *deux*");
+        assert_eq!(end, (3, 4));
+        let types: Vec<BlockType> = doc.blocks.iter().map(|b| b.ty.clone()).collect();
+        assert_eq!(
+            types,
+            [
+                BlockType::Paragraph,
+                BlockType::Heading1,
+                BlockType::ListItem,
+                BlockType::ListItem
+            ]
+        );
+        assert_eq!(doc.blocks[0].to_markdown(), "avant **gras** x");
+        assert_eq!(doc.blocks[3].to_markdown(), "*deux*après");
+
+        // Dans un paragraphe vide, le bloc collé garde son type
+        let mut doc = Document::from_markdown("");
+        assert_eq!(doc.insert_markdown(0, 0, "## Suite `code`"), (0, 10));
+        assert_eq!(doc.blocks[0].ty, BlockType::Heading2);
+        assert!(parser::looks_like_markdown("- [lien](a.md)"));
+        assert!(!parser::looks_like_markdown("deux lignes\nde texte, 3 * 4"));
+    }
+
+    #[test]
+    fn test_copy_as_markdown() {
+        let doc = Document::from_markdown(
+            "# Titre\n\n- un **gras**\n  - deux *it*\n\n```rust\nlet x = 1;\n```\n",
+        );
+        assert_eq!(
+            doc.get_markdown_in_range((0, 2), (2, 4)),
+            "# tre\n\n- un **gras**\n  - deux"
+        );
+        // Élément entier d'une sous-liste : il remonte au premier niveau
+        assert_eq!(doc.get_markdown_in_range((2, 0), (2, 7)), "- deux *it*");
+        assert_eq!(doc.get_markdown_in_range((2, 0), (2, 6)), "deux *i*");
+        assert_eq!(
+            doc.get_markdown_in_range((2, 0), (3, 3)),
+            "- deux *it*\n\n```rust\nlet\n```"
+        );
+        assert_eq!(doc.get_markdown_in_range((3, 4), (3, 5)), "`x`");
+        assert_eq!(
+            doc.get_html_in_range((1, 3), (1, 7)),
+            "<p><strong>gras</strong></p>"
+        );
+    }
+
+    #[test]
+    fn test_outline_nesting() {
+        let doc = Document::from_markdown("# A\n\ntext\n\n### B\n\n## C\n\n# D\n");
+        let outline: Vec<_> = doc
+            .outline()
+            .into_iter()
+            .map(|e| (e.block_idx, e.level, e.depth, e.title))
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                (0, 1, 0, "A".to_string()),
+                (2, 3, 1, "B".to_string()),
+                (3, 2, 1, "C".to_string()),
+                (4, 1, 0, "D".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_modified_tracking() {
+        let mut doc = Document::from_markdown("a\n");
+        assert!(!doc.is_modified());
+        doc.mark_modified();
+        let saving = doc.snapshot().revision();
+        // Modifié pendant l'enregistrement : reste modifié
+        doc.mark_modified();
+        doc.mark_saved(saving);
+        assert!(doc.is_modified());
+        doc.mark_saved(doc.revision());
+        assert!(!doc.is_modified());
+
+        // Chaque mutation marque le document, sans passer par l'éditeur
+        let edits: [fn(&mut Document); 5] = [
+            |d| _ = d.insert_text_at(0, 1, "b"),
+            |d| _ = d.delete_range((0, 0), (0, 1)),
+            |d| _ = d.split_block(0, 1, BlockType::Paragraph),
+            |d| d.toggle_formatting(0, 0, 1, InlineStyle::Bold),
+            |d| _ = d.try_convert_table(0),
+        ];
+        for edit in edits {
+            let mut doc = Document::from_markdown("| x | y |\n");
+            edit(&mut doc);
+            assert!(doc.is_modified());
+        }
+        let mut doc = Document::from_markdown("a\n");
+        assert!(!doc.toggle_task(0));
+        assert!(!doc.is_modified());
+    }
+
+    #[test]
+    fn test_save_is_atomic_and_keeps_permissions() {
+        let dir = std::env::temp_dir().join(format!("ndown-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("note.md");
+        let filename = file.to_string_lossy().into_owned();
+        fs::write(&file, "ancien contenu\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o600)).unwrap();
+        }
+
+        let doc = Document::from_markdown("# Nouveau\n");
+        doc.save_to_file(&filename).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "# Nouveau\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&file).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // Aucun fichier temporaire ne reste
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
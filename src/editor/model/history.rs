@@ -0,0 +1,256 @@
+use crate::editor::model::block::Block;
+use crate::editor::model::document::Document;
+use std::time::{Duration, Instant};
+
+const MAX_UNDO_STEPS: usize = 500;
+// Frappes consécutives plus rapprochées que ce délai = une seule étape d'annulation
+const GROUP_TIMEOUT: Duration = Duration::from_millis(1000);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditKind {
+    /// Text typed at the cursor. Consecutive typing is grouped.
+    Typing,
+    /// Single characters removed with Backspace/Delete. Grouped like typing.
+    Deletion,
+    /// Anything else (paste, cut, formatting, block changes). Never grouped.
+    Other,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CursorState {
+    pub cursor: (usize, usize),
+    pub anchor: Option<(usize, usize)>,
+}
+
+// Une étape : les blocs [first_block, first_block + before.len()) ont été remplacés
+// par `after_len` blocs.
+#[derive(Clone, Debug)]
+struct Change {
+    kind: EditKind,
+    first_block: usize,
+    before: Vec<Block>,
+    after_len: usize,
+    // Rempli lors de l'annulation, pour pouvoir rétablir
+    after: Vec<Block>,
+    cursor_before: CursorState,
+    cursor_after: CursorState,
+    // Plus aucune frappe ne peut être regroupée dans cette étape
+    sealed: bool,
+    last_edit: Instant,
+}
+
+#[derive(Clone, Debug)]
+struct PendingChange {
+    kind: EditKind,
+    first_block: usize,
+    before: Vec<Block>,
+    doc_len_before: usize,
+    cursor_before: CursorState,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Change>,
+    redo_stack: Vec<Change>,
+    pending: Option<PendingChange>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.pending = None;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Records blocks `first..=last` before they are mutated. Must be followed by `end`.
+    pub fn begin(
+        &mut self,
+        doc: &Document,
+        kind: EditKind,
+        first: usize,
+        last: usize,
+        cursor: CursorState,
+    ) {
+        let last = last.min(doc.blocks.len().saturating_sub(1));
+        let first = first.min(last);
+        self.pending = Some(PendingChange {
+            kind,
+            first_block: first,
+            before: doc.blocks[first..=last].to_vec(),
+            doc_len_before: doc.blocks.len(),
+            cursor_before: cursor,
+        });
+    }
+
+    /// Closes the change opened by `begin`, merging it into the previous step when
+    /// it continues the same run of typing or deleting. Returns false if the blocks
+    /// did not change.
+    pub fn end(&mut self, doc: &Document, cursor: CursorState) -> bool {
+        let Some(pending) = self.pending.take() else {
+            return false;
+        };
+        let after_len =
+            (pending.before.len() + doc.blocks.len()).saturating_sub(pending.doc_len_before);
+
+        let after = &doc.blocks[pending.first_block..pending.first_block + after_len];
+        if after_len == pending.before.len()
+            && after
+                .iter()
+                .zip(&pending.before)
+                .all(|(a, b)| a.same_content(b))
+        {
+            // Nothing changed (e.g. Backspace at the start of the document)
+            return false;
+        }
+
+        self.redo_stack.clear();
+        let now = Instant::now();
+
+        if let Some(top) = self.undo_stack.last_mut() {
+            let can_group = pending.kind != EditKind::Other
+                && top.kind == pending.kind
+                && !top.sealed
+                && top.first_block == pending.first_block
+                && top.before.len() == 1
+                && top.after_len == 1
+                && pending.before.len() == 1
+                && after_len == 1
+                && top.cursor_after == pending.cursor_before
+                && now.duration_since(top.last_edit) < GROUP_TIMEOUT;
+            if can_group {
+                top.cursor_after = cursor;
+                top.last_edit = now;
+                return true;
+            }
+        }
+
+        self.undo_stack.push(Change {
+            kind: pending.kind,
+            first_block: pending.first_block,
+            before: pending.before,
+            after_len,
+            after: Vec::new(),
+            cursor_before: pending.cursor_before,
+            cursor_after: cursor,
+            sealed: false,
+            last_edit: now,
+        });
+        if self.undo_stack.len() > MAX_UNDO_STEPS {
+            self.undo_stack.remove(0);
+        }
+        true
+    }
+
+    /// Ends the current typing group: the next edit starts a new undo step.
+    pub fn seal(&mut self) {
+        if let Some(top) = self.undo_stack.last_mut() {
+            top.sealed = true;
+        }
+    }
+
+    /// Reverts the last step. Returns the cursor to restore and the first block touched.
+    pub fn undo(&mut self, doc: &mut Document) -> Option<(CursorState, usize)> {
+        let mut change = self.undo_stack.pop()?;
+        let end = (change.first_block + change.after_len).min(doc.blocks.len());
+        let mut restored = change.before.clone();
+        for block in &mut restored {
+            block.mark_dirty();
+        }
+        change.after = doc
+            .blocks
+            .splice(change.first_block..end, restored)
+            .collect();
+        change.sealed = true;
+        let result = (change.cursor_before, change.first_block);
+        self.redo_stack.push(change);
+        Some(result)
+    }
+
+    /// Re-applies the last undone step.
+    pub fn redo(&mut self, doc: &mut Document) -> Option<(CursorState, usize)> {
+        let mut change = self.redo_stack.pop()?;
+        let end = (change.first_block + change.before.len()).min(doc.blocks.len());
+        let mut after = std::mem::take(&mut change.after);
+        for block in &mut after {
+            block.mark_dirty();
+        }
+        doc.blocks.splice(change.first_block..end, after);
+        let result = (change.cursor_after, change.first_block);
+        self.undo_stack.push(change);
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(block: usize, ch: usize) -> CursorState {
+        CursorState {
+            cursor: (block, ch),
+            anchor: None,
+        }
+    }
+
+    fn texts(doc: &Document) -> Vec<&str> {
+        doc.blocks.iter().map(|b| b.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_typing_is_grouped_until_sealed() {
+        let mut doc = Document::from_markdown("ab");
+        let mut history = History::new();
+
+        for (i, c) in ["c", "d"].iter().enumerate() {
+            history.begin(&doc, EditKind::Typing, 0, 0, cursor(0, 2 + i));
+            doc.insert_text_at(0, 2 + i, c);
+            history.end(&doc, cursor(0, 3 + i));
+        }
+        history.seal();
+        history.begin(&doc, EditKind::Typing, 0, 0, cursor(0, 4));
+        doc.insert_text_at(0, 4, "e");
+        history.end(&doc, cursor(0, 5));
+
+        assert_eq!(history.undo(&mut doc).unwrap().0, cursor(0, 4));
+        assert_eq!(texts(&doc), vec!["abcd"]);
+        assert_eq!(history.undo(&mut doc).unwrap().0, cursor(0, 2));
+        assert_eq!(texts(&doc), vec!["ab"]);
+        assert!(history.undo(&mut doc).is_none());
+
+        assert_eq!(history.redo(&mut doc).unwrap().0, cursor(0, 4));
+        assert_eq!(history.redo(&mut doc).unwrap().0, cursor(0, 5));
+        assert_eq!(texts(&doc), vec!["abcde"]);
+    }
+
+    #[test]
+    fn test_undo_multi_block_delete() {
+        let mut doc = Document::from_markdown("one\n\ntwo\n\nthree");
+        let mut history = History::new();
+        let before = CursorState {
+            cursor: (2, 2),
+            anchor: Some((0, 1)),
+        };
+
+        history.begin(&doc, EditKind::Other, 0, 2, before);
+        doc.delete_range((0, 1), (2, 2));
+        history.end(&doc, cursor(0, 1));
+        assert_eq!(texts(&doc), vec!["oree"]);
+
+        assert_eq!(history.undo(&mut doc).unwrap().0, before);
+        assert_eq!(texts(&doc), vec!["one", "two", "three"]);
+        history.redo(&mut doc);
+        assert_eq!(texts(&doc), vec!["oree"]);
+    }
+}
@@ -0,0 +1,227 @@
+// Rendu HTML des blocs, pour le presse-papiers : courriels, messageries…
+use super::block::{Block, BlockType, ColumnAlign, InlineStyle, IMAGE_PLACEHOLDER};
+use std::ops::Range;
+
+// Balises des styles, de la plus extérieure à la plus intérieure
+const STYLE_TAGS: [(InlineStyle, &str); 7] = [
+    (InlineStyle::Bold, "strong"),
+    (InlineStyle::Italic, "em"),
+    (InlineStyle::Strikethrough, "del"),
+    (InlineStyle::Highlight, "mark"),
+    (InlineStyle::Superscript, "sup"),
+    (InlineStyle::Subscript, "sub"),
+    (InlineStyle::Code, "code"),
+];
+
+fn push_escaped(html: &mut String, c: char) {
+    match c {
+        '&' => html.push_str("&amp;"),
+        '<' => html.push_str("&lt;"),
+        '>' => html.push_str("&gt;"),
+        '"' => html.push_str("&quot;"),
+        c => html.push(c),
+    }
+}
+
+// Texte des caractères `range` du bloc, avec ses styles et ses liens
+fn push_inline(html: &mut String, block: &Block, range: Range<usize>) {
+    let mut pos = 0;
+    for span in &block.styles {
+        let start = pos.max(range.start);
+        let end = (pos + span.len).min(range.end).min(block.text_len());
+        pos += span.len;
+        if start >= end {
+            continue;
+        }
+        let text = block.text.slice_chars(start..end);
+        if let (true, Some(src)) = (span.style.is_image, &span.link) {
+            html.push_str("<img src=\"");
+            src.chars().for_each(|c| push_escaped(html, c));
+            html.push_str("\" alt=\"");
+            text.chars()
+                .filter(|&c| c != IMAGE_PLACEHOLDER)
+                .for_each(|c| push_escaped(html, c));
+            html.push_str("\">");
+            continue;
+        }
+        let tags: Vec<&str> = STYLE_TAGS
+            .iter()
+            .filter(|(style, _)| span.style.has(*style))
+            .map(|(_, tag)| *tag)
+            .collect();
+        if let Some(url) = &span.link {
+            html.push_str("<a href=\"");
+            url.chars().for_each(|c| push_escaped(html, c));
+            html.push_str("\">");
+        }
+        for tag in &tags {
+            html.push_str(&format!("<{tag}>"));
+        }
+        // Un saut de ligne simple reste un blanc, comme dans le Markdown
+        for c in text.chars() {
+            if c == '\n' && span.style.is_hard_break {
+                html.push_str("<br>");
+            }
+            push_escaped(html, c);
+        }
+        for tag in tags.iter().rev() {
+            html.push_str(&format!("</{tag}>"));
+        }
+        if span.link.is_some() {
+            html.push_str("</a>");
+        }
+    }
+}
+
+fn heading_level(ty: &BlockType) -> Option<u8> {
+    match ty {
+        BlockType::Heading1 => Some(1),
+        BlockType::Heading2 => Some(2),
+        BlockType::Heading3 => Some(3),
+        BlockType::Heading4 => Some(4),
+        BlockType::Heading5 => Some(5),
+        _ => None,
+    }
+}
+
+fn push_table(html: &mut String, block: &Block) {
+    html.push_str("<table>");
+    for (row, cells) in block.table_cells().into_iter().enumerate() {
+        html.push_str(if row == 0 { "<thead><tr>" } else { "<tr>" });
+        let tag = if row == 0 { "th" } else { "td" };
+        for (col, cell) in cells.into_iter().enumerate() {
+            match block.columns.get(col).copied().unwrap_or_default() {
+                ColumnAlign::None => html.push_str(&format!("<{tag}>")),
+                ColumnAlign::Left => html.push_str(&format!("<{tag} align=\"left\">")),
+                ColumnAlign::Center => html.push_str(&format!("<{tag} align=\"center\">")),
+                ColumnAlign::Right => html.push_str(&format!("<{tag} align=\"right\">")),
+            }
+            push_inline(html, block, cell);
+            html.push_str(&format!("</{tag}>"));
+        }
+        html.push_str(if row == 0 {
+            "</tr></thead><tbody>"
+        } else {
+            "</tr>"
+        });
+    }
+    html.push_str("</tbody></table>");
+}
+
+/// HTML fragment for `blocks`: headings, nested lists, quotes, code blocks, tables
+/// and inline styles, as a mail or chat application expects it on the clipboard.
+pub fn blocks_to_html(blocks: &[Block]) -> String {
+    let mut html = String::new();
+    // Listes ouvertes : balise et indentation
+    let mut lists: Vec<(&str, u8)> = Vec::new();
+    for block in blocks {
+        let list_tag = match block.ty {
+            BlockType::ListItem | BlockType::TaskItem => Some("ul"),
+            BlockType::OrderedListItem => Some("ol"),
+            _ => None,
+        };
+        // Ferme les listes plus profondes, et celle du même niveau si le type change
+        while let Some(&(open, indent)) = lists.last() {
+            match list_tag {
+                Some(tag) if indent < block.indent || (indent == block.indent && open == tag) => {
+                    break
+                }
+                _ => {
+                    html.push_str(&format!("</li></{open}>"));
+                    lists.pop();
+                }
+            }
+        }
+
+        let len = block.text_len();
+        if let Some(tag) = list_tag {
+            if lists
+                .last()
+                .is_some_and(|&(_, indent)| indent == block.indent)
+            {
+                html.push_str("</li>");
+            } else {
+                match block.syntax.number {
+                    Some(start) if tag == "ol" && start != 1 => {
+                        html.push_str(&format!("<ol start=\"{start}\">"))
+                    }
+                    _ => html.push_str(&format!("<{tag}>")),
+                }
+                lists.push((tag, block.indent));
+            }
+            html.push_str("<li>");
+            if block.ty == BlockType::TaskItem {
+                html.push_str(if block.checked {
+                    "<input type=\"checkbox\" checked disabled> "
+                } else {
+                    "<input type=\"checkbox\" disabled> "
+                });
+            }
+            push_inline(&mut html, block, 0..len);
+            continue;
+        }
+
+        // Bloc brut (HTML, définition de lien) : déjà écrit tel quel
+        if block.syntax.raw {
+            html.push_str(&block.text);
+            continue;
+        }
+        if let Some(level) = heading_level(&block.ty) {
+            html.push_str(&format!("<h{level}>"));
+            push_inline(&mut html, block, 0..len);
+            html.push_str(&format!("</h{level}>"));
+            continue;
+        }
+        match block.ty {
+            BlockType::Paragraph if block.text == "---" => html.push_str("<hr>"),
+            BlockType::Quote => {
+                html.push_str("<blockquote><p>");
+                push_inline(&mut html, block, 0..len);
+                html.push_str("</p></blockquote>");
+            }
+            BlockType::CodeBlock => {
+                match block.language() {
+                    "" => html.push_str("<pre><code>"),
+                    lang => html.push_str(&format!("<pre><code class=\"language-{lang}\">")),
+                }
+                block.text.chars().for_each(|c| push_escaped(&mut html, c));
+                html.push_str("</code></pre>");
+            }
+            BlockType::Table => push_table(&mut html, block),
+            _ => {
+                html.push_str("<p>");
+                push_inline(&mut html, block, 0..len);
+                html.push_str("</p>");
+            }
+        }
+    }
+    for (open, _) in lists.into_iter().rev() {
+        html.push_str(&format!("</li></{open}>"));
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::model::document::Document;
+
+    #[test]
+    fn test_blocks_to_html() {
+        let doc = Document::from_markdown(
+            "## Titre & co\n\nligne\nsuite\\\nfin ![](a.png)\n\n\
+             - un **gras**\n  1. [lien](a.md)\n- [x] fait\n\n\
+             | a | b |\n|:-|--:|\n| `<x>` | ==y== |\n",
+        );
+        assert_eq!(
+            blocks_to_html(&doc.blocks),
+            "<h2>Titre &amp; co</h2><p>ligne\nsuite<br>\nfin <img src=\"a.png\" alt=\"\"></p>\
+             <ul><li>un <strong>gras</strong>\
+             <ol><li><a href=\"a.md\">lien</a></li></ol></li>\
+             <li><input type=\"checkbox\" checked disabled> fait</li></ul>\
+             <table><thead><tr><th align=\"left\">a</th><th align=\"right\">b</th></tr></thead>\
+             <tbody><tr><td align=\"left\"><code>&lt;x&gt;</code></td>\
+             <td align=\"right\"><mark>y</mark></td></tr></tbody></table>"
+        );
+    }
+}
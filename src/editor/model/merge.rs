@@ -0,0 +1,250 @@
+// Fusion à trois voies, ligne par ligne (diff3)
+
+/// Result of a three-way merge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Merge {
+    pub text: String,
+    /// Number of regions changed on both sides, written between conflict markers.
+    pub conflicts: usize,
+}
+
+pub const MARKER_MINE: &str = "<<<<<<< modifications locales";
+pub const MARKER_SEPARATOR: &str = "=======";
+pub const MARKER_THEIRS: &str = ">>>>>>> fichier sur le disque";
+
+// Pour chaque ligne de `base`, la ligne correspondante de `other` dans une plus longue
+// sous-séquence commune
+fn match_lines(base: &[&str], other: &[&str]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; base.len()];
+    common_lines(base, other, (0, 0), &mut matches);
+    matches
+}
+
+// Algorithme de Myers en espace linéaire : hors du début et de la fin communs, le
+// problème est coupé en deux au milieu d'un chemin d'édition minimal
+fn common_lines(a: &[&str], b: &[&str], at: (usize, usize), matches: &mut [Option<usize>]) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+    for i in 0..prefix {
+        matches[at.0 + i] = Some(at.1 + i);
+    }
+    let at = (at.0 + prefix, at.1 + prefix);
+    for i in 0..suffix {
+        matches[at.0 + a.len() + i] = Some(at.1 + b.len() + i);
+    }
+    if a.is_empty() || b.is_empty() {
+        return;
+    }
+    if let Some((x, y)) = middle_split(a, b) {
+        common_lines(&a[..x], &b[..y], at, matches);
+        common_lines(&a[x..], &b[y..], (at.0 + x, at.1 + y), matches);
+    }
+}
+
+// Point où se rejoignent les chemins partis du début et de la fin ; `None` si les deux
+// côtés n'ont aucune ligne en commun
+fn middle_split(a: &[&str], b: &[&str]) -> Option<(usize, usize)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let len = 2 * max_d + 2;
+    // Par diagonale k = x - y : x le plus loin atteint depuis le début (v1) ou la fin (v2)
+    let mut v1 = vec![-1; len as usize];
+    let mut v2 = vec![-1; len as usize];
+    v1[max_d as usize + 1] = 0;
+    v2[max_d as usize + 1] = 0;
+    let delta = n - m;
+    let front = delta % 2 != 0;
+    // Diagonales sorties de la grille, à ne plus parcourir
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+    for d in 0..max_d {
+        for k1 in (-d + k1_start..=d - k1_end).step_by(2) {
+            let i = (max_d + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && v1[i - 1] < v1[i + 1]) {
+                v1[i + 1]
+            } else {
+                v1[i - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && a[x1 as usize] == b[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            v1[i] = x1;
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let j = max_d + delta - k1;
+                if (0..len).contains(&j) && v2[j as usize] != -1 && x1 >= n - v2[j as usize] {
+                    return Some((x1 as usize, y1 as usize));
+                }
+            }
+        }
+        for k2 in (-d + k2_start..=d - k2_end).step_by(2) {
+            let i = (max_d + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && v2[i - 1] < v2[i + 1]) {
+                v2[i + 1]
+            } else {
+                v2[i - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && a[(n - x2 - 1) as usize] == b[(m - y2 - 1) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            v2[i] = x2;
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let j = max_d + delta - k2;
+                if (0..len).contains(&j) && v1[j as usize] != -1 {
+                    let x1 = v1[j as usize];
+                    let y1 = max_d + x1 - j;
+                    if x1 >= n - x2 {
+                        return Some((x1 as usize, y1 as usize));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Merges the changes made in `mine` and in `theirs` since `base`. Regions changed
+/// differently on both sides keep both versions between conflict markers.
+pub fn merge3(base: &str, mine: &str, theirs: &str) -> Merge {
+    let base: Vec<&str> = base.lines().collect();
+    let mine: Vec<&str> = mine.lines().collect();
+    let theirs: Vec<&str> = theirs.lines().collect();
+    let in_mine = match_lines(&base, &mine);
+    let in_theirs = match_lines(&base, &theirs);
+
+    let mut out: Vec<&str> = Vec::new();
+    let mut conflicts = 0;
+    let (mut b, mut m, mut t) = (0, 0, 0);
+    loop {
+        // Prochaine ligne de base présente, inchangée, des deux côtés
+        let sync = (b..base.len()).find_map(|i| match (in_mine[i], in_theirs[i]) {
+            (Some(mi), Some(ti)) => Some((i, mi, ti)),
+            _ => None,
+        });
+        let (b_end, m_end, t_end) = sync.unwrap_or((base.len(), mine.len(), theirs.len()));
+
+        let base_chunk = &base[b..b_end];
+        let mine_chunk = &mine[m..m_end];
+        let theirs_chunk = &theirs[t..t_end];
+        if mine_chunk == base_chunk {
+            out.extend(theirs_chunk);
+        } else if theirs_chunk == base_chunk || mine_chunk == theirs_chunk {
+            out.extend(mine_chunk);
+        } else {
+            conflicts += 1;
+            out.push(MARKER_MINE);
+            out.extend(mine_chunk);
+            out.push(MARKER_SEPARATOR);
+            out.extend(theirs_chunk);
+            out.push(MARKER_THEIRS);
+        }
+
+        let Some((i, mi, ti)) = sync else {
+            break;
+        };
+        out.push(base[i]);
+        (b, m, t) = (i + 1, mi + 1, ti + 1);
+    }
+
+    let mut text = out.join("\n");
+    text.push('\n');
+    Merge { text, conflicts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::model::block::BlockType;
+    use crate::editor::model::document::Document;
+
+    #[test]
+    fn test_match_lines() {
+        // Comparé à la table complète de plus longue sous-séquence commune
+        let lcs_len = |a: &[&str], b: &[&str]| {
+            let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+            for i in (0..a.len()).rev() {
+                for j in (0..b.len()).rev() {
+                    lcs[i][j] = if a[i] == b[j] {
+                        lcs[i + 1][j + 1] + 1
+                    } else {
+                        lcs[i + 1][j].max(lcs[i][j + 1])
+                    };
+                }
+            }
+            lcs[0][0]
+        };
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = |bound: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % bound as u64) as usize
+        };
+        let lines = ["a", "b", "c", "", "d"];
+        for _ in 0..500 {
+            let a: Vec<&str> = (0..next(12)).map(|_| lines[next(lines.len())]).collect();
+            let b: Vec<&str> = (0..next(12)).map(|_| lines[next(lines.len())]).collect();
+            let matches = match_lines(&a, &b);
+            let pairs: Vec<(usize, usize)> = matches
+                .iter()
+                .enumerate()
+                .filter_map(|(i, j)| j.map(|j| (i, j)))
+                .collect();
+            assert!(pairs.iter().all(|&(i, j)| a[i] == b[j]));
+            assert!(pairs.windows(2).all(|w| w[0].1 < w[1].1));
+            assert_eq!(pairs.len(), lcs_len(&a, &b), "{a:?} {b:?}");
+        }
+    }
+
+    #[test]
+    fn test_merge3() {
+        let base = "# Notes\n\nun\n\ndeux\n\ntrois\n";
+        let mine = "# Notes\n\nun modifié\n\ndeux\n\ntrois\n";
+        let theirs = "# Notes\n\nun\n\ndeux\n\ntrois\n\nquatre\n";
+        let merged = merge3(base, mine, theirs);
+        assert_eq!(merged.conflicts, 0);
+        assert_eq!(
+            merged.text,
+            "# Notes\n\nun modifié\n\ndeux\n\ntrois\n\nquatre\n"
+        );
+
+        let theirs = "# Notes\n\nun autre\n\ndeux\n\ntrois\n";
+        let merged = merge3(base, mine, theirs);
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(
+            merged.text,
+            format!(
+                "# Notes\n\n{MARKER_MINE}\nun modifié\n{MARKER_SEPARATOR}\nun autre\n\
+                 {MARKER_THEIRS}\n\ndeux\n\ntrois\n"
+            )
+        );
+
+        // Rechargée, la zone de conflit reste un bloc tel quel, marqueurs compris
+        let doc = Document::from_markdown(&merged.text);
+        let conflict = &doc.blocks[1];
+        assert_eq!(conflict.ty, BlockType::Paragraph);
+        assert_eq!(
+            conflict.text,
+            format!("{MARKER_MINE}\nun modifié\n{MARKER_SEPARATOR}\nun autre\n{MARKER_THEIRS}")
+        );
+        assert_eq!(doc.blocks[2].text, "deux");
+        assert_eq!(doc.to_markdown(), merged.text);
+    }
+}
@@ -1,2 +1,7 @@
 pub mod block;
 pub mod document;
+pub mod history;
+pub mod html;
+pub mod merge;
+pub mod parser;
+pub mod text;
@@ -0,0 +1,978 @@
+use crate::editor::model::block::{
+    Block, BlockSyntax, BlockType, ColumnAlign, InlineStyle, StyleBits, StyleSpan, HTML_TAGS,
+    IMAGE_PLACEHOLDER,
+};
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+// Block en cours de construction (texte + spans de style)
+struct PendingBlock {
+    ty: BlockType,
+    indent: u8,
+    text: String,
+    styles: Vec<StyleSpan>,
+    info: String,
+    checked: bool,
+    columns: Vec<ColumnAlign>,
+    syntax: BlockSyntax,
+    // Caractères échappés par un `\` dans la source (jamais des marqueurs)
+    escaped: Vec<usize>,
+    // Début de la première ligne du bloc dans la source
+    line_start: usize,
+    // Plage du contenu inline (pour conserver la source d'origine)
+    content: Option<Range<usize>>,
+    // Fin du bloc dans la source
+    end: usize,
+}
+
+impl PendingBlock {
+    fn new(ty: BlockType, indent: u8, line_start: usize) -> Self {
+        Self {
+            ty,
+            indent,
+            text: String::new(),
+            styles: Vec::new(),
+            info: String::new(),
+            checked: false,
+            columns: Vec::new(),
+            syntax: BlockSyntax::default(),
+            escaped: Vec::new(),
+            line_start,
+            content: None,
+            end: line_start,
+        }
+    }
+
+    fn push(&mut self, text: &str, style: StyleBits, link: Option<&String>) {
+        let len = text.chars().count();
+        if len == 0 {
+            return;
+        }
+        self.text.push_str(text);
+        if let Some(last) = self.styles.last_mut() {
+            if last.style == style && last.link.as_ref() == link {
+                last.len += len;
+                return;
+            }
+        }
+        self.styles.push(StyleSpan {
+            len,
+            style,
+            link: link.cloned(),
+        });
+    }
+
+    fn is_list(&self) -> bool {
+        matches!(
+            self.ty,
+            BlockType::ListItem | BlockType::OrderedListItem | BlockType::TaskItem
+        )
+    }
+
+    fn extend_content(&mut self, range: &Range<usize>) {
+        self.content = Some(match self.content.take() {
+            Some(c) => c.start.min(range.start)..c.end.max(range.end),
+            None => range.clone(),
+        });
+        self.end = self.end.max(range.end);
+    }
+}
+
+struct BlockBuilder<'a> {
+    source: &'a str,
+    blocks: Vec<Block>,
+    next_id: u64,
+    current: Option<PendingBlock>,
+    // One entry per open list: true for ordered lists
+    lists: Vec<bool>,
+    // Start of a list item whose first block is not emitted yet
+    item_pending: Option<usize>,
+    // End of the marker of an item whose first child is a block (`- # Title`, `- - a`):
+    // written before that block
+    item_prefix_end: Option<usize>,
+    quote_depth: usize,
+    // Start of a quote with no block yet
+    quote_pending: Option<usize>,
+    bold_depth: usize,
+    italic_depth: usize,
+    // Styles ouverts par une balise HTML (`<em>`, `<mark>`...)
+    html_styles: Vec<InlineStyle>,
+    // Un élément par `~` ouvert : true pour `~~barré~~`, false pour `~indice~`
+    tildes: Vec<bool>,
+    // Destination du lien en cours
+    link: Option<String>,
+    // Dans une image : le lien qui l'entoure, rétabli à sa fin, et la longueur du
+    // texte à son début
+    image: Option<(Option<String>, usize)>,
+    // Cellules déjà ouvertes dans la ligne de tableau en cours
+    row_cells: usize,
+    // Fin du dernier bloc émis
+    prev_end: usize,
+}
+
+impl<'a> BlockBuilder<'a> {
+    fn new(source: &'a str, first_id: u64) -> Self {
+        Self {
+            source,
+            blocks: Vec::new(),
+            next_id: first_id,
+            current: None,
+            lists: Vec::new(),
+            item_pending: None,
+            item_prefix_end: None,
+            quote_depth: 0,
+            quote_pending: None,
+            bold_depth: 0,
+            italic_depth: 0,
+            html_styles: Vec::new(),
+            tildes: Vec::new(),
+            link: None,
+            image: None,
+            row_cells: 0,
+            prev_end: 0,
+        }
+    }
+
+    fn style(&self) -> StyleBits {
+        let mut style = StyleBits {
+            is_bold: self.bold_depth > 0,
+            is_italic: self.italic_depth > 0,
+            is_strikethrough: self.tildes.contains(&true),
+            is_subscript: self.tildes.contains(&false),
+            is_image: self.image.is_some(),
+            ..StyleBits::default()
+        };
+        for &s in &self.html_styles {
+            style.set(s, true);
+        }
+        style
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.source[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0)
+    }
+
+    // Spaces between the start of the line and `pos`, if that is all there is
+    fn leading_spaces(&self, line_start: usize, pos: usize) -> Option<usize> {
+        let lead = &self.source[line_start..pos];
+        lead.bytes().all(|b| b == b' ').then_some(lead.len())
+    }
+
+    // Fin du marqueur d'un élément de liste écrit sur la même ligne que le bloc
+    fn item_prefix(&self, line_start: usize) -> Option<usize> {
+        self.item_prefix_end
+            .filter(|&end| end >= line_start && !self.source[line_start..end].contains('\n'))
+    }
+
+    // Début de la ligne après les préfixes des conteneurs : marqueur de l'élément dont
+    // le bloc est le premier enfant, puis `>` des citations ouvertes (`> `, `> > `,
+    // `  > ` dans une liste)
+    fn content_start(&self, line_start: usize) -> usize {
+        let start = self.item_prefix(line_start).unwrap_or(line_start);
+        let written = self.source[line_start..start].matches('>').count();
+        let mut rest = &self.source[start..];
+        for _ in written..self.quote_depth {
+            let Some(after) = rest.trim_start_matches(' ').strip_prefix('>') else {
+                break;
+            };
+            rest = after.strip_prefix(' ').unwrap_or(after);
+        }
+        self.source.len() - rest.len()
+    }
+
+    // Le premier enfant de l'élément en attente est un bloc commençant à `pos` : sur
+    // la même ligne, le marqueur de l'élément lui est laissé ; sinon l'élément est
+    // un bloc vide
+    fn item_child(&mut self, pos: usize) {
+        let Some(item_start) = self.item_pending else {
+            return;
+        };
+        if self.line_start(item_start) == self.line_start(pos) {
+            self.item_pending = None;
+            self.item_prefix_end = Some(pos);
+        } else {
+            self.empty_item(item_start);
+        }
+    }
+
+    // Élément sans contenu (`- `) : un bloc quand même. Les blancs après le marqueur
+    // sont gardés comme sa source.
+    fn empty_item(&mut self, item_start: usize) {
+        let mut pending = self.context_block(item_start);
+        let rest = &self.source[pending.end..];
+        let line = &rest[..rest.find(['\n', '\r']).unwrap_or(rest.len())];
+        if !line.is_empty() && line.trim().is_empty() {
+            pending.extend_content(&(pending.end..pending.end + line.len()));
+        }
+        self.open(pending);
+        self.flush();
+    }
+
+    // Type d'un nouveau bloc de texte selon le contexte (liste, citation)
+    fn context_block(&mut self, pos: usize) -> PendingBlock {
+        if let Some(item_start) = self.item_pending.take() {
+            let ordered = self.lists.last().copied().unwrap_or(false);
+            let ty = if ordered {
+                BlockType::OrderedListItem
+            } else {
+                BlockType::ListItem
+            };
+            let line_start = self.line_start(item_start);
+            let mut pending =
+                PendingBlock::new(ty, self.lists.len().saturating_sub(1) as u8, line_start);
+            self.read_list_marker(&mut pending, item_start);
+            pending
+        } else if self.quote_depth > 0 {
+            PendingBlock::new(BlockType::Quote, 0, self.line_start(pos))
+        } else {
+            PendingBlock::new(BlockType::Paragraph, 0, self.line_start(pos))
+        }
+    }
+
+    fn read_list_marker(&self, pending: &mut PendingBlock, item_start: usize) {
+        let rest = &self.source[item_start..];
+        let marker_pos = item_start + (rest.len() - rest.trim_start_matches(' ').len());
+        let content_start = self.content_start(pending.line_start);
+        pending.syntax.leading_spaces = self.leading_spaces(content_start, marker_pos);
+
+        let marker = &self.source[marker_pos..];
+        let digits = marker.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if digits > 0 {
+            pending.syntax.number = marker[..digits].parse().ok();
+            pending.syntax.marker = marker[digits..].chars().next().map(String::from);
+        } else {
+            pending.syntax.marker = marker.chars().next().map(String::from);
+        }
+        pending.end = marker_pos + digits + 1;
+    }
+
+    fn open(&mut self, pending: PendingBlock) {
+        self.flush();
+        self.current = Some(pending);
+    }
+
+    fn current(&mut self, pos: usize) -> &mut PendingBlock {
+        if self.current.is_none() {
+            let pending = self.context_block(pos);
+            self.current = Some(pending);
+        }
+        self.current.as_mut().unwrap()
+    }
+
+    fn push_text(&mut self, text: &str, range: &Range<usize>) {
+        let style = self.style();
+        let link = self.link.clone();
+        // pulldown-cmark commence un nouveau texte au caractère échappé (précédé d'un
+        // nombre impair de `\`)
+        let backslashes = self.source.as_bytes()[..range.start]
+            .iter()
+            .rev()
+            .take_while(|&&b| b == b'\\')
+            .count();
+        let escaped = backslashes % 2 == 1
+            && text.starts_with(|c: char| c.is_ascii_punctuation())
+            && self.source[range.start..].starts_with(&text[..1]);
+        let pending = self.current(range.start);
+        if escaped {
+            let at = pending.styles.iter().map(|s| s.len).sum();
+            pending.escaped.push(at);
+        }
+        pending.push(text, style, link.as_ref());
+        pending.extend_content(range);
+    }
+
+    // `<em>`, `</strong>`... écrits là où un marqueur ne serait pas reconnu
+    fn style_tag(&mut self, html: &str, range: &Range<usize>) -> bool {
+        let (close, name) = match html.strip_prefix("</") {
+            Some(rest) => (true, rest),
+            None => (false, &html[1..]),
+        };
+        let Some((style, _)) = HTML_TAGS
+            .iter()
+            .find(|(_, tag)| name.strip_prefix(tag) == Some(">"))
+        else {
+            return false;
+        };
+        if !close {
+            self.html_styles.push(*style);
+        } else if let Some(i) = self.html_styles.iter().rposition(|s| s == style) {
+            self.html_styles.remove(i);
+        }
+        self.current(range.start).extend_content(range);
+        true
+    }
+
+    // Définition de lien ([r]: url) ou zone de conflit : conservée telle quelle dans
+    // un bloc de texte
+    fn push_raw(&mut self, range: Range<usize>) {
+        self.flush();
+        let raw = self.source[range.clone()].trim_end();
+        let mut pending = self.context_block(range.start);
+        pending.syntax.raw = true;
+        pending.push(raw, StyleBits::default(), None);
+        pending.extend_content(&(range.start..range.start + raw.len()));
+        self.open(pending);
+        self.flush();
+    }
+
+    fn flush(&mut self) {
+        let Some(mut pending) = self.current.take() else {
+            return;
+        };
+        self.quote_pending = None;
+
+        let source = self.source;
+        while pending.end > pending.line_start
+            && matches!(source.as_bytes()[pending.end - 1], b'\n' | b'\r')
+        {
+            pending.end -= 1;
+        }
+
+        // Écrits devant le bloc : marqueur de l'élément dont il est le premier enfant, et
+        // `>` des citations s'il n'en est pas une lui-même
+        let content_start = match pending.ty {
+            BlockType::Quote => self
+                .item_prefix(pending.line_start)
+                .unwrap_or(pending.line_start),
+            _ => self.content_start(pending.line_start),
+        };
+        self.item_prefix_end = None;
+        if content_start > pending.line_start {
+            pending.syntax.container_prefix =
+                Some(source[pending.line_start..content_start].to_string());
+        }
+
+        if pending.ty == BlockType::Quote {
+            // Marqueur tel qu'écrit ("> ", ">", ...)
+            if let Some(content) = &pending.content {
+                let prefix = &source[content_start..content.start.max(content_start)];
+                let marker = prefix.trim_start_matches(' ');
+                if marker.starts_with('>') && marker.trim_start_matches(['>', ' ']).is_empty() {
+                    pending.syntax.leading_spaces = Some(prefix.len() - marker.len());
+                    pending.syntax.marker = Some(marker.to_string());
+                }
+            }
+        } else if pending.syntax.leading_spaces.is_none()
+            && !pending.is_list()
+            && !is_indented_code(&pending)
+        {
+            let line = &source[content_start..pending.end.max(content_start)];
+            let spaces = line.len() - line.trim_start_matches(' ').len();
+            if spaces > 0 {
+                pending.syntax.leading_spaces = Some(spaces);
+            }
+        }
+
+        // `# Titre #` : clôture telle qu'écrite après le texte
+        if let Some(content) = pending.content.as_ref().filter(|c| c.end <= pending.end) {
+            if pending
+                .syntax
+                .marker
+                .as_deref()
+                .is_some_and(|m| m.starts_with('#'))
+            {
+                let closing = &source[content.end..pending.end];
+                if !closing.is_empty() {
+                    pending.syntax.atx_closing = Some(closing.to_string());
+                }
+            }
+        }
+
+        // Code dont une ligne suivante n'a pas le préfixe attendu (ligne vide `>`, ligne
+        // paresseuse) : conservé tel qu'écrit
+        let continuation = pending.syntax.continuation_prefix();
+        if pending.ty == BlockType::CodeBlock
+            && !continuation.is_empty()
+            && source[pending.line_start..pending.end]
+                .lines()
+                .skip(1)
+                .any(|line| !line.starts_with(&continuation))
+        {
+            let mut raw = PendingBlock::new(BlockType::Paragraph, 0, pending.line_start);
+            raw.syntax.raw = true;
+            raw.syntax.container_prefix = pending.syntax.container_prefix.take();
+            raw.push(
+                &source[content_start..pending.end],
+                StyleBits::default(),
+                None,
+            );
+            raw.end = pending.end;
+            pending = raw;
+        }
+
+        if self.prev_end <= pending.line_start {
+            pending.syntax.separator = Some(source[self.prev_end..pending.line_start].to_string());
+        }
+        self.prev_end = self.prev_end.max(pending.end);
+
+        let mut block = Block::new(self.next_id, pending.ty, "");
+        self.next_id += 1;
+        block.indent = pending.indent;
+        block.text = pending.text.into();
+        if !pending.styles.is_empty() {
+            block.styles = pending.styles;
+        }
+        if block.ty != BlockType::CodeBlock && !pending.syntax.raw {
+            apply_extended_markers(&mut block, &pending.escaped);
+        }
+        block.info = pending.info;
+        block.checked = pending.checked;
+        block.columns = pending.columns;
+        block.syntax = pending.syntax;
+        if block.ty != BlockType::CodeBlock {
+            if let Some(content) = pending.content {
+                let raw = source[content].trim_end_matches(['\n', '\r']).to_string();
+                block.syntax.source = Some((block.content_hash(), raw));
+            }
+        }
+        self.blocks.push(block);
+    }
+
+    fn handle(&mut self, event: Event, range: Range<usize>) {
+        match event {
+            Event::Start(tag) => self.start(tag, range),
+            Event::End(tag) => self.end(tag, range),
+            Event::Text(text) => self.push_text(&text, &range),
+            Event::Code(code) => {
+                let mut style = self.style();
+                style.is_code = true;
+                let link = self.link.clone();
+                let pending = self.current(range.start);
+                pending.push(&code, style, link.as_ref());
+                pending.extend_content(&range);
+            }
+            Event::InlineHtml(html) if self.style_tag(&html, &range) => {}
+            Event::Html(html) | Event::InlineHtml(html) => self.push_text(&html, &range),
+            Event::SoftBreak => self.push_text("\n", &range),
+            Event::HardBreak => {
+                let mut style = self.style();
+                style.is_hard_break = true;
+                let link = self.link.clone();
+                // `\` ou espaces, tels qu'écrits avant le saut de ligne
+                let written = self.source[range.clone()].trim_end_matches(['\n', '\r']);
+                let pending = self.current(range.start);
+                if pending.syntax.hard_break.is_none() {
+                    pending.syntax.hard_break = Some(written.to_string());
+                }
+                pending.push("\n", style, link.as_ref());
+                pending.extend_content(&range);
+            }
+            Event::TaskListMarker(checked) => {
+                // `[ ]` / `[x]` en tête d'élément : hors du contenu inline
+                let pending = self.current(range.start);
+                if pending.is_list() && pending.text.is_empty() {
+                    pending.ty = BlockType::TaskItem;
+                    pending.checked = checked;
+                    pending.end = pending.end.max(range.end);
+                }
+            }
+            Event::Rule => {
+                let mut pending = self.context_block(range.start);
+                pending.push("---", StyleBits::default(), None);
+                let rule = self.source[range.clone()].trim_end();
+                let rule_start = range.start + (rule.len() - rule.trim_start().len());
+                pending.extend_content(&(rule_start..range.start + rule.len()));
+                self.open(pending);
+                self.flush();
+            }
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag, range: Range<usize>) {
+        match tag {
+            Tag::Paragraph => {
+                let pending = self.context_block(range.start);
+                self.open(pending);
+            }
+            Tag::Heading { level, .. } => {
+                let ty = match level {
+                    HeadingLevel::H1 => BlockType::Heading1,
+                    HeadingLevel::H2 => BlockType::Heading2,
+                    HeadingLevel::H3 => BlockType::Heading3,
+                    HeadingLevel::H4 => BlockType::Heading4,
+                    // Pas de Heading6 dans le modèle
+                    HeadingLevel::H5 | HeadingLevel::H6 => BlockType::Heading5,
+                };
+                self.item_child(range.start);
+                let mut pending = PendingBlock::new(ty, 0, self.line_start(range.start));
+                let heading = self.source[range.clone()].trim_start_matches(' ');
+                if heading.starts_with('#') {
+                    let hashes = heading.bytes().take_while(|&b| b == b'#').count();
+                    pending.syntax.marker = Some("#".repeat(hashes));
+                } else if let Some(underline) = heading.trim_end().lines().last() {
+                    pending.syntax.setext_underline = Some(underline.trim().to_string());
+                }
+                pending.end = range.end;
+                self.open(pending);
+            }
+            Tag::CodeBlock(kind) => {
+                self.item_child(range.start);
+                let line_start = self.line_start(range.start);
+                let mut pending = PendingBlock::new(BlockType::CodeBlock, 0, line_start);
+                match kind {
+                    CodeBlockKind::Fenced(_) => {
+                        let code = self.source[range.clone()].trim_start_matches(' ');
+                        let fence_char = code.chars().next().unwrap_or('`');
+                        let fence_len = code.chars().take_while(|&c| c == fence_char).count();
+                        // Info string telle qu'écrite (espaces avant, échappements)
+                        let info_line = code[fence_len..].lines().next().unwrap_or("");
+                        pending.info = info_line.trim_end().to_string();
+                        pending.syntax.marker = Some(fence_char.to_string().repeat(fence_len));
+                    }
+                    CodeBlockKind::Indented => {
+                        let content_start = self.content_start(line_start);
+                        pending.syntax.marker =
+                            Some(self.source[content_start..range.start].to_string());
+                    }
+                }
+                pending.end = range.end;
+                self.open(pending);
+            }
+            Tag::HtmlBlock => {
+                let mut pending = self.context_block(range.start);
+                pending.syntax.raw = true;
+                self.open(pending);
+            }
+            Tag::Table(alignments) => {
+                self.item_child(range.start);
+                let mut pending =
+                    PendingBlock::new(BlockType::Table, 0, self.line_start(range.start));
+                pending.columns = alignments
+                    .iter()
+                    .map(|a| match a {
+                        Alignment::None => ColumnAlign::None,
+                        Alignment::Left => ColumnAlign::Left,
+                        Alignment::Center => ColumnAlign::Center,
+                        Alignment::Right => ColumnAlign::Right,
+                    })
+                    .collect();
+                self.open(pending);
+            }
+            Tag::TableHead | Tag::TableRow => {
+                self.row_cells = 0;
+                let pending = self.current(range.start);
+                if !matches!(tag, Tag::TableHead) {
+                    pending.push("\n", StyleBits::default(), None);
+                }
+            }
+            Tag::TableCell => {
+                if self.row_cells > 0 {
+                    self.current(range.start)
+                        .push("\t", StyleBits::default(), None);
+                }
+                self.row_cells += 1;
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                self.item_child(range.start);
+                self.quote_depth += 1;
+                self.quote_pending = Some(range.start);
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.item_child(range.start);
+                self.lists.push(start.is_some());
+            }
+            Tag::Item => {
+                self.flush();
+                self.item_pending = Some(range.start);
+            }
+            Tag::Emphasis => {
+                self.italic_depth += 1;
+                self.current(range.start).extend_content(&range);
+            }
+            Tag::Strong => {
+                self.bold_depth += 1;
+                self.current(range.start).extend_content(&range);
+            }
+            Tag::Strikethrough => {
+                let double = self.source[range.clone()].starts_with("~~");
+                self.tildes.push(double);
+                self.current(range.start).extend_content(&range);
+            }
+            Tag::Link { dest_url, .. } => {
+                self.link = Some(dest_url.to_string());
+                self.current(range.start).extend_content(&range);
+            }
+            Tag::Image { dest_url, .. } => {
+                let pending = self.current(range.start);
+                pending.extend_content(&range);
+                let start = pending.text.len();
+                self.image = Some((self.link.replace(dest_url.to_string()), start));
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd, range: Range<usize>) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::Heading(_) => {
+                if let Some(pending) = self.current.as_mut() {
+                    pending.end = pending.end.max(range.end);
+                }
+                self.flush();
+            }
+            TagEnd::CodeBlock | TagEnd::HtmlBlock => {
+                if let Some(pending) = self.current.as_mut() {
+                    trim_trailing_newline(pending);
+                    pending.end = pending.end.max(range.end);
+                }
+                self.flush();
+            }
+            TagEnd::Table => {
+                if let Some(pending) = self.current.as_mut() {
+                    // Le tableau entier est conservé tel qu'écrit tant qu'il n'est pas modifié
+                    let table = self.source[range.clone()].trim_end_matches(['\n', '\r']);
+                    let start = range.start + (table.len() - table.trim_start().len());
+                    pending.content = Some(start..range.start + table.len());
+                    pending.end = range.end;
+                }
+                self.flush();
+            }
+            TagEnd::BlockQuote(_) => {
+                if let Some(quote_start) = self.quote_pending {
+                    // Empty quote (">"): still a block
+                    let pending = self.context_block(quote_start);
+                    self.open(pending);
+                }
+                self.flush();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+            }
+            TagEnd::Item => {
+                if let Some(item_start) = self.item_pending {
+                    self.empty_item(item_start);
+                }
+                self.flush();
+            }
+            TagEnd::Emphasis => self.italic_depth = self.italic_depth.saturating_sub(1),
+            TagEnd::Strong => self.bold_depth = self.bold_depth.saturating_sub(1),
+            TagEnd::Strikethrough => {
+                self.tildes.pop();
+            }
+            TagEnd::Link => self.link = None,
+            TagEnd::Image => {
+                let style = self.style();
+                let Some((outer, start)) = self.image.take() else {
+                    return;
+                };
+                let image = std::mem::replace(&mut self.link, outer);
+                // Sans texte alternatif, l'image garde une place dans le texte
+                if let Some(pending) = self.current.as_mut().filter(|p| p.text.len() == start) {
+                    pending.push(&IMAGE_PLACEHOLDER.to_string(), style, image.as_ref());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Analyse une partie de la source, en gardant les positions dans la source entière
+    fn parse(&mut self, part: Range<usize>) {
+        let source = self.source;
+        let offset = part.start;
+        let shift = |range: Range<usize>| range.start + offset..range.end + offset;
+        let options =
+            Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS | Options::ENABLE_TABLES;
+        let events = Parser::new_ext(&source[part], options).into_offset_iter();
+
+        // Les définitions de liens ne produisent aucun événement
+        let mut definitions: Vec<Range<usize>> = events
+            .reference_definitions()
+            .iter()
+            .map(|(_, def)| shift(def.span.clone()))
+            .collect();
+        definitions.sort_by_key(|def| def.start);
+        let mut definitions = definitions.into_iter().peekable();
+
+        for (event, range) in events {
+            let range = shift(range);
+            let is_end = matches!(event, Event::End(_));
+            while let Some(def) = definitions
+                .next_if(|def| def.start < range.start || (is_end && def.end <= range.end))
+            {
+                self.push_raw(def);
+            }
+            self.handle(event, range);
+        }
+        for def in definitions {
+            self.push_raw(def);
+        }
+        self.flush();
+    }
+}
+
+fn is_indented_code(pending: &PendingBlock) -> bool {
+    pending.ty == BlockType::CodeBlock
+        && pending
+            .syntax
+            .marker
+            .as_deref()
+            .is_some_and(|m| m.bytes().all(|b| b == b' ' || b == b'\t'))
+}
+
+// `==surligné==`, `^exposant^` et `H~2~O` (indice au milieu d'un mot) ne sont pas
+// reconnus par pulldown-cmark : on les repère dans le texte déjà découpé en spans
+fn apply_extended_markers(block: &mut Block, escaped: &[usize]) {
+    if !block.text.contains(['=', '^', '~']) {
+        return;
+    }
+    let mut text = block.text.chars();
+    let mut chars: Vec<(char, StyleSpan, bool)> = Vec::new();
+    for span in &block.styles {
+        for c in text.by_ref().take(span.len) {
+            let literal = escaped.contains(&chars.len());
+            chars.push((
+                c,
+                StyleSpan {
+                    len: 1,
+                    ..span.clone()
+                },
+                literal,
+            ));
+        }
+    }
+
+    for style in [
+        InlineStyle::Highlight,
+        InlineStyle::Superscript,
+        InlineStyle::Subscript,
+    ] {
+        let marker: Vec<char> = style.marker().chars().collect();
+        // Le marqueur exact, pas un morceau de `===` ou de `~~`
+        let is_marker = |chars: &[(char, StyleSpan, bool)], at: usize| {
+            let outside = |pos: Option<usize>| {
+                pos.and_then(|p| chars.get(p))
+                    .is_none_or(|(c, ..)| *c != marker[0])
+            };
+            marker.iter().enumerate().all(|(n, m)| {
+                matches!(chars.get(at + n), Some((c, span, false)) if c == m && !span.style.is_code)
+            }) && outside(at.checked_sub(1))
+                && outside(Some(at + marker.len()))
+        };
+        let mut i = 0;
+        while i < chars.len() {
+            let inner = i + marker.len();
+            let opens = is_marker(&chars, i)
+                && matches!(chars.get(inner), Some((c, ..)) if !c.is_whitespace());
+            let mut close = None;
+            if opens {
+                for j in inner + 1..chars.len() {
+                    // Pandoc : pas d'espace dans un exposant ou un indice
+                    if style != InlineStyle::Highlight && chars[j].0.is_whitespace() {
+                        break;
+                    }
+                    if is_marker(&chars, j) && !chars[j - 1].0.is_whitespace() {
+                        close = Some(j);
+                        break;
+                    }
+                }
+            }
+            let Some(close) = close else {
+                i += 1;
+                continue;
+            };
+            for (_, span, _) in &mut chars[inner..close] {
+                span.style.set(style, true);
+            }
+            chars.drain(close..close + marker.len());
+            chars.drain(i..inner);
+            i = close - marker.len();
+        }
+    }
+
+    block.text = chars.iter().map(|(c, ..)| *c).collect();
+    block.styles.clear();
+    for (_, span, _) in chars {
+        match block.styles.last_mut() {
+            Some(last) if last.style == span.style && last.link == span.link => last.len += 1,
+            _ => block.styles.push(span),
+        }
+    }
+    if block.styles.is_empty() {
+        block.styles.push(StyleSpan {
+            len: 0,
+            style: StyleBits::default(),
+            link: None,
+        });
+    }
+}
+
+fn trim_trailing_newline(pending: &mut PendingBlock) {
+    if pending.text.ends_with('\n') {
+        pending.text.pop();
+        if let Some(last) = pending.styles.last_mut() {
+            last.len -= 1;
+            if last.len == 0 {
+                pending.styles.pop();
+            }
+        }
+    }
+}
+
+/// Whether pasted text is written in Markdown: block markers at the start of a line,
+/// or inline markers. Other text is pasted line by line, as typed.
+pub fn looks_like_markdown(text: &str) -> bool {
+    let block_marker = text.lines().any(|line| {
+        let line = line.trim_start();
+        let hashes = line.bytes().take_while(|&b| b == b'#').count();
+        let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+        (1..=6).contains(&hashes) && line[hashes..].starts_with(' ')
+            || ["- ", "* ", "+ ", "> ", "```", "~~~"]
+                .iter()
+                .any(|marker| line.starts_with(marker))
+            || digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") "))
+            || line.len() > 1 && line.starts_with('|') && line.ends_with('|')
+    });
+    let inline_marker = ["**", "__", "~~", "](", "`"]
+        .iter()
+        .any(|marker| text.contains(marker));
+    block_marker || inline_marker
+}
+
+// Zones `<<<<<<<` / `=======` / `>>>>>>>` laissées par une fusion, de la première ligne
+// à la fin de la dernière
+fn conflict_regions(source: &str) -> Vec<Range<usize>> {
+    let mut regions = Vec::new();
+    let mut open = None;
+    let mut separated = false;
+    let mut pos = 0;
+    for line in source.split_inclusive('\n') {
+        let text = line.trim_end();
+        if text.starts_with("<<<<<<<") {
+            open = Some(pos);
+            separated = false;
+        } else if text == "=======" {
+            separated = open.is_some();
+        } else if text.starts_with(">>>>>>>") && separated {
+            if let Some(start) = open.take() {
+                regions.push(start..pos + text.len());
+            }
+            separated = false;
+        }
+        pos += line.len();
+    }
+    regions
+}
+
+/// Parses CommonMark source into editor blocks. Ids start at `first_id`. Conflict
+/// regions left by a merge are kept as written, in raw blocks.
+pub fn parse_markdown(source: &str, first_id: u64) -> Vec<Block> {
+    let mut builder = BlockBuilder::new(source, first_id);
+    let mut start = 0;
+    for conflict in conflict_regions(source) {
+        builder.parse(start..conflict.start);
+        builder.push_raw(conflict.clone());
+        start = conflict.end;
+    }
+    builder.parse(start..source.len());
+    builder.blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::model::document::Document;
+
+    fn types(blocks: &[Block]) -> Vec<BlockType> {
+        blocks.iter().map(|b| b.ty.clone()).collect()
+    }
+
+    #[test]
+    fn test_parse_block_types() {
+        let src = "# Title\n\nSome *text*\non two lines\n\n> quoted\n\n- one\n- two\n  - nested\n\n3. three\n4. four\n\n```rust\nfn main() {}\n```\n";
+        let blocks = parse_markdown(src, 1);
+        assert_eq!(
+            types(&blocks),
+            vec![
+                BlockType::Heading1,
+                BlockType::Paragraph,
+                BlockType::Quote,
+                BlockType::ListItem,
+                BlockType::ListItem,
+                BlockType::ListItem,
+                BlockType::OrderedListItem,
+                BlockType::OrderedListItem,
+                BlockType::CodeBlock,
+            ]
+        );
+        assert_eq!(blocks[1].text, "Some text\non two lines");
+        assert_eq!(blocks[5].indent, 1);
+        assert_eq!(blocks[8].text, "fn main() {}");
+        assert_eq!(blocks[8].info, "rust");
+    }
+
+    #[test]
+    fn test_parse_inline_styles() {
+        let blocks = parse_markdown("a **bold** and *it* with `code`", 1);
+        let block = &blocks[0];
+        assert_eq!(block.text, "a bold and it with code");
+        let styles: Vec<(usize, bool, bool, bool)> = block
+            .styles
+            .iter()
+            .map(|s| (s.len, s.style.is_bold, s.style.is_italic, s.style.is_code))
+            .collect();
+        assert_eq!(
+            styles,
+            vec![
+                (2, false, false, false),
+                (4, true, false, false),
+                (5, false, false, false),
+                (2, false, true, false),
+                (6, false, false, false),
+                (4, false, false, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_extended_styles() {
+        let src = "~~gone~~ ==hot== x^2^ H~2~O `a==b==`";
+        let block = &parse_markdown(src, 1)[0];
+        assert_eq!(block.text, "gone hot x2 H2O a==b==");
+        let styled: Vec<(&str, StyleBits)> = block
+            .styles
+            .iter()
+            .scan(0, |pos, span| {
+                let start = *pos;
+                *pos += span.len;
+                Some((&block.text[start..*pos], span.style))
+            })
+            .filter(|(_, style)| *style != StyleBits::default())
+            .collect();
+        let only = |style: InlineStyle| {
+            let mut bits = StyleBits::default();
+            bits.set(style, true);
+            bits
+        };
+        assert_eq!(
+            styled,
+            vec![
+                ("gone", only(InlineStyle::Strikethrough)),
+                ("hot", only(InlineStyle::Highlight)),
+                ("2", only(InlineStyle::Superscript)),
+                ("2", only(InlineStyle::Subscript)),
+                ("a==b==", only(InlineStyle::Code)),
+            ]
+        );
+        assert_eq!(block.to_markdown(), src);
+    }
+
+    #[test]
+    fn test_saved_document_reloads_identically() {
+        let src = "# Title\n\nSome **bold** text\n\n- item\n\n1. first\n\n```\nlet x = 1;\n```";
+        let doc = Document::from_markdown(src);
+        let mut out = Vec::new();
+        doc.write_markdown(&mut out).unwrap();
+        let reloaded = Document::from_markdown(std::str::from_utf8(&out).unwrap());
+
+        assert_eq!(types(&doc.blocks), types(&reloaded.blocks));
+        for (a, b) in doc.blocks.iter().zip(reloaded.blocks.iter()) {
+            assert_eq!(a.text, b.text);
+            assert_eq!(a.to_markdown(), b.to_markdown());
+        }
+    }
+}
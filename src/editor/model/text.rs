@@ -0,0 +1,272 @@
+// Texte d'un bloc, indexé par caractère : une position (en caractères) se convertit
+// en octets sans parcourir tout le texte.
+use std::fmt;
+use std::ops::{Deref, Range};
+
+// Caractères par tronçon de l'index, au plus
+const CHUNK: usize = 64;
+
+/// Block text kept in one `String`, with an index of chunk starts so that char
+/// positions map to byte offsets in O(log n). Derefs to `str` for reading; edits go
+/// through the methods below, which keep the index up to date.
+#[derive(Clone)]
+pub struct BlockText {
+    text: String,
+    /// (byte, char) at the start of each chunk. The first one is always (0, 0).
+    chunks: Vec<(usize, usize)>,
+    len_chars: usize,
+}
+
+impl BlockText {
+    pub fn new() -> Self {
+        Self::from(String::new())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Number of chars, in O(1).
+    pub fn len_chars(&self) -> usize {
+        self.len_chars
+    }
+
+    // Tronçon contenant le caractère `char_idx`
+    fn chunk_of_char(&self, char_idx: usize) -> (usize, usize) {
+        let k = self.chunks.partition_point(|&(_, c)| c <= char_idx);
+        self.chunks[k - 1]
+    }
+
+    /// Byte offset of `char_idx`, or the text length past the end.
+    pub fn char_to_byte(&self, char_idx: usize) -> usize {
+        if char_idx >= self.len_chars {
+            return self.text.len();
+        }
+        let (byte, start) = self.chunk_of_char(char_idx);
+        self.text[byte..]
+            .char_indices()
+            .nth(char_idx - start)
+            .map_or(self.text.len(), |(i, _)| byte + i)
+    }
+
+    /// Char index of `byte_idx`, which must be on a char boundary.
+    pub fn byte_to_char(&self, byte_idx: usize) -> usize {
+        let byte_idx = byte_idx.min(self.text.len());
+        let k = self.chunks.partition_point(|&(b, _)| b <= byte_idx);
+        let (byte, start) = self.chunks[k - 1];
+        start + self.text[byte..byte_idx].chars().count()
+    }
+
+    pub fn char_at(&self, char_idx: usize) -> Option<char> {
+        if char_idx >= self.len_chars {
+            return None;
+        }
+        self.text[self.char_to_byte(char_idx)..].chars().next()
+    }
+
+    /// Text between two char indices, clamped to the text.
+    pub fn slice_chars(&self, range: Range<usize>) -> &str {
+        let start = self.char_to_byte(range.start);
+        let end = self.char_to_byte(range.end).max(start);
+        &self.text[start..end]
+    }
+
+    pub fn insert_str(&mut self, byte_idx: usize, text: &str) {
+        self.replace_range(byte_idx..byte_idx, text);
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.replace_range(self.text.len()..self.text.len(), text);
+    }
+
+    /// Removes the char starting at `byte_idx` and returns it.
+    pub fn remove(&mut self, byte_idx: usize) -> char {
+        let c = self.text[byte_idx..]
+            .chars()
+            .next()
+            .expect("byte index past the end");
+        self.replace_range(byte_idx..byte_idx + c.len_utf8(), "");
+        c
+    }
+
+    /// Splits the text at `byte_idx` and returns what follows.
+    pub fn split_off(&mut self, byte_idx: usize) -> BlockText {
+        let tail = BlockText::from(&self.text[byte_idx..]);
+        self.replace_range(byte_idx..self.text.len(), "");
+        tail
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Replaces a byte range. Only the chunks touched by the edit are scanned again;
+    /// the ones after it are shifted.
+    pub fn replace_range(&mut self, range: Range<usize>, with: &str) {
+        let Range { start, end } = range;
+        let removed_chars = self.text[start..end].chars().count();
+        self.text.replace_range(start..end, with);
+        let added_chars = with.chars().count();
+
+        // Tronçons touchés : de celui qui contient `start` au premier qui commence après `end`
+        let first = self.chunks.partition_point(|&(b, _)| b <= start) - 1;
+        let next = self
+            .chunks
+            .partition_point(|&(b, _)| b <= end)
+            .max(first + 1);
+        let shift = |(b, c): (usize, usize)| {
+            (
+                b + with.len() - (end - start),
+                c + added_chars - removed_chars,
+            )
+        };
+        let region_end = self
+            .chunks
+            .get(next)
+            .map_or(self.text.len(), |&chunk| shift(chunk).0);
+
+        let (region_start, mut char_idx) = self.chunks[first];
+        let mut rebuilt = Vec::new();
+        for (n, (i, _)) in self.text[region_start..region_end]
+            .char_indices()
+            .enumerate()
+        {
+            if n % CHUNK == 0 {
+                rebuilt.push((region_start + i, char_idx));
+            }
+            char_idx += 1;
+        }
+        for chunk in &mut self.chunks[next..] {
+            *chunk = shift(*chunk);
+        }
+        self.chunks.splice(first..next, rebuilt);
+        if self.chunks.is_empty() {
+            self.chunks.push((0, 0));
+        }
+        self.len_chars = self.len_chars + added_chars - removed_chars;
+    }
+}
+
+impl Default for BlockText {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<String> for BlockText {
+    fn from(text: String) -> Self {
+        let mut chunks = vec![(0, 0)];
+        let mut len_chars = 0;
+        for (i, _) in text.char_indices() {
+            if len_chars > 0 && len_chars % CHUNK == 0 {
+                chunks.push((i, len_chars));
+            }
+            len_chars += 1;
+        }
+        Self {
+            text,
+            chunks,
+            len_chars,
+        }
+    }
+}
+
+impl From<&str> for BlockText {
+    fn from(text: &str) -> Self {
+        Self::from(text.to_string())
+    }
+}
+
+impl FromIterator<char> for BlockText {
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<String>())
+    }
+}
+
+impl Deref for BlockText {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Debug for BlockText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.text, f)
+    }
+}
+
+impl fmt::Display for BlockText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl PartialEq for BlockText {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl PartialEq<str> for BlockText {
+    fn eq(&self, other: &str) -> bool {
+        self.text == other
+    }
+}
+
+impl PartialEq<&str> for BlockText {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+impl PartialEq<String> for BlockText {
+    fn eq(&self, other: &String) -> bool {
+        &self.text == other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char_index() {
+        let line = "déjà vu 👍🏽 ";
+        let mut text = BlockText::from(line.repeat(40));
+        let mut expected = line.repeat(40);
+        let check = |text: &BlockText, expected: &str| {
+            assert_eq!(text.len_chars(), expected.chars().count());
+            for (n, (i, c)) in expected.char_indices().enumerate() {
+                assert_eq!(text.char_to_byte(n), i);
+                assert_eq!(text.byte_to_char(i), n);
+                assert_eq!(text.char_at(n), Some(c));
+            }
+            assert_eq!(text.char_to_byte(text.len_chars()), expected.len());
+        };
+        check(&text, &expected);
+
+        // Éditions au début, au milieu (à cheval sur plusieurs tronçons) et à la fin
+        for (range, with) in [(0..0, "« "), (100..260, "x"), (30..31, ""), (0..0, "")] {
+            let range = text.char_to_byte(range.start)..text.char_to_byte(range.end);
+            text.replace_range(range.clone(), with);
+            expected.replace_range(range, with);
+            check(&text, &expected);
+        }
+        let c = text.remove(text.char_to_byte(5));
+        assert_eq!(
+            c,
+            expected.remove(expected.char_indices().nth(5).unwrap().0)
+        );
+        text.push_str("fin");
+        expected.push_str("fin");
+        check(&text, &expected);
+        assert_eq!(text, expected);
+        assert_eq!(text.slice_chars(0..4), "« dé");
+
+        text.replace_range(0..text.len(), "");
+        check(&text, "");
+        assert_eq!(text.char_at(0), None);
+    }
+}
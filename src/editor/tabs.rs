@@ -0,0 +1,136 @@
+// Onglets : état gardé pour chaque document ouvert qui n'est pas affiché
+use crate::editor::model::block::{Block, BlockType};
+use crate::editor::model::document::Document;
+use crate::editor::model::history::History;
+use crate::editor::watcher::{FileWatcher, OwnWrites};
+use std::path::Path;
+
+/// Everything that belongs to one open document rather than to the editor.
+#[derive(Default)]
+pub struct DocTab {
+    pub document: Document,
+    pub history: History,
+    pub current_file: Option<String>,
+    pub cursor_block: usize,
+    pub cursor_char: usize,
+    pub selection_anchor: Option<(usize, usize)>,
+    pub scroll_y: f64,
+    pub watcher: Option<FileWatcher>,
+    pub disk_base: String,
+    pub disk_conflict: Option<String>,
+    pub own_writes: OwnWrites,
+}
+
+impl DocTab {
+    /// Empty document, not yet associated with a file.
+    pub fn blank() -> Self {
+        Self {
+            document: Document::from_blocks(vec![Block::new(1, BlockType::Paragraph, "")]),
+            ..Self::default()
+        }
+    }
+
+    pub fn info(&self) -> TabInfo {
+        TabInfo::new(
+            self.current_file.as_deref(),
+            &self.document,
+            self.disk_conflict.is_some(),
+        )
+    }
+
+    /// The file of this hidden tab changed on disk: same rules as for the tab on
+    /// screen, reloaded without local edits, kept as a conflict otherwise.
+    pub fn disk_changed(&mut self, content: String) {
+        if content == self.disk_base {
+            return;
+        }
+        if self.own_writes.is_own(&content) || content == self.document.to_markdown() {
+            self.disk_base = content;
+            return;
+        }
+        if self.document.is_modified() {
+            self.disk_conflict = Some(content);
+            return;
+        }
+        self.document = Document::from_markdown(&content);
+        self.history.clear();
+        self.selection_anchor = None;
+        (self.cursor_block, self.cursor_char) =
+            clamp_cursor(&self.document, self.cursor_block, self.cursor_char);
+        self.disk_base = content;
+    }
+}
+
+// Position la plus proche qui existe encore dans le document
+pub fn clamp_cursor(doc: &Document, block: usize, char_idx: usize) -> (usize, usize) {
+    let Some(last) = doc.blocks.len().checked_sub(1) else {
+        return (0, 0);
+    };
+    let block = block.min(last);
+    (block, char_idx.min(doc.blocks[block].text_len()))
+}
+
+/// What the tab strip shows for one tab.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TabInfo {
+    pub title: String,
+    pub path: Option<String>,
+    pub modified: bool,
+    /// The file changed on disk while the tab had unsaved edits.
+    pub conflict: bool,
+}
+
+impl TabInfo {
+    pub fn new(path: Option<&str>, document: &Document, conflict: bool) -> Self {
+        Self {
+            title: tab_title(path),
+            path: path.map(str::to_string),
+            modified: document.is_modified(),
+            conflict,
+        }
+    }
+}
+
+// Nom du fichier seul, le chemin complet reste dans le titre de la fenêtre
+pub fn tab_title(path: Option<&str>) -> String {
+    match path.and_then(|path| Path::new(path).file_name()) {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => "Sans titre".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tab_info() {
+        let mut tab = DocTab::blank();
+        assert_eq!(tab.info().title, "Sans titre");
+        assert!(!tab.info().modified);
+
+        tab.current_file = Some("notes/idées.md".to_string());
+        tab.document.mark_modified();
+        let info = tab.info();
+        assert_eq!(info.title, "idées.md");
+        assert_eq!(info.path.as_deref(), Some("notes/idées.md"));
+        assert!(info.modified);
+
+        // Fichier modifié ailleurs pendant l'édition : gardé en conflit
+        tab.disk_changed("# Autre version\n".to_string());
+        assert!(tab.info().conflict);
+        assert_eq!(tab.document.blocks[0].text, "");
+    }
+
+    #[test]
+    fn test_own_write_is_not_a_conflict() {
+        let mut tab = DocTab::blank();
+        tab.own_writes.record("# Enregistré\n");
+        // Modifié depuis l'enregistrement, signalé avant la fin de celui-ci
+        tab.document.insert_text_at(0, 0, "suite");
+        tab.disk_changed("# Enregistré\n".to_string());
+        assert!(!tab.info().conflict);
+        assert_eq!(tab.disk_base, "# Enregistré\n");
+        assert_eq!(tab.document.blocks[0].text, "suite");
+    }
+}
@@ -1,12 +1,89 @@
-use crate::editor::model::block::{BlockLayoutCache, BlockType};
+use crate::editor::highlight::{self, Token, TokenKind};
+use crate::editor::model::block::{BlockLayoutCache, BlockType, ColumnAlign, StyleSpan};
 use crate::editor::model::document::Document;
 use makepad_widgets::*;
 
+// Couleurs de la coloration syntaxique des blocs de code
+#[derive(Live, LiveHook)]
+pub struct SyntaxColors {
+    #[live]
+    pub keyword: Vec4,
+    #[live]
+    pub type_name: Vec4,
+    #[live]
+    pub function: Vec4,
+    #[live]
+    pub string: Vec4,
+    #[live]
+    pub number: Vec4,
+    #[live]
+    pub comment: Vec4,
+}
+
+impl SyntaxColors {
+    fn color(&self, kind: TokenKind) -> Option<Vec4> {
+        match kind {
+            TokenKind::Plain => None,
+            TokenKind::Keyword => Some(self.keyword),
+            TokenKind::Type => Some(self.type_name),
+            TokenKind::Function => Some(self.function),
+            TokenKind::String => Some(self.string),
+            TokenKind::Number => Some(self.number),
+            TokenKind::Comment => Some(self.comment),
+        }
+    }
+}
+
+// Dessine un morceau de code token par token; `text` commence à `text_offset` dans le bloc
+fn draw_code_tokens(
+    cx: &mut Cx2d,
+    draw_text: &mut DrawText,
+    colors: &SyntaxColors,
+    pos: DVec2,
+    text: &str,
+    text_offset: usize,
+    tokens: &[Token],
+) {
+    let base_color = draw_text.color;
+    let line_height = draw_text
+        .layout(cx, 0.0, 0.0, None, false, Align::default(), "A")
+        .size_in_lpxs
+        .height as f64;
+    let text_end = text_offset + text.len();
+    let mut x = pos.x;
+    let mut y = pos.y;
+
+    for token in tokens {
+        let start = token.range.start.max(text_offset);
+        let end = token.range.end.min(text_end);
+        if start >= end {
+            continue;
+        }
+        draw_text.color = colors.color(token.kind).unwrap_or(base_color);
+        let piece = &text[start - text_offset..end - text_offset];
+        for (i, line) in piece.split('\n').enumerate() {
+            if i > 0 {
+                x = pos.x;
+                y += line_height;
+            }
+            if !line.is_empty() {
+                draw_text.draw_abs(cx, dvec2(x, y), line);
+                x += draw_text
+                    .layout(cx, 0.0, 0.0, None, false, Align::default(), line)
+                    .size_in_lpxs
+                    .width as f64;
+            }
+        }
+    }
+    draw_text.color = base_color;
+}
+
 pub struct EditorView<'a> {
     pub draw_bg: &'a mut DrawColor,
     pub draw_text_reg: &'a mut DrawText,
     pub draw_text_bold: &'a mut DrawText,
     pub draw_text_italic: &'a mut DrawText,
+    pub draw_text_bold_italic: &'a mut DrawText,
     pub draw_text_code: &'a mut DrawText,
     pub draw_text_header1: &'a mut DrawText,
     pub draw_text_header2: &'a mut DrawText,
@@ -14,15 +91,26 @@ pub struct EditorView<'a> {
     pub draw_text_header4: &'a mut DrawText,
     pub draw_text_header5: &'a mut DrawText,
     pub draw_text_quote: &'a mut DrawText,
+    pub draw_text_link: &'a mut DrawText,
+    pub draw_link_underline: &'a mut DrawColor,
+    pub draw_strikethrough: &'a mut DrawColor,
+    pub draw_highlight: &'a mut DrawColor,
+    pub draw_checkbox: &'a mut DrawColor,
+    pub draw_checkbox_checked: &'a mut DrawColor,
+    pub draw_table_border: &'a mut DrawColor,
+    pub draw_table_header_bg: &'a mut DrawColor,
     pub draw_cursor: &'a mut DrawColor,
     pub draw_selection: &'a mut DrawColor,
     pub draw_code_bg: &'a mut DrawColor,
     pub draw_text_code_header: &'a mut DrawText,
+    pub syntax_colors: &'a SyntaxColors,
 }
 
 pub struct HitResult {
     pub block_idx: usize,
     pub char_idx: usize,
+    /// The finger landed on the checkbox of a task item.
+    pub on_checkbox: bool,
 }
 
 pub struct DrawParams<'a> {
@@ -102,7 +190,7 @@ impl<'a> EditorView<'a> {
         
         let mut current_y = start_y + content_y;
         
-        let mut list_counters: Vec<u32> = vec![0; 10];
+        let mut list_counters: Vec<Option<u64>> = vec![None; 10];
 
         // Optimization: Find the start of the current list "cluster" to avoid O(N) iteration from the beginning of the document.
         let mut scan_start_idx = 0;
@@ -110,7 +198,7 @@ impl<'a> EditorView<'a> {
             let mut i = start_block_idx;
             while i > 0 {
                 let block = &params.doc.blocks[i - 1];
-                if block.ty != BlockType::OrderedListItem && block.ty != BlockType::ListItem {
+                if !block.is_list_item() {
                     scan_start_idx = i;
                     break;
                 }
@@ -120,16 +208,19 @@ impl<'a> EditorView<'a> {
 
         for i in scan_start_idx..start_block_idx {
             let block = &params.doc.blocks[i];
-            if block.ty != BlockType::OrderedListItem && block.ty != BlockType::ListItem {
-                list_counters.fill(0);
+            if !block.is_list_item() {
+                list_counters.fill(None);
             } else if block.ty == BlockType::OrderedListItem {
                 let level = block.indent as usize;
                 if level >= list_counters.len() {
-                    list_counters.resize(level + 1, 0);
+                    list_counters.resize(level + 1, None);
                 }
-                list_counters[level] += 1;
+                list_counters[level] = Some(match list_counters[level] {
+                    Some(n) => n + 1,
+                    None => block.syntax.number.unwrap_or(1),
+                });
                 for counter in list_counters.iter_mut().skip(level + 1) {
-                    *counter = 0;
+                    *counter = None;
                 }
             }
         }
@@ -151,6 +242,7 @@ impl<'a> EditorView<'a> {
                 BlockType::Quote => 24.2,
                 BlockType::ListItem => 21.8,
                 BlockType::OrderedListItem => 21.8,
+                BlockType::TaskItem => 21.8,
                 _ => 21.8,
             };
 
@@ -175,6 +267,28 @@ impl<'a> EditorView<'a> {
                 continue;
             }
 
+            if block.ty == BlockType::Table {
+                list_counters.fill(None);
+                let height = self.draw_table(
+                    cx,
+                    &params,
+                    block_idx,
+                    dvec2(start_x, current_y),
+                    &mut hit_result,
+                );
+                let block = &mut params.doc.blocks[block_idx];
+                if !use_cached_layout {
+                    block.layout_cache = Some(BlockLayoutCache {
+                        height,
+                        width: params.rect.size.x,
+                    });
+                    block.is_dirty = false;
+                }
+                current_y += height + 5.0;
+                content_y += height + 5.0;
+                continue;
+            }
+
             let mut current_x = start_x;
 
             if block.ty == BlockType::CodeBlock {
@@ -190,14 +304,15 @@ impl<'a> EditorView<'a> {
                 });
                 
                 // Draw header
-                self.draw_text_code_header.draw_abs(cx, dvec2(start_x + 10.0, current_y + 5.0), "language");
+                let language = if block.language().is_empty() { "code" } else { block.language() };
+                self.draw_text_code_header.draw_abs(cx, dvec2(start_x + 10.0, current_y + 5.0), language);
                 
                 current_y += 32.0; // Space for header (22) + top margin (10)
                 current_x += 15.0; // Left margin
             }
 
-            if block.ty != BlockType::OrderedListItem && block.ty != BlockType::ListItem {
-                list_counters.fill(0);
+            if !block.is_list_item() {
+                list_counters.fill(None);
             }
 
             if block.ty == BlockType::ListItem {
@@ -209,19 +324,58 @@ impl<'a> EditorView<'a> {
                         .draw_abs(cx, dvec2(current_x, current_y), "• ");
                 }
                 current_x += 15.0;
+            } else if block.ty == BlockType::TaskItem {
+                current_x += (block.indent as f64) * 20.0;
+                let checkbox = Rect {
+                    pos: dvec2(current_x, current_y + 3.0),
+                    size: dvec2(13.0, 13.0),
+                };
+                if current_y >= params.rect.pos.y
+                    && current_y < params.rect.pos.y + params.rect.size.y
+                {
+                    if block.checked {
+                        self.draw_checkbox_checked.draw_abs(cx, checkbox);
+                    } else {
+                        self.draw_checkbox.draw_abs(cx, checkbox);
+                    }
+                }
+                if hit_result.is_none() {
+                    // Zone de clic un peu plus large que la case
+                    if let Some(pos) = params.finger_hit {
+                        let area = Rect {
+                            pos: checkbox.pos - dvec2(3.0, 3.0),
+                            size: checkbox.size + dvec2(6.0, 6.0),
+                        };
+                        if area.contains(pos) {
+                            hit_result = Some(HitResult {
+                                block_idx,
+                                char_idx: 0,
+                                on_checkbox: true,
+                            });
+                        }
+                    }
+                }
+                current_x += 21.0;
             } else if block.ty == BlockType::OrderedListItem {
                 let level = block.indent as usize;
                 if level >= list_counters.len() {
-                    list_counters.resize(level + 1, 0);
+                    list_counters.resize(level + 1, None);
                 }
-                list_counters[level] += 1;
+                list_counters[level] = Some(match list_counters[level] {
+                    Some(n) => n + 1,
+                    None => block.syntax.number.unwrap_or(1),
+                });
                 for counter in list_counters.iter_mut().skip(level + 1) {
-                    *counter = 0;
+                    *counter = None;
                 }
 
                 current_x += (block.indent as f64) * 20.0;
 
-                let prefix = format!("{}. ", list_counters[level]);
+                let delimiter = match block.syntax.marker.as_deref() {
+                    Some(")") => ")",
+                    _ => ".",
+                };
+                let prefix = format!("{}{} ", list_counters[level].unwrap_or(1), delimiter);
                 let prefix_layout =
                     self.draw_text_reg
                         .layout(cx, 0.0, 0.0, None, false, Align::default(), &prefix);
@@ -246,6 +400,14 @@ impl<'a> EditorView<'a> {
             let mut char_iter = block.text.chars();
             let mut current_byte_offset = 0;
 
+            let code_tokens = if block.ty == BlockType::CodeBlock
+                && highlight::is_supported(block.language())
+            {
+                highlight::tokenize(block.language(), &block.text)
+            } else {
+                Vec::new()
+            };
+
             for span in &block.styles {
                 let base_draw = match block.ty {
                     BlockType::Heading1 => self.draw_text_header1 as &mut DrawText,
@@ -259,6 +421,10 @@ impl<'a> EditorView<'a> {
                 };
                 let draw_text = if span.style.is_code {
                     self.draw_text_code as &mut DrawText
+                } else if span.link.is_some() {
+                    self.draw_text_link as &mut DrawText
+                } else if span.style.is_bold && span.style.is_italic {
+                    self.draw_text_bold_italic as &mut DrawText
                 } else if span.style.is_bold {
                     self.draw_text_bold as &mut DrawText
                 } else if span.style.is_italic {
@@ -267,6 +433,11 @@ impl<'a> EditorView<'a> {
                     base_draw
                 };
 
+                let fake_bold = !span.style.is_code
+                    && span.link.is_none()
+                    && span.style.is_bold
+                    && span.style.is_italic;
+
                 if block.ty == BlockType::Heading1 {
                     draw_text.text_style.font_size = 29.0;
                 } else if block.ty == BlockType::Heading2 {
@@ -280,6 +451,9 @@ impl<'a> EditorView<'a> {
                 } else {
                     draw_text.text_style.font_size = 12.1;
                 }
+                if span.style.is_superscript || span.style.is_subscript {
+                    draw_text.text_style.font_size *= 0.7;
+                }
 
                 // Optimization: Zero-copy slicing
                 let mut span_byte_len = 0;
@@ -288,6 +462,7 @@ impl<'a> EditorView<'a> {
                         span_byte_len += c.len_utf8();
                     }
                 }
+                let span_start_byte = current_byte_offset;
                 let span_end_byte = current_byte_offset + span_byte_len;
                 // Ensure we don't panic if indices are out of bounds (though they shouldn't be)
                 let span_text = if span_end_byte <= block.text.len() {
@@ -306,8 +481,23 @@ impl<'a> EditorView<'a> {
 
                 let should_draw = current_y + height >= params.rect.pos.y
                     && current_y < params.rect.pos.y + params.rect.size.y;
+                // Exposant collé en haut de la ligne, indice descendu vers la ligne de base
+                let text_y = if span.style.is_subscript {
+                    current_y + height * 0.45
+                } else {
+                    current_y
+                };
 
                 if should_draw {
+                    if span.style.is_highlight {
+                        self.draw_highlight.draw_abs(
+                            cx,
+                            Rect {
+                                pos: dvec2(current_x, text_y),
+                                size: dvec2(width, height),
+                            },
+                        );
+                    }
                     if span.style.is_code {
                         self.draw_code_bg.draw_abs(
                             cx,
@@ -401,12 +591,49 @@ impl<'a> EditorView<'a> {
                                 hit_result = Some(HitResult {
                                     block_idx,
                                     char_idx: char_count_so_far + local_char,
+                                    on_checkbox: false,
                                 });
                             }
                         }
                     }
 
-                    draw_text.draw_abs(cx, dvec2(current_x, current_y), span_text);
+                    if code_tokens.is_empty() {
+                        draw_text.draw_abs(cx, dvec2(current_x, text_y), span_text);
+                        if fake_bold {
+                            // Graisse simulée : l'italique redessiné un demi-pixel plus loin
+                            draw_text.draw_abs(cx, dvec2(current_x + 0.5, text_y), span_text);
+                        }
+                    } else {
+                        draw_code_tokens(
+                            cx,
+                            draw_text,
+                            self.syntax_colors,
+                            dvec2(current_x, text_y),
+                            span_text,
+                            span_start_byte,
+                            &code_tokens,
+                        );
+                    }
+
+                    if span.style.is_strikethrough {
+                        self.draw_strikethrough.draw_abs(
+                            cx,
+                            Rect {
+                                pos: dvec2(current_x, text_y + height * 0.55),
+                                size: dvec2(width, 1.0),
+                            },
+                        );
+                    }
+
+                    if span.link.is_some() {
+                        self.draw_link_underline.draw_abs(
+                            cx,
+                            Rect {
+                                pos: dvec2(current_x, current_y + height - 2.0),
+                                size: dvec2(width, 1.0),
+                            },
+                        );
+                    }
 
                     if block_idx == params.cursor.0
                         && !found_cursor
@@ -491,11 +718,13 @@ impl<'a> EditorView<'a> {
                             hit_result = Some(HitResult {
                                 block_idx,
                                 char_idx: char_count_so_far,
+                                on_checkbox: false,
                             });
                         } else if pos.x < start_x {
                             hit_result = Some(HitResult {
                                 block_idx,
                                 char_idx: 0,
+                                on_checkbox: false,
                             });
                         }
                     }
@@ -521,4 +750,263 @@ impl<'a> EditorView<'a> {
             hit_result,
         )
     }
+    // Police d'un morceau de cellule ; l'en-tête est en gras
+    fn table_font(&mut self, span: &StyleSpan, header: bool) -> &mut DrawText {
+        let bold = span.style.is_bold || header;
+        let draw_text = if span.style.is_code {
+            &mut *self.draw_text_code
+        } else if span.link.is_some() {
+            &mut *self.draw_text_link
+        } else if bold && span.style.is_italic {
+            &mut *self.draw_text_bold_italic
+        } else if bold {
+            &mut *self.draw_text_bold
+        } else if span.style.is_italic {
+            &mut *self.draw_text_italic
+        } else {
+            &mut *self.draw_text_reg
+        };
+        draw_text.text_style.font_size = 12.1;
+        draw_text
+    }
+
+    // Largeur des `upto` premiers caractères d'une cellule
+    fn cell_width(
+        &mut self,
+        cx: &mut Cx2d,
+        pieces: &[(String, &StyleSpan)],
+        header: bool,
+        upto: usize,
+    ) -> f64 {
+        let mut width = 0.0;
+        let mut left = upto;
+        for (text, span) in pieces {
+            if left == 0 {
+                break;
+            }
+            let end = text.char_indices().nth(left).map_or(text.len(), |(i, _)| i);
+            left = left.saturating_sub(text.chars().count());
+            width += self
+                .table_font(span, header)
+                .layout(cx, 0.0, 0.0, None, false, Align::default(), &text[..end])
+                .size_in_lpxs
+                .width as f64;
+        }
+        width
+    }
+
+    /// Draws a table block at `origin` and returns its height.
+    fn draw_table(
+        &mut self,
+        cx: &mut Cx2d,
+        params: &DrawParams,
+        block_idx: usize,
+        origin: DVec2,
+        hit_result: &mut Option<HitResult>,
+    ) -> f64 {
+        const PAD: f64 = 8.0;
+        let block = &params.doc.blocks[block_idx];
+        let chars: Vec<char> = block.text.chars().collect();
+        let rows = block.table_cells();
+        let columns = rows[0].len();
+
+        // Morceaux de texte de chaque cellule, avec leur style
+        let cells: Vec<Vec<Vec<(String, &StyleSpan)>>> = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| {
+                        let mut pieces = Vec::new();
+                        let mut pos = 0;
+                        for span in &block.styles {
+                            let start = pos.max(cell.start);
+                            let end = (pos + span.len).min(cell.end);
+                            if start < end {
+                                pieces.push((chars[start..end].iter().collect(), span));
+                            }
+                            pos += span.len;
+                        }
+                        pieces
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let line_height = self
+            .draw_text_reg
+            .layout(cx, 0.0, 0.0, None, false, Align::default(), "A")
+            .size_in_lpxs
+            .height as f64;
+        let row_height = line_height + PAD;
+        let mut text_widths = vec![vec![0.0; columns]; rows.len()];
+        let mut widths = vec![40.0; columns];
+        for (r, row) in cells.iter().enumerate() {
+            for (c, pieces) in row.iter().enumerate().take(columns) {
+                let width = self.cell_width(cx, pieces, r == 0, usize::MAX);
+                text_widths[r][c] = width;
+                widths[c] = f64::max(widths[c], width + 2.0 * PAD);
+            }
+        }
+        let total_width: f64 = widths.iter().sum();
+        let height = rows.len() as f64 * row_height;
+        let visible = origin.y + height >= params.rect.pos.y
+            && origin.y < params.rect.pos.y + params.rect.size.y;
+
+        if visible {
+            self.draw_table_header_bg.draw_abs(
+                cx,
+                Rect {
+                    pos: origin,
+                    size: dvec2(total_width, row_height),
+                },
+            );
+        }
+
+        let selection = params.selection.and_then(|(start, end)| {
+            if block_idx < start.0 || block_idx > end.0 {
+                return None;
+            }
+            let from = if start.0 == block_idx { start.1 } else { 0 };
+            let to = if end.0 == block_idx {
+                end.1
+            } else {
+                chars.len()
+            };
+            Some((from, to))
+        });
+
+        let mut y = origin.y;
+        for (r, row) in rows.iter().enumerate() {
+            let mut x = origin.x;
+            for (c, cell) in row.iter().enumerate().take(columns) {
+                let pieces = &cells[r][c];
+                let header = r == 0;
+                let text_x = x + match block.columns.get(c) {
+                    Some(ColumnAlign::Right) => widths[c] - PAD - text_widths[r][c],
+                    Some(ColumnAlign::Center) => (widths[c] - text_widths[r][c]) / 2.0,
+                    _ => PAD,
+                };
+                let text_y = y + PAD / 2.0;
+
+                if visible {
+                    if let Some((from, to)) = selection {
+                        let from = from.clamp(cell.start, cell.end) - cell.start;
+                        let to = to.clamp(cell.start, cell.end) - cell.start;
+                        if from < to {
+                            let x0 = self.cell_width(cx, pieces, header, from);
+                            let x1 = self.cell_width(cx, pieces, header, to);
+                            self.draw_selection.draw_abs(
+                                cx,
+                                Rect {
+                                    pos: dvec2(text_x + x0, text_y),
+                                    size: dvec2(x1 - x0, line_height),
+                                },
+                            );
+                        }
+                    }
+
+                    let mut piece_x = text_x;
+                    for (text, span) in pieces {
+                        let draw_text = self.table_font(span, header);
+                        let width = draw_text
+                            .layout(cx, 0.0, 0.0, None, false, Align::default(), text)
+                            .size_in_lpxs
+                            .width as f64;
+                        draw_text.draw_abs(cx, dvec2(piece_x, text_y), text);
+                        if span.style.is_strikethrough {
+                            self.draw_strikethrough.draw_abs(
+                                cx,
+                                Rect {
+                                    pos: dvec2(piece_x, text_y + line_height * 0.55),
+                                    size: dvec2(width, 1.0),
+                                },
+                            );
+                        }
+                        if span.link.is_some() {
+                            self.draw_link_underline.draw_abs(
+                                cx,
+                                Rect {
+                                    pos: dvec2(piece_x, text_y + line_height - 2.0),
+                                    size: dvec2(width, 1.0),
+                                },
+                            );
+                        }
+                        piece_x += width;
+                    }
+
+                    if params.cursor.0 == block_idx
+                        && cell.start <= params.cursor.1
+                        && params.cursor.1 <= cell.end
+                    {
+                        let cursor_x = text_x
+                            + self.cell_width(cx, pieces, header, params.cursor.1 - cell.start);
+                        self.draw_cursor.draw_abs(
+                            cx,
+                            Rect {
+                                pos: dvec2(cursor_x, text_y),
+                                size: dvec2(2.0, line_height),
+                            },
+                        );
+                    }
+                }
+
+                if hit_result.is_none() {
+                    if let Some(pos) = params.finger_hit {
+                        let last_column = c + 1 == columns;
+                        let in_cell = pos.y >= y
+                            && pos.y < y + row_height
+                            && pos.x >= x
+                            && (pos.x < x + widths[c] || last_column);
+                        if in_cell {
+                            // Caractère dont le bord est le plus proche du clic
+                            let len = cell.end - cell.start;
+                            let local = (0..=len)
+                                .map(|n| (n, self.cell_width(cx, pieces, header, n)))
+                                .min_by(|a, b| {
+                                    let da = (text_x + a.1 - pos.x).abs();
+                                    let db = (text_x + b.1 - pos.x).abs();
+                                    da.total_cmp(&db)
+                                })
+                                .map_or(0, |(n, _)| n);
+                            *hit_result = Some(HitResult {
+                                block_idx,
+                                char_idx: cell.start + local,
+                                on_checkbox: false,
+                            });
+                        }
+                    }
+                }
+                x += widths[c];
+            }
+            y += row_height;
+        }
+
+        if visible {
+            // Grille : une ligne par bord de ligne et de colonne
+            let mut y = origin.y;
+            for _ in 0..=rows.len() {
+                self.draw_table_border.draw_abs(
+                    cx,
+                    Rect {
+                        pos: dvec2(origin.x, y),
+                        size: dvec2(total_width, 1.0),
+                    },
+                );
+                y += row_height;
+            }
+            let mut x = origin.x;
+            for c in 0..=columns {
+                self.draw_table_border.draw_abs(
+                    cx,
+                    Rect {
+                        pos: dvec2(x, origin.y),
+                        size: dvec2(1.0, height),
+                    },
+                );
+                x += widths.get(c).copied().unwrap_or(0.0);
+            }
+        }
+
+        height
+    }
 }
@@ -0,0 +1,149 @@
+// Surveillance du fichier ouvert : inotify sous Linux, date de modification ailleurs
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Intervalle de vérification de l'arrêt (et de la date de modification hors Linux)
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches one file from a background thread until dropped.
+pub struct FileWatcher {
+    path: String,
+    stop: Arc<AtomicBool>,
+}
+
+impl FileWatcher {
+    /// Calls `on_change` from the watcher thread each time `path` is written, replaced
+    /// or removed by another program (or by our own saves, which the caller must ignore).
+    pub fn new<F>(path: &str, on_change: F) -> Self
+    where
+        F: Fn() + Send + 'static,
+    {
+        let stop = Arc::new(AtomicBool::new(false));
+        let file = PathBuf::from(path);
+        let thread_stop = stop.clone();
+        std::thread::spawn(move || watch(&file, &thread_stop, on_change));
+        Self {
+            path: path.to_string(),
+            stop,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Hash of the content our last save wrote to the file. The save task records it
+/// before writing, so the change it causes is recognised even when the watcher
+/// reports it before the save itself.
+#[derive(Clone, Default)]
+pub struct OwnWrites(Arc<AtomicU64>);
+
+impl OwnWrites {
+    pub fn record(&self, content: &str) {
+        self.0.store(text_hash(content), Ordering::Relaxed);
+    }
+
+    pub fn is_own(&self, content: &str) -> bool {
+        self.0.load(Ordering::Relaxed) == text_hash(content)
+    }
+}
+
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    // 0 : rien d'écrit encore
+    hasher.finish().max(1)
+}
+
+// Le dossier parent est surveillé : un enregistrement atomique remplace le fichier
+// par renommage, ce qui ferait perdre une surveillance posée sur le fichier lui-même.
+#[cfg(target_os = "linux")]
+fn watch<F: Fn()>(file: &Path, stop: &AtomicBool, on_change: F) {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Some(name) = file.file_name() else {
+        return;
+    };
+    let Ok(dir_c) = CString::new(dir.as_os_str().as_bytes()) else {
+        return;
+    };
+
+    // SAFETY: appels système sur un descripteur possédé par ce thread, fermé en sortie.
+    unsafe {
+        let fd = libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC);
+        if fd < 0 {
+            return;
+        }
+        let mask = libc::IN_CLOSE_WRITE
+            | libc::IN_MOVED_TO
+            | libc::IN_CREATE
+            | libc::IN_DELETE
+            | libc::IN_MOVED_FROM;
+        if libc::inotify_add_watch(fd, dir_c.as_ptr(), mask) < 0 {
+            libc::close(fd);
+            return;
+        }
+
+        let mut buf = [0u8; 4096];
+        let header = std::mem::size_of::<libc::inotify_event>();
+        while !stop.load(Ordering::Relaxed) {
+            let mut pollfd = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            if libc::poll(&mut pollfd, 1, POLL_INTERVAL.as_millis() as i32) <= 0 {
+                continue;
+            }
+            let len = libc::read(fd, buf.as_mut_ptr().cast(), buf.len());
+            if len <= 0 {
+                continue;
+            }
+            let mut changed = false;
+            let mut offset = 0;
+            while offset + header <= len as usize {
+                let event: libc::inotify_event =
+                    std::ptr::read_unaligned(buf.as_ptr().add(offset).cast());
+                let name_bytes = &buf[offset + header..offset + header + event.len as usize];
+                let event_name = name_bytes.split(|&b| b == 0).next().unwrap_or_default();
+                changed |= event_name == name.as_bytes();
+                offset += header + event.len as usize;
+            }
+            // Plusieurs évènements pour une même écriture : un seul appel
+            if changed && !stop.load(Ordering::Relaxed) {
+                on_change();
+            }
+        }
+        libc::close(fd);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch<F: Fn()>(file: &Path, stop: &AtomicBool, on_change: F) {
+    let modified = || std::fs::metadata(file).and_then(|m| m.modified()).ok();
+    let mut last = modified();
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(POLL_INTERVAL);
+        let current = modified();
+        if current != last {
+            last = current;
+            on_change();
+        }
+    }
+}
@@ -1,20 +1,92 @@
 use makepad_widgets::*;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::TOKIO_RUNTIME;
 
+pub mod ops;
+
 #[derive(Clone, DefaultNone, Debug)]
 pub enum FileExplorerAction {
     FileSelected(String),
-    AsyncFilesLoaded(Vec<String>),
+    /// Content of a directory, read in the background. Empty if it could not be read.
+    AsyncDirLoaded(PathBuf, Vec<FileNode>),
+    /// A file operation finished. `moved` holds the old and new path of a rename or move.
+    AsyncOpDone {
+        created: Option<PathBuf>,
+        moved: Option<(PathBuf, PathBuf)>,
+    },
+    AsyncOpError(String),
+    /// A file or folder was renamed or moved (absolute paths).
+    FileMoved {
+        from: PathBuf,
+        to: PathBuf,
+    },
     None,
 }
 
+#[derive(Clone, Debug)]
+pub struct FileNode {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+}
+
+impl FileNode {
+    pub fn is_markdown(&self) -> bool {
+        !self.is_dir
+            && self.path.extension().is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")
+            })
+    }
+}
+
+// Ligne visible de l'arbre
+#[derive(Clone, Debug)]
+struct TreeRow {
+    node: FileNode,
+    depth: usize,
+}
+
+// Dossiers d'abord, puis ordre alphabétique sans tenir compte de la casse
+fn read_dir_sorted(dir: &Path) -> Vec<FileNode> {
+    let mut nodes: Vec<FileNode> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if name.starts_with('.') {
+                    return None;
+                }
+                // Suit les liens symboliques
+                let is_dir = entry.path().is_dir();
+                Some(FileNode {
+                    path: entry.path(),
+                    name,
+                    is_dir,
+                })
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    nodes.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    nodes
+}
+
 live_design! {
     use link::theme::*;
     use link::widgets::*;
     use crate::theme::*;
 
+    ToolButton = <Button> {
+        width: 26, height: 26
+        draw_text: { color: (NORD_SNOW_2) }
+    }
+
     pub FileExplorer = {{FileExplorer}}{
         width: 250, height: Fill
         flow: Down, padding: 10
@@ -32,6 +104,17 @@ live_design! {
 
             <View> { width: Fill }
 
+            new_note_btn = <ToolButton> { text: "+" }
+            new_folder_btn = <ToolButton> { text: "📁" }
+            rename_btn = <ToolButton> { text: "✎" }
+            delete_btn = <ToolButton> { text: "🗑" }
+
+            refresh_btn = <Button> {
+                width: 30, height: 30
+                text: "⟳"
+                draw_text: { color: (NORD_SNOW_2) }
+            }
+
             toggle_btn = <Button> {
                 width: 30, height: 30
                 text: "☰"
@@ -45,6 +128,13 @@ live_design! {
 
             FileItem = <View> {
                 width: Fill, height: 30, flow: Overlay
+                show_bg: true
+                draw_bg: {
+                    instance selected: 0.0
+                    fn pixel(self) -> vec4 {
+                        return mix(vec4(0.,0.,0.,0.), (NORD_POLAR_2), self.selected);
+                    }
+                }
 
                 content = <View> {
                     width: Fill, height: Fill, flow: Right, align: {y: 0.5}, padding: 5
@@ -52,7 +142,7 @@ live_design! {
                     icon = <Label> { text: "📄", draw_text: { color: (NORD_FROST_1) } }
                     name = <Label> {
                         text: "filename.md",
-                        draw_text: { text_style: <THEME_FONT_REGULAR> {font_size: 11}, color: (NORD_SNOW_0) }
+                        draw_text: { text_style: <THEME_FONT_REGULAR> {font_size: 11}, color: (NORD_POLAR_3) }
                     }
                 }
 
@@ -64,6 +154,30 @@ live_design! {
                     text: ""
                 }
             }
+            FolderItem = <FileItem> {
+                content = {
+                    icon = { text: "📁", draw_text: { color: (NORD_AURORA_YELLOW) } }
+                    name = { draw_text: { text_style: <THEME_FONT_BOLD> {font_size: 11}, color: (NORD_SNOW_1) } }
+                }
+            }
+            // Fichiers Markdown mis en avant
+            MarkdownItem = <FileItem> {
+                content = {
+                    icon = { text: "📝" }
+                    name = { draw_text: { color: (NORD_FROST_1) } }
+                }
+            }
+            // Renommage sur place
+            RenameItem = <View> {
+                width: Fill, height: 30, flow: Right, align: {y: 0.5}, padding: 5
+
+                name_input = <TextInput> {
+                    width: Fill, height: Fit
+                    draw_bg: { color: (NORD_POLAR_0) }
+                    draw_text: { text_style: <THEME_FONT_REGULAR> {font_size: 11}, color: (NORD_SNOW_2) }
+                    draw_cursor: { color: (NORD_SNOW_2) }
+                }
+            }
         }
     }
 }
@@ -74,57 +188,326 @@ pub struct FileExplorer {
     view: View,
 
     #[rust]
-    files: Vec<String>,
+    root: PathBuf,
+    // Contenu des dossiers déjà lus
+    #[rust]
+    children: HashMap<PathBuf, Vec<FileNode>>,
+    // Dossiers ouverts, conservés entre deux rafraîchissements
+    #[rust]
+    expanded: HashSet<PathBuf>,
+    #[rust]
+    rows: Vec<TreeRow>,
+    // Zone de chaque ligne dessinée, pour trouver la cible d'un glisser-déposer
+    #[rust]
+    row_rects: Vec<(usize, Rect)>,
+    #[rust]
+    selected: Option<PathBuf>,
+    #[rust]
+    renaming: Option<PathBuf>,
+    // Le champ de renommage doit recevoir le nom et le focus au prochain dessin
+    #[rust]
+    rename_init: bool,
+    #[rust]
+    drag_source: Option<PathBuf>,
+    #[rust]
+    drag_start: DVec2,
 }
 
 impl LiveHook for FileExplorer {
     fn after_new_from_doc(&mut self, _cx: &mut Cx) {
-        self.load_files_async();
+        self.root = std::env::current_dir().unwrap_or(PathBuf::from("."));
+        self.load_dir_async(self.root.clone());
     }
 }
 
 impl FileExplorer {
-    fn load_files_async(&mut self) {
+    fn load_dir_async(&self, dir: PathBuf) {
         TOKIO_RUNTIME.spawn(async move {
-            let path = std::env::current_dir().unwrap_or(PathBuf::from("."));
-            let mut files = Vec::new();
-
-            if let Ok(entries) = fs::read_dir(path) {
-                for entry in entries.flatten() {
-                    let name = entry.file_name().to_string_lossy().into_owned();
-                    if !name.starts_with('.') {
-                        files.push(name);
-                    }
-                }
+            let nodes = read_dir_sorted(&dir);
+            Cx::post_action(FileExplorerAction::AsyncDirLoaded(dir, nodes));
+        });
+    }
+
+    /// Reads the root and every open folder again. Open folders stay open.
+    pub fn refresh(&mut self) {
+        self.load_dir_async(self.root.clone());
+        for dir in &self.expanded {
+            self.load_dir_async(dir.clone());
+        }
+    }
+
+    fn rebuild_rows(&mut self) {
+        let mut rows = Vec::new();
+        self.push_rows(&self.root, 0, &mut rows);
+        self.rows = rows;
+    }
+
+    fn push_rows(&self, dir: &Path, depth: usize, rows: &mut Vec<TreeRow>) {
+        let Some(nodes) = self.children.get(dir) else {
+            return;
+        };
+        for node in nodes {
+            rows.push(TreeRow {
+                node: node.clone(),
+                depth,
+            });
+            if node.is_dir && self.expanded.contains(&node.path) {
+                self.push_rows(&node.path, depth + 1, rows);
+            }
+        }
+    }
+
+    fn spawn_op<F>(&self, op: F)
+    where
+        F: FnOnce() -> std::io::Result<FileExplorerAction> + Send + 'static,
+    {
+        TOKIO_RUNTIME.spawn(async move {
+            let action = op().unwrap_or_else(|e| FileExplorerAction::AsyncOpError(e.to_string()));
+            Cx::post_action(action);
+        });
+    }
+
+    fn selected_row(&self) -> Option<&TreeRow> {
+        let selected = self.selected.as_ref()?;
+        self.rows.iter().find(|row| &row.node.path == selected)
+    }
+
+    // Dossier qui reçoit les nouveaux fichiers : le dossier sélectionné ou celui du fichier sélectionné
+    fn target_dir(&self) -> PathBuf {
+        match self.selected_row() {
+            Some(row) if row.node.is_dir => row.node.path.clone(),
+            Some(row) => row
+                .node
+                .path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or(self.root.clone()),
+            None => self.root.clone(),
+        }
+    }
+
+    fn start_rename(&mut self, cx: &mut Cx, path: PathBuf) {
+        self.renaming = Some(path);
+        self.rename_init = true;
+        self.redraw(cx);
+    }
+
+    // `None` annule le renommage
+    fn finish_rename(&mut self, cx: &mut Cx, name: Option<String>) {
+        let Some(path) = self.renaming.take() else {
+            return;
+        };
+        self.redraw(cx);
+        let Some(name) = name.map(|name| name.trim().to_string()) else {
+            return;
+        };
+        if name.is_empty() || path.file_name().is_some_and(|old| old == name.as_str()) {
+            return;
+        }
+        self.spawn_op(move || {
+            let to = ops::rename(&path, &name)?;
+            Ok(FileExplorerAction::AsyncOpDone {
+                created: None,
+                moved: Some((path, to)),
+            })
+        });
+    }
+
+    // Ligne sous le doigt à la fin d'un glisser : déplace la source dans son dossier
+    fn drop_on(&mut self, source: PathBuf, abs: DVec2) {
+        let Some(row) = self
+            .row_rects
+            .iter()
+            .find(|(_, rect)| rect.contains(abs))
+            .and_then(|(item_id, _)| self.rows.get(*item_id))
+        else {
+            return;
+        };
+        let dest = if row.node.is_dir {
+            row.node.path.clone()
+        } else {
+            match row.node.path.parent() {
+                Some(parent) => parent.to_path_buf(),
+                None => return,
             }
-            files.sort();
-            Cx::post_action(FileExplorerAction::AsyncFilesLoaded(files));
+        };
+        if source.parent() == Some(dest.as_path()) {
+            return;
+        }
+        self.spawn_op(move || {
+            let to = ops::move_into(&source, &dest)?;
+            Ok(FileExplorerAction::AsyncOpDone {
+                created: None,
+                moved: Some((source, to)),
+            })
         });
     }
 
-    pub fn handle_file_actions(&self, _cx: &mut Cx, actions: &Actions) -> Option<String> {
+    fn toggle_folder(&mut self, cx: &mut Cx, dir: PathBuf) {
+        if !self.expanded.remove(&dir) {
+            if !self.children.contains_key(&dir) {
+                self.load_dir_async(dir.clone());
+            }
+            self.expanded.insert(dir);
+        }
+        self.rebuild_rows();
+        self.redraw(cx);
+    }
+
+    // Chemin (relatif au dossier racine) du fichier cliqué ; un clic sur un dossier l'ouvre ou le ferme
+    pub fn handle_file_actions(&mut self, cx: &mut Cx, actions: &Actions) -> Option<String> {
+        if self.view.button(ids!(header.refresh_btn)).clicked(actions) {
+            self.refresh();
+        }
+        if self.view.button(ids!(header.new_note_btn)).clicked(actions) {
+            let dir = self.target_dir();
+            self.spawn_op(move || {
+                Ok(FileExplorerAction::AsyncOpDone {
+                    created: Some(ops::create_note(&dir)?),
+                    moved: None,
+                })
+            });
+        }
+        if self
+            .view
+            .button(ids!(header.new_folder_btn))
+            .clicked(actions)
+        {
+            let dir = self.target_dir();
+            self.spawn_op(move || {
+                Ok(FileExplorerAction::AsyncOpDone {
+                    created: Some(ops::create_folder(&dir)?),
+                    moved: None,
+                })
+            });
+        }
+        if self.view.button(ids!(header.rename_btn)).clicked(actions) {
+            if let Some(path) = self.selected.clone() {
+                self.start_rename(cx, path);
+            }
+        }
+        if self.view.button(ids!(header.delete_btn)).clicked(actions) {
+            if let Some(path) = self.selected.take() {
+                let root = self.root.clone();
+                self.spawn_op(move || {
+                    ops::move_to_trash(&root, &path)?;
+                    Ok(FileExplorerAction::AsyncOpDone {
+                        created: None,
+                        moved: None,
+                    })
+                });
+            }
+        }
+
         let list = self.view.portal_list(ids!(file_list));
+        let mut clicked = None;
         for (item_id, item) in list.items_with_actions(actions) {
-            if item.button(ids!(btn)).clicked(actions) {
-                return self.files.get(item_id).cloned();
+            let Some(path) = self.rows.get(item_id).map(|row| row.node.path.clone()) else {
+                continue;
+            };
+            let btn = item.button(ids!(btn));
+            if btn.pressed(actions) {
+                self.drag_source = Some(path);
+            }
+            if btn.clicked(actions) {
+                clicked = self.rows.get(item_id).map(|row| row.node.clone());
+            }
+            let input = item.text_input(ids!(name_input));
+            if input.returned(actions).is_some() {
+                self.finish_rename(cx, Some(input.text()));
+            } else if input.escaped(actions) {
+                self.finish_rename(cx, None);
             }
         }
-        None
+
+        let clicked = clicked?;
+        self.selected = Some(clicked.path.clone());
+        self.redraw(cx);
+        if clicked.is_dir {
+            self.toggle_folder(cx, clicked.path);
+            return None;
+        }
+        let path = clicked
+            .path
+            .strip_prefix(&self.root)
+            .unwrap_or(&clicked.path);
+        Some(path.to_string_lossy().into_owned())
     }
 }
 
 impl Widget for FileExplorer {
     fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        match event {
+            Event::FingerDown(fe) => self.drag_start = fe.abs,
+            Event::FingerUp(fe) => {
+                // Un glisser suffisamment long vers une autre ligne déplace le fichier
+                if let Some(source) = self.drag_source.take() {
+                    if (fe.abs - self.drag_start).length() > 8.0 {
+                        self.drop_on(source, fe.abs);
+                    }
+                }
+            }
+            _ => {}
+        }
         self.view.handle_event(cx, event, scope);
 
         if let Event::Actions(actions) = event {
             for action in actions {
                 let fe_action: FileExplorerAction = action.cast();
                 match fe_action {
-                    FileExplorerAction::AsyncFilesLoaded(files) => {
-                        self.files = files;
+                    FileExplorerAction::AsyncDirLoaded(dir, nodes) => {
+                        // Un dossier disparu n'est plus ouvert
+                        if nodes.is_empty() && !dir.is_dir() {
+                            self.expanded.remove(&dir);
+                        }
+                        // Oublie les sous-dossiers qui n'existent plus
+                        let removed: Vec<PathBuf> = self
+                            .children
+                            .get(&dir)
+                            .into_iter()
+                            .flatten()
+                            .filter(|old| old.is_dir && !nodes.iter().any(|n| n.path == old.path))
+                            .map(|old| old.path.clone())
+                            .collect();
+                        for path in removed {
+                            self.children.retain(|known, _| !known.starts_with(&path));
+                            self.expanded.retain(|open| !open.starts_with(&path));
+                        }
+                        self.children.insert(dir, nodes);
+                        self.rebuild_rows();
                         self.redraw(cx);
                     }
+                    FileExplorerAction::AsyncOpDone { created, moved } => {
+                        if let Some(path) = created {
+                            // Le dossier parent s'ouvre pour montrer le nouvel élément
+                            if let Some(parent) = path.parent().filter(|p| *p != self.root) {
+                                self.expanded.insert(parent.to_path_buf());
+                            }
+                            self.selected = Some(path.clone());
+                            self.start_rename(cx, path);
+                        }
+                        if let Some((from, to)) = moved {
+                            let rebase = |path: &PathBuf| ops::rebase(path, &from, &to);
+                            self.selected = self
+                                .selected
+                                .as_ref()
+                                .map(|p| rebase(p).unwrap_or(p.clone()));
+                            self.expanded = self
+                                .expanded
+                                .iter()
+                                .map(|p| rebase(p).unwrap_or(p.clone()))
+                                .collect();
+                            cx.widget_action(
+                                self.widget_uid(),
+                                &scope.path,
+                                FileExplorerAction::FileMoved { from, to },
+                            );
+                        }
+                        self.refresh();
+                    }
+                    FileExplorerAction::AsyncOpError(e) => {
+                        makepad_widgets::log!("File operation failed: {}", e);
+                    }
                     _ => {}
                 }
             }
@@ -134,15 +517,48 @@ impl Widget for FileExplorer {
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
         while let Some(item) = self.view.draw_walk(cx, scope, walk).step() {
             if let Some(mut list) = item.as_portal_list().borrow_mut() {
-                list.set_item_range(cx, 0, self.files.len());
+                list.set_item_range(cx, 0, self.rows.len());
+                self.row_rects.clear();
                 while let Some(item_id) = list.next_visible_item(cx) {
-                    if item_id < self.files.len() {
-                        let file_name = &self.files[item_id];
-                        let item = list.item(cx, item_id, live_id!(FileItem));
-                        item.view(ids!(content))
-                            .label(ids!(name))
-                            .set_text(cx, file_name);
+                    if let Some(row) = self.rows.get(item_id) {
+                        let node = &row.node;
+                        let indent = 5.0 + row.depth as f64 * 16.0;
+                        if self.renaming.as_ref() == Some(&node.path) {
+                            let item = list.item(cx, item_id, live_id!(RenameItem));
+                            item.apply_over(cx, live! { padding: { left: (indent) } });
+                            item.draw_all(cx, scope);
+                            if std::mem::take(&mut self.rename_init) {
+                                let input = item.text_input(ids!(name_input));
+                                input.set_text(cx, &node.name);
+                                input.set_key_focus(cx);
+                            }
+                            continue;
+                        }
+                        let template = if node.is_dir {
+                            live_id!(FolderItem)
+                        } else if node.is_markdown() {
+                            live_id!(MarkdownItem)
+                        } else {
+                            live_id!(FileItem)
+                        };
+                        let item = list.item(cx, item_id, template);
+                        let selected = if self.selected.as_ref() == Some(&node.path) {
+                            1.0
+                        } else {
+                            0.0
+                        };
+                        item.apply_over(cx, live! { draw_bg: { selected: (selected) } });
+                        let content = item.view(ids!(content));
+                        content.apply_over(cx, live! { padding: { left: (indent) } });
+                        if node.is_dir {
+                            let open = self.expanded.contains(&node.path);
+                            content
+                                .label(ids!(icon))
+                                .set_text(cx, if open { "📂" } else { "📁" });
+                        }
+                        content.label(ids!(name)).set_text(cx, &node.name);
                         item.draw_all(cx, scope);
+                        self.row_rects.push((item_id, item.area().rect(cx)));
                     }
                 }
             }
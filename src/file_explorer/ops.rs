@@ -0,0 +1,165 @@
+// Opérations sur les fichiers de l'explorateur (lancées depuis TOKIO_RUNTIME)
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Folder at the root of the workspace that receives deleted files.
+pub const TRASH_DIR: &str = ".trash";
+
+// Premier nom libre : "nom.ext", "nom 2.ext", "nom 3.ext"...
+fn unique_path(dir: &Path, stem: &str, ext: Option<&str>) -> PathBuf {
+    let name = |n: usize| {
+        let stem = if n == 1 {
+            stem.to_string()
+        } else {
+            format!("{stem} {n}")
+        };
+        match ext {
+            Some(ext) => format!("{stem}.{ext}"),
+            None => stem,
+        }
+    };
+    (1..)
+        .map(|n| dir.join(name(n)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+fn check_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid file name: {name:?}"),
+        ));
+    }
+    Ok(())
+}
+
+fn check_free(path: &Path) -> io::Result<()> {
+    if path.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Creates an empty note in `dir` and returns its path.
+pub fn create_note(dir: &Path) -> io::Result<PathBuf> {
+    let path = unique_path(dir, "Nouvelle note", Some("md"));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    Ok(path)
+}
+
+pub fn create_folder(dir: &Path) -> io::Result<PathBuf> {
+    let path = unique_path(dir, "Nouveau dossier", None);
+    fs::create_dir(&path)?;
+    Ok(path)
+}
+
+/// Renames a file or folder in place and returns its new path.
+pub fn rename(path: &Path, new_name: &str) -> io::Result<PathBuf> {
+    check_name(new_name)?;
+    let target = path.with_file_name(new_name);
+    if target == path {
+        return Ok(target);
+    }
+    check_free(&target)?;
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// Moves a file or folder into `dest_dir` and returns its new path.
+pub fn move_into(path: &Path, dest_dir: &Path) -> io::Result<PathBuf> {
+    if dest_dir.starts_with(path) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "cannot move a folder into itself",
+        ));
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+    let target = dest_dir.join(name);
+    if target == path {
+        return Ok(target);
+    }
+    check_free(&target)?;
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// Moves a file or folder to the trash folder of `root`, renaming it if the name is taken.
+pub fn move_to_trash(root: &Path, path: &Path) -> io::Result<PathBuf> {
+    let trash = root.join(TRASH_DIR);
+    if path.starts_with(&trash) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "already in the trash",
+        ));
+    }
+    fs::create_dir_all(&trash)?;
+    let target = if path.is_dir() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        unique_path(&trash, &name, None)
+    } else {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let ext = path.extension().map(|ext| ext.to_string_lossy());
+        unique_path(&trash, &stem, ext.as_deref())
+    };
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// New location of `path` once `from` has been moved to `to` (`path` may be `from` itself
+/// or something inside it).
+pub fn rebase(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(from).ok()?;
+    if rest.as_os_str().is_empty() {
+        Some(to.to_path_buf())
+    } else {
+        Some(to.join(rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_operations() {
+        let root = std::env::temp_dir().join(format!("ndown-ops-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let note = create_note(&root).unwrap();
+        assert_eq!(note, root.join("Nouvelle note.md"));
+        assert_eq!(create_note(&root).unwrap(), root.join("Nouvelle note 2.md"));
+
+        let folder = create_folder(&root).unwrap();
+        let note = rename(&note, "idées.md").unwrap();
+        assert!(rename(&note, "a/b.md").is_err());
+        assert!(rename(&note, "Nouvelle note 2.md").is_err());
+
+        let moved = move_into(&note, &folder).unwrap();
+        assert_eq!(moved, folder.join("idées.md"));
+        assert!(move_into(&folder, &folder).is_err());
+        assert_eq!(
+            rebase(&moved, &folder, &root.join("notes")),
+            Some(root.join("notes/idées.md"))
+        );
+
+        let trashed = move_to_trash(&root, &moved).unwrap();
+        assert_eq!(trashed, root.join(TRASH_DIR).join("idées.md"));
+        fs::write(&moved, "").unwrap();
+        let trashed = move_to_trash(&root, &moved).unwrap();
+        assert_eq!(trashed, root.join(TRASH_DIR).join("idées 2.md"));
+        assert!(!moved.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
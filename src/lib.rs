@@ -6,9 +6,14 @@ use tokio::runtime::Runtime;
 pub mod app;
 pub mod theme;
 
+pub mod conflict_banner;
 pub mod editor;
 pub mod file_explorer;
+pub mod link_dialog;
 pub mod panel;
+pub mod quick_open;
+pub mod recovery_prompt;
+pub mod save_prompt;
 pub mod top_bar;
 
 pub static TOKIO_RUNTIME: Lazy<Runtime> = Lazy::new(|| {
@@ -20,8 +25,13 @@ pub fn live_design(cx: &mut Cx) {
     makepad_code_editor::live_design(cx);
 
     theme::live_design(cx);
+    conflict_banner::live_design(cx);
     editor::live_design(cx);
     file_explorer::live_design(cx);
+    link_dialog::live_design(cx);
     panel::live_design(cx);
+    quick_open::live_design(cx);
+    recovery_prompt::live_design(cx);
+    save_prompt::live_design(cx);
     top_bar::live_design(cx);
 }
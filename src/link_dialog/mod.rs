@@ -0,0 +1,108 @@
+use makepad_widgets::*;
+
+// Résultat de la boîte de dialogue, lu par l'App
+#[derive(Clone, Debug)]
+pub enum LinkDialogResult {
+    Apply { text: String, url: String },
+    Remove,
+    Cancel,
+}
+
+live_design! {
+    use link::theme::*;
+    use link::widgets::*;
+    use crate::theme::*;
+
+    FieldLabel = <Label> {
+        draw_text: { text_style: <THEME_FONT_REGULAR> {font_size: 10}, color: (NORD_SNOW_0) }
+    }
+
+    FieldInput = <TextInput> {
+        width: Fill, height: Fit
+        draw_bg: { color: (NORD_POLAR_0) }
+        draw_text: { text_style: <THEME_FONT_REGULAR> {font_size: 11}, color: (NORD_SNOW_2) }
+        draw_cursor: { color: (NORD_SNOW_2) }
+    }
+
+    pub LinkDialog = {{LinkDialog}}{
+        width: 420, height: Fit
+        flow: Down, spacing: 8, padding: 20
+        show_bg: true
+        draw_bg: { color: (NORD_POLAR_1) }
+
+        title = <Label> {
+            text: "LIEN"
+            draw_text: { text_style: <THEME_FONT_BOLD> {font_size: 12}, color: (NORD_FROST_2) }
+        }
+
+        <FieldLabel> { text: "Texte" }
+        text_input = <FieldInput> {}
+
+        <FieldLabel> { text: "Adresse" }
+        url_input = <FieldInput> {}
+
+        buttons = <View> {
+            width: Fill, height: Fit
+            flow: Right, spacing: 10, margin: {top: 10}
+
+            remove_btn = <Button> { text: "Retirer", draw_text: { color: (NORD_AURORA_RED) } }
+            <View> { width: Fill }
+            cancel_btn = <Button> { text: "Annuler", draw_text: { color: (NORD_SNOW_2) } }
+            apply_btn = <Button> { text: "Appliquer", draw_text: { color: (NORD_FROST_1) } }
+        }
+    }
+}
+
+#[derive(Live, Widget)]
+pub struct LinkDialog {
+    #[deref]
+    view: View,
+}
+
+impl LiveHook for LinkDialog {}
+
+impl LinkDialog {
+    pub fn set_link(&mut self, cx: &mut Cx, text: &str, url: &str) {
+        self.view.text_input(ids!(text_input)).set_text(cx, text);
+        let url_input = self.view.text_input(ids!(url_input));
+        url_input.set_text(cx, url);
+        url_input.set_key_focus(cx);
+    }
+
+    pub fn handle_dialog_actions(&self, actions: &Actions) -> Option<LinkDialogResult> {
+        let url_input = self.view.text_input(ids!(url_input));
+        if self.view.button(ids!(apply_btn)).clicked(actions)
+            || url_input.returned(actions).is_some()
+        {
+            return Some(LinkDialogResult::Apply {
+                text: self.view.text_input(ids!(text_input)).text(),
+                url: url_input.text().trim().to_string(),
+            });
+        }
+        if self.view.button(ids!(remove_btn)).clicked(actions) {
+            return Some(LinkDialogResult::Remove);
+        }
+        if self.view.button(ids!(cancel_btn)).clicked(actions) {
+            return Some(LinkDialogResult::Cancel);
+        }
+        None
+    }
+}
+
+impl LinkDialogRef {
+    pub fn set_link(&self, cx: &mut Cx, text: &str, url: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_link(cx, text, url);
+        }
+    }
+}
+
+impl Widget for LinkDialog {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope)
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
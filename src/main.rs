@@ -1,3 +1,27 @@
+mod cli;
+
+use cli::Command;
+
 fn main() {
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(e) => {
+            eprintln!("ndown: {e}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // L'explorateur et les chemins relatifs partent du dossier courant
+    if let Some(workspace) = &args.workspace {
+        if let Err(e) = std::env::set_current_dir(workspace) {
+            eprintln!("ndown: cannot open {}: {e}", workspace.display());
+            std::process::exit(1);
+        }
+    }
+    ndown::app::set_startup_options(args.options);
     ndown::app::app_main()
 }
@@ -1,10 +1,32 @@
 use makepad_widgets::*;
+use crate::editor::model::document::OutlineEntry;
 
 live_design! {
     use link::theme::*;
     use link::widgets::*;
     use crate::theme::*;
 
+    OutlineItem = <View> {
+        width: Fill, height: 26, flow: Overlay
+
+        content = <View> {
+            width: Fill, height: Fill, flow: Right, align: {y: 0.5}, padding: {left: 5, right: 5}
+
+            title = <Label> {
+                text: "",
+                draw_text: { text_style: <THEME_FONT_REGULAR> {font_size: 11}, color: (NORD_SNOW_0) }
+            }
+        }
+
+        btn = <Button> {
+            width: Fill, height: Fill
+            draw_bg: {
+                fn pixel(self) -> vec4 { return vec4(0.,0.,0.,0.); }
+            }
+            text: ""
+        }
+    }
+
     pub OutlinePanel = {{OutlinePanel}}{
         view: <View> {
             width: 250, height: Fill
@@ -29,12 +51,16 @@ live_design! {
                 }
             }
 
-            <Label> {
-                margin: {top: 20}
-                text: "# Heading 1\n## Heading 2\n### Heading 3"
-                draw_text: {
-                    text_style: <THEME_FONT_REGULAR> {font_size: 14}
-                    color: (NORD_SNOW_0)
+            outline_list = <PortalList> {
+                width: Fill, height: Fill
+                flow: Down
+
+                OutlineItem = <OutlineItem> {}
+                // Titre qui contient le curseur
+                OutlineItemCurrent = <OutlineItem> {
+                    show_bg: true
+                    draw_bg: { color: (NORD_POLAR_2) }
+                    content = { title = { draw_text: { color: (NORD_FROST_1) } } }
                 }
             }
         }
@@ -46,16 +72,60 @@ pub struct OutlinePanel {
     #[deref]
     #[live]
     view: View,
+
+    #[rust]
+    entries: Vec<OutlineEntry>,
+    #[rust]
+    current: Option<usize>,
 }
 
 impl LiveHook for OutlinePanel {}
 
+impl OutlinePanel {
+    pub fn set_outline(&mut self, cx: &mut Cx, entries: Vec<OutlineEntry>, current: Option<usize>) {
+        self.entries = entries;
+        self.current = current;
+        self.redraw(cx);
+    }
+
+    // Bloc du titre cliqué
+    pub fn handle_outline_actions(&self, actions: &Actions) -> Option<usize> {
+        let list = self.view.portal_list(ids!(outline_list));
+        for (item_id, item) in list.items_with_actions(actions) {
+            if item.button(ids!(btn)).clicked(actions) {
+                return self.entries.get(item_id).map(|entry| entry.block_idx);
+            }
+        }
+        None
+    }
+}
+
 impl Widget for OutlinePanel {
     fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
         self.view.handle_event(cx, event, scope)
     }
 
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
-        self.view.draw_walk(cx, scope, walk)
+        while let Some(item) = self.view.draw_walk(cx, scope, walk).step() {
+            if let Some(mut list) = item.as_portal_list().borrow_mut() {
+                list.set_item_range(cx, 0, self.entries.len());
+                while let Some(item_id) = list.next_visible_item(cx) {
+                    if let Some(entry) = self.entries.get(item_id) {
+                        let template = if self.current == Some(item_id) {
+                            live_id!(OutlineItemCurrent)
+                        } else {
+                            live_id!(OutlineItem)
+                        };
+                        let item = list.item(cx, item_id, template);
+                        let indent = 5.0 + entry.depth as f64 * 14.0;
+                        let content = item.view(ids!(content));
+                        content.apply_over(cx, live! { padding: { left: (indent) } });
+                        content.label(ids!(title)).set_text(cx, &entry.title);
+                        item.draw_all(cx, scope);
+                    }
+                }
+            }
+        }
+        DrawStep::done()
     }
 }
@@ -0,0 +1,214 @@
+// Recherche approximative dans les chemins des fichiers de l'espace de travail (Ctrl+P)
+use std::fs;
+use std::path::Path;
+
+/// A path containing every character of the query, in order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuzzyMatch {
+    pub path: String,
+    pub score: i64,
+    /// Char indices of `path` matched by the query.
+    pub positions: Vec<usize>,
+}
+
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 24;
+const WORD_START: i64 = 20;
+const IN_FILE_NAME: i64 = 8;
+const GAP: i64 = 1;
+// Bonus du fichier ouvert le plus récemment, réduit pour les suivants
+const RECENT: i64 = 40;
+const RECENT_STEP: i64 = 4;
+
+const NO_MATCH: i64 = i64::MIN / 2;
+
+fn is_word_start(chars: &[char], idx: usize) -> bool {
+    idx == 0
+        || matches!(chars[idx - 1], '/' | '\\' | ' ' | '-' | '_' | '.')
+        || (chars[idx - 1].is_lowercase() && chars[idx].is_uppercase())
+}
+
+/// Best placement of the query characters in `path`, ignoring case and spaces.
+/// Returns the score and the matched char indices.
+pub fn fuzzy_match(query: &str, path: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let chars: Vec<char> = path.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|&c| c.to_lowercase().next().unwrap_or(c))
+        .collect();
+    let (n, m) = (query.len(), chars.len());
+    if n > m {
+        return None;
+    }
+    let name_start = chars
+        .iter()
+        .rposition(|&c| c == '/' || c == '\\')
+        .map_or(0, |i| i + 1);
+    let bonus = |j: usize| {
+        let mut bonus = MATCH;
+        if is_word_start(&chars, j) {
+            bonus += WORD_START;
+        }
+        if j >= name_start {
+            bonus += IN_FILE_NAME;
+        }
+        bonus
+    };
+
+    // score[i][j] : meilleur score avec query[i] placé sur chars[j] ; from[i][j] : place de query[i - 1]
+    let mut score = vec![vec![NO_MATCH; m]; n];
+    let mut from = vec![vec![0; m]; n];
+    for j in 0..m {
+        if lower[j] == query[0] {
+            score[0][j] = bonus(j);
+        }
+    }
+    for i in 1..n {
+        // Meilleur score[i - 1][k] + k * GAP pour k < j - 1 : l'écart se paie à la lettre
+        let mut best: Option<(i64, usize)> = None;
+        for j in i..m {
+            if j >= 2 && score[i - 1][j - 2] > NO_MATCH {
+                let k = j - 2;
+                let candidate = score[i - 1][k] + k as i64 * GAP;
+                if best.is_none_or(|(b, _)| candidate > b) {
+                    best = Some((candidate, k));
+                }
+            }
+            if lower[j] != query[i] {
+                continue;
+            }
+            let mut current = NO_MATCH;
+            if let Some((b, k)) = best {
+                current = b - (j as i64 - 1) * GAP;
+                from[i][j] = k;
+            }
+            if score[i - 1][j - 1] > NO_MATCH && score[i - 1][j - 1] + CONSECUTIVE >= current {
+                current = score[i - 1][j - 1] + CONSECUTIVE;
+                from[i][j] = j - 1;
+            }
+            if current > NO_MATCH {
+                score[i][j] = current + bonus(j);
+            }
+        }
+    }
+
+    let (mut j, best) = score[n - 1]
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|&(_, s)| s > NO_MATCH)
+        .max_by_key(|&(j, s)| (s, std::cmp::Reverse(j)))?;
+    let mut positions = vec![0; n];
+    for i in (0..n).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+    // À score égal, le chemin le plus court d'abord
+    Some((best - m as i64 / 8, positions))
+}
+
+/// Files matching `query`, best first, at most `limit`. `recent` lists the recently
+/// opened files, most recent first; they rank higher, and come first for an empty query.
+pub fn rank(query: &str, files: &[String], recent: &[String], limit: usize) -> Vec<FuzzyMatch> {
+    let mut matches: Vec<FuzzyMatch> = files
+        .iter()
+        .filter_map(|path| {
+            let (score, positions) = fuzzy_match(query, path)?;
+            let recent_bonus = recent
+                .iter()
+                .position(|r| r == path)
+                .map_or(0, |rank| (RECENT - rank as i64 * RECENT_STEP).max(0));
+            Some(FuzzyMatch {
+                path: path.clone(),
+                score: score + recent_bonus,
+                positions,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    matches.truncate(limit);
+    matches
+}
+
+/// Markdown files under `root`, as paths relative to it. Hidden files and folders
+/// (`.trash`, `.ndown`…) are skipped, as in the explorer.
+pub fn list_markdown_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            // Les liens vers des dossiers ne sont pas suivis : pas de boucle possible
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")
+            }) {
+                if let Ok(relative) = path.strip_prefix(root) {
+                    files.push(relative.to_string_lossy().into_owned());
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_files() {
+        let root = std::env::temp_dir().join(format!("ndown-quick-open-{}", std::process::id()));
+        for dir in ["projets/ndown", ".trash", "notes"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "projets/ndown/feuille de route.md",
+            "projets/budget.md",
+            "notes/recettes.md",
+            "notes/image.png",
+            ".trash/ancien.md",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let files = list_markdown_files(&root);
+        assert_eq!(
+            files,
+            [
+                "notes/recettes.md",
+                "projets/budget.md",
+                "projets/ndown/feuille de route.md"
+            ]
+        );
+
+        let ranked = rank("fdr", &files, &[], 10);
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].path, "projets/ndown/feuille de route.md");
+        assert_eq!(ranked[0].positions, [14, 22, 25]);
+
+        // Début du nom de fichier avant les lettres éparses ("pRojEts")
+        let ranked = rank("re", &files, &[], 10);
+        assert_eq!(ranked[0].path, "notes/recettes.md");
+        // Sans requête, les fichiers récents d'abord
+        let recent = ["projets/budget.md".to_string()];
+        assert_eq!(rank("", &files, &recent, 10)[0].path, "projets/budget.md");
+        assert!(rank("xyz", &files, &recent, 10).is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,237 @@
+use makepad_widgets::*;
+use crate::TOKIO_RUNTIME;
+
+pub mod fuzzy;
+use fuzzy::FuzzyMatch;
+
+// Résultats affichés : tous visibles sans défilement
+const MAX_RESULTS: usize = 12;
+// Fichiers récents gardés pour le classement
+const MAX_RECENT: usize = 20;
+
+#[derive(Clone, DefaultNone, Debug)]
+pub enum QuickOpenAction {
+    /// Markdown files of the workspace, listed in the background.
+    AsyncFilesListed(Vec<String>),
+    None,
+}
+
+// Choix fait dans la recherche rapide, lu par l'App
+#[derive(Clone, Debug, PartialEq)]
+pub enum QuickOpenResult {
+    Open(String),
+    Cancel,
+}
+
+live_design! {
+    use link::theme::*;
+    use link::widgets::*;
+    use crate::theme::*;
+
+    ResultItem = <View> {
+        width: Fill, height: 28, flow: Overlay
+
+        content = <View> {
+            width: Fill, height: Fill, align: {y: 0.5}, padding: {left: 8, right: 8}
+
+            path = <Html> {
+                width: Fill, height: Fit
+                font_size: 11
+                font_color: (NORD_SNOW_0)
+                draw_bold: { color: (NORD_FROST_1) }
+            }
+        }
+
+        btn = <Button> {
+            width: Fill, height: Fill
+            draw_bg: {
+                fn pixel(self) -> vec4 { return vec4(0.,0.,0.,0.); }
+            }
+            text: ""
+        }
+    }
+
+    pub QuickOpen = {{QuickOpen}}{
+        width: 560, height: Fit
+        flow: Down, spacing: 8, padding: 16
+        show_bg: true
+        draw_bg: { color: (NORD_POLAR_1) }
+
+        query_input = <TextInput> {
+            width: Fill, height: Fit
+            empty_text: "Rechercher un fichier…"
+            draw_bg: { color: (NORD_POLAR_0) }
+            draw_text: { text_style: <THEME_FONT_REGULAR> {font_size: 12}, color: (NORD_SNOW_2) }
+            draw_cursor: { color: (NORD_SNOW_2) }
+        }
+
+        result_list = <PortalList> {
+            width: Fill, height: 336
+            flow: Down
+
+            ResultItem = <ResultItem> {}
+            // Résultat ouvert par Entrée
+            ResultItemSelected = <ResultItem> {
+                show_bg: true
+                draw_bg: { color: (NORD_POLAR_2) }
+            }
+        }
+    }
+}
+
+#[derive(Live, Widget)]
+pub struct QuickOpen {
+    #[deref]
+    view: View,
+
+    #[rust]
+    files: Vec<String>,
+    // Du plus récent au plus ancien
+    #[rust]
+    recent: Vec<String>,
+    #[rust]
+    results: Vec<FuzzyMatch>,
+    #[rust]
+    selected: usize,
+}
+
+impl LiveHook for QuickOpen {}
+
+// Chemin en HTML, lettres trouvées en gras
+fn highlight_html(result: &FuzzyMatch) -> String {
+    let mut html = String::new();
+    for (idx, c) in result.path.chars().enumerate() {
+        let bold = result.positions.contains(&idx);
+        if bold {
+            html.push_str("<b>");
+        }
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            c => html.push(c),
+        }
+        if bold {
+            html.push_str("</b>");
+        }
+    }
+    html
+}
+
+impl QuickOpen {
+    /// Clears the query and lists the files of the workspace again.
+    pub fn open(&mut self, cx: &mut Cx) {
+        let input = self.view.text_input(ids!(query_input));
+        input.set_text(cx, "");
+        input.set_key_focus(cx);
+        self.update_results(cx);
+
+        TOKIO_RUNTIME.spawn(async move {
+            let root = std::env::current_dir().unwrap_or_default();
+            let files = fuzzy::list_markdown_files(&root);
+            Cx::post_action(QuickOpenAction::AsyncFilesListed(files));
+        });
+    }
+
+    pub fn add_recent(&mut self, path: &str) {
+        self.recent.retain(|recent| recent != path);
+        self.recent.insert(0, path.to_string());
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    fn update_results(&mut self, cx: &mut Cx) {
+        let query = self.view.text_input(ids!(query_input)).text();
+        self.results = fuzzy::rank(&query, &self.files, &self.recent, MAX_RESULTS);
+        self.selected = 0;
+        self.redraw(cx);
+    }
+
+    pub fn handle_quick_open_actions(
+        &mut self,
+        cx: &mut Cx,
+        actions: &Actions,
+    ) -> Option<QuickOpenResult> {
+        let input = self.view.text_input(ids!(query_input));
+        if input.changed(actions).is_some() {
+            self.update_results(cx);
+        }
+        if input.returned(actions).is_some() {
+            let chosen = self.results.get(self.selected)?;
+            return Some(QuickOpenResult::Open(chosen.path.clone()));
+        }
+        if input.escaped(actions) {
+            return Some(QuickOpenResult::Cancel);
+        }
+        let list = self.view.portal_list(ids!(result_list));
+        for (item_id, item) in list.items_with_actions(actions) {
+            if item.button(ids!(btn)).clicked(actions) {
+                let chosen = self.results.get(item_id)?;
+                return Some(QuickOpenResult::Open(chosen.path.clone()));
+            }
+        }
+        None
+    }
+}
+
+impl QuickOpenRef {
+    pub fn open(&self, cx: &mut Cx) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.open(cx);
+        }
+    }
+
+    pub fn add_recent(&self, path: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.add_recent(path);
+        }
+    }
+}
+
+impl Widget for QuickOpen {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        // Haut / Bas : résultat ouvert par Entrée
+        if let Event::KeyDown(ke) = event {
+            let input = self.view.text_input(ids!(query_input));
+            if cx.has_key_focus(input.area()) && !self.results.is_empty() {
+                let last = self.results.len() - 1;
+                match ke.key_code {
+                    KeyCode::ArrowDown => self.selected = (self.selected + 1).min(last),
+                    KeyCode::ArrowUp => self.selected = self.selected.saturating_sub(1),
+                    _ => {}
+                }
+                self.redraw(cx);
+            }
+        }
+        self.view.handle_event(cx, event, scope);
+
+        if let Event::Actions(actions) = event {
+            for action in actions {
+                if let QuickOpenAction::AsyncFilesListed(files) = action.cast() {
+                    self.files = files;
+                    self.update_results(cx);
+                }
+            }
+        }
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        while let Some(item) = self.view.draw_walk(cx, scope, walk).step() {
+            if let Some(mut list) = item.as_portal_list().borrow_mut() {
+                list.set_item_range(cx, 0, self.results.len());
+                while let Some(item_id) = list.next_visible_item(cx) {
+                    if let Some(result) = self.results.get(item_id) {
+                        let template = if item_id == self.selected {
+                            live_id!(ResultItemSelected)
+                        } else {
+                            live_id!(ResultItem)
+                        };
+                        let item = list.item(cx, item_id, template);
+                        item.html(ids!(path)).set_text(cx, &highlight_html(result));
+                        item.draw_all(cx, scope);
+                    }
+                }
+            }
+        }
+        DrawStep::done()
+    }
+}
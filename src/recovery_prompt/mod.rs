@@ -0,0 +1,92 @@
+use makepad_widgets::*;
+
+// Réponse à "Restaurer les modifications non enregistrées ?", lue par l'App
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecoveryPromptResult {
+    Restore,
+    Discard,
+}
+
+live_design! {
+    use link::theme::*;
+    use link::widgets::*;
+    use crate::theme::*;
+
+    pub RecoveryPrompt = {{RecoveryPrompt}}{
+        width: 420, height: Fit
+        flow: Down, spacing: 8, padding: 20
+        show_bg: true
+        draw_bg: { color: (NORD_POLAR_1) }
+
+        title = <Label> {
+            text: "RÉCUPÉRATION"
+            draw_text: { text_style: <THEME_FONT_BOLD> {font_size: 12}, color: (NORD_FROST_2) }
+        }
+
+        message = <Label> {
+            width: Fill
+            text: ""
+            draw_text: {
+                text_style: <THEME_FONT_REGULAR> {font_size: 11}, color: (NORD_SNOW_0)
+                wrap: Word
+            }
+        }
+
+        buttons = <View> {
+            width: Fill, height: Fit
+            flow: Right, spacing: 10, margin: {top: 10}
+
+            discard_btn = <Button> { text: "Supprimer la copie", draw_text: { color: (NORD_AURORA_RED) } }
+            <View> { width: Fill }
+            restore_btn = <Button> { text: "Restaurer", draw_text: { color: (NORD_FROST_1) } }
+        }
+    }
+}
+
+#[derive(Live, Widget)]
+pub struct RecoveryPrompt {
+    #[deref]
+    view: View,
+}
+
+impl LiveHook for RecoveryPrompt {}
+
+impl RecoveryPrompt {
+    pub fn set_file(&mut self, cx: &mut Cx, name: &str) {
+        self.view.label(ids!(message)).set_text(
+            cx,
+            &format!(
+                "« {name} » a des modifications plus récentes que le fichier, \
+                 gardées après une fermeture imprévue. Les restaurer ?"
+            ),
+        );
+    }
+
+    pub fn handle_prompt_actions(&self, actions: &Actions) -> Option<RecoveryPromptResult> {
+        if self.view.button(ids!(restore_btn)).clicked(actions) {
+            return Some(RecoveryPromptResult::Restore);
+        }
+        if self.view.button(ids!(discard_btn)).clicked(actions) {
+            return Some(RecoveryPromptResult::Discard);
+        }
+        None
+    }
+}
+
+impl RecoveryPromptRef {
+    pub fn set_file(&self, cx: &mut Cx, name: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_file(cx, name);
+        }
+    }
+}
+
+impl Widget for RecoveryPrompt {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope)
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
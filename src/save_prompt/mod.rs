@@ -0,0 +1,94 @@
+use makepad_widgets::*;
+
+// Réponse à "Enregistrer les modifications ?", lue par l'App
+#[derive(Clone, Debug, PartialEq)]
+pub enum SavePromptResult {
+    Save,
+    Discard,
+    Cancel,
+}
+
+live_design! {
+    use link::theme::*;
+    use link::widgets::*;
+    use crate::theme::*;
+
+    pub SavePrompt = {{SavePrompt}}{
+        width: 420, height: Fit
+        flow: Down, spacing: 8, padding: 20
+        show_bg: true
+        draw_bg: { color: (NORD_POLAR_1) }
+
+        title = <Label> {
+            text: "MODIFICATIONS NON ENREGISTRÉES"
+            draw_text: { text_style: <THEME_FONT_BOLD> {font_size: 12}, color: (NORD_FROST_2) }
+        }
+
+        message = <Label> {
+            width: Fill
+            text: ""
+            draw_text: {
+                text_style: <THEME_FONT_REGULAR> {font_size: 11}, color: (NORD_SNOW_0)
+                wrap: Word
+            }
+        }
+
+        buttons = <View> {
+            width: Fill, height: Fit
+            flow: Right, spacing: 10, margin: {top: 10}
+
+            discard_btn = <Button> { text: "Ne pas enregistrer", draw_text: { color: (NORD_AURORA_RED) } }
+            <View> { width: Fill }
+            cancel_btn = <Button> { text: "Annuler", draw_text: { color: (NORD_SNOW_2) } }
+            save_btn = <Button> { text: "Enregistrer", draw_text: { color: (NORD_FROST_1) } }
+        }
+    }
+}
+
+#[derive(Live, Widget)]
+pub struct SavePrompt {
+    #[deref]
+    view: View,
+}
+
+impl LiveHook for SavePrompt {}
+
+impl SavePrompt {
+    pub fn set_file(&mut self, cx: &mut Cx, name: &str) {
+        self.view.label(ids!(message)).set_text(
+            cx,
+            &format!("Enregistrer les modifications de « {name} » avant de continuer ?"),
+        );
+    }
+
+    pub fn handle_prompt_actions(&self, actions: &Actions) -> Option<SavePromptResult> {
+        if self.view.button(ids!(save_btn)).clicked(actions) {
+            return Some(SavePromptResult::Save);
+        }
+        if self.view.button(ids!(discard_btn)).clicked(actions) {
+            return Some(SavePromptResult::Discard);
+        }
+        if self.view.button(ids!(cancel_btn)).clicked(actions) {
+            return Some(SavePromptResult::Cancel);
+        }
+        None
+    }
+}
+
+impl SavePromptRef {
+    pub fn set_file(&self, cx: &mut Cx, name: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_file(cx, name);
+        }
+    }
+}
+
+impl Widget for SavePrompt {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope)
+    }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
+}
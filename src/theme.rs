@@ -63,6 +63,13 @@ live_design! {
         }
     }
 
+    // Pas de fonte grasse italique dans assets/fonts : l'éditeur épaissit l'italique au dessin
+    pub THEME_FONT_BOLD_ITALIC = {
+        font_family: {
+            latin = font("crate://self/assets/fonts/UbuntuNerdFont-Italic.ttf", 0.0, 0.0),
+        }
+    }
+
     pub THEME_FONT_CODE = {
         font_family: {
             latin = font("crate://self/assets/fonts/UbuntuSansMonoNerdFont-Regular.ttf", 0.0, 0.0),
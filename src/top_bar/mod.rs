@@ -1,44 +1,117 @@
 use makepad_widgets::*;
+use crate::editor::tabs::TabInfo;
+
+// Onglet cliqué dans la barre, lu par l'App
+#[derive(Clone, Debug, PartialEq)]
+pub enum TabRequest {
+    Select(usize),
+    Close(usize),
+}
+
+#[derive(Clone, DefaultNone, Debug)]
+pub enum TabItemAction {
+    Clicked,
+    /// Clic du milieu
+    CloseClicked,
+    None,
+}
 
 live_design! {
     use link::theme::*;
     use link::widgets::*;
     use crate::theme::*;
 
+    TabItem = {{TabItem}} {
+        width: Fit, height: Fill
+        padding: {left: 12, right: 12}
+        align: {y: 0.5}
+        show_bg: true
+        draw_bg: { color: (NORD_POLAR_0) }
+        cursor: Hand
+
+        title = <Label> {
+            text: ""
+            draw_text: { text_style: <THEME_FONT_REGULAR> {font_size: 10}, color: (NORD_SNOW_0) }
+        }
+    }
+
     pub TopBar = {{TopBar}}{
         view: <View> {
             width: Fill, height: Fit
-            flow: Right, spacing: 0, padding: {left: 10, right: 10, top: 10, bottom: 10}
+            flow: Down
             show_bg: true
             draw_bg: { color: (NORD_POLAR_0) }
 
-            left_toggle = <Button> {
-                text: "☰"
-                width: 30, height: 30
-                draw_text: { color: (NORD_SNOW_2) }
-                visible: false // Hidden by default, shown when sidebar is closed
+            header = <View> {
+                width: Fill, height: Fit
+                flow: Right, spacing: 0, padding: {left: 10, right: 10, top: 10, bottom: 10}
+
+                left_toggle = <Button> {
+                    text: "☰"
+                    width: 30, height: 30
+                    draw_text: { color: (NORD_SNOW_2) }
+                    visible: false // Hidden by default, shown when sidebar is closed
+                }
+
+                <View> { width: Fill }
+
+                title = <Label> {
+                    text: "Ndown Editor"
+                    draw_text: {
+                        text_style: <THEME_FONT_BOLD> {font_size: 14}
+                        color: (NORD_SNOW_2)
+                    }
+                }
+
+                <View> { width: Fill }
+
+                right_toggle = <Button> {
+                    text: "☰"
+                    width: 30, height: 30
+                    draw_text: { color: (NORD_SNOW_2) }
+                    visible: false // Hidden by default, shown when sidebar is closed
+                }
             }
 
-            <View> { width: Fill }
+            tab_list = <PortalList> {
+                width: Fill, height: 30
+                flow: Right
 
-            title = <Label> {
-                text: "Ndown Editor"
-                draw_text: {
-                    text_style: <THEME_FONT_BOLD> {font_size: 14}
-                    color: (NORD_SNOW_2)
+                TabItem = <TabItem> {}
+                // Onglet affiché
+                TabItemActive = <TabItem> {
+                    draw_bg: { color: (NORD_POLAR_1) }
+                    title = { draw_text: { color: (NORD_FROST_1) } }
                 }
             }
+        }
+    }
+}
 
-            <View> { width: Fill }
+#[derive(Live, LiveHook, Widget)]
+pub struct TabItem {
+    #[deref]
+    view: View,
+}
 
-            right_toggle = <Button> {
-                text: "☰"
-                width: 30, height: 30
-                draw_text: { color: (NORD_SNOW_2) }
-                visible: false // Hidden by default, shown when sidebar is closed
+impl Widget for TabItem {
+    fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
+        self.view.handle_event(cx, event, scope);
+        if let Hit::FingerUp(fe) = event.hits(cx, self.view.area()) {
+            if fe.is_over {
+                let action = if fe.mouse_button() == Some(MouseButton::MIDDLE) {
+                    TabItemAction::CloseClicked
+                } else {
+                    TabItemAction::Clicked
+                };
+                cx.widget_action(self.widget_uid(), &scope.path, action);
             }
         }
     }
+
+    fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
+        self.view.draw_walk(cx, scope, walk)
+    }
 }
 
 #[derive(Live, Widget)]
@@ -46,16 +119,107 @@ pub struct TopBar {
     #[deref]
     #[live]
     view: View,
+
+    #[rust]
+    title: String,
+    #[rust]
+    modified: bool,
+    #[rust]
+    tabs: Vec<TabInfo>,
+    #[rust]
+    active_tab: usize,
 }
 
 impl LiveHook for TopBar {}
 
+impl TopBar {
+    pub fn set_title(&mut self, cx: &mut Cx, title: &str) {
+        self.title = title.to_string();
+        self.update_title(cx);
+    }
+
+    pub fn set_modified(&mut self, cx: &mut Cx, modified: bool) {
+        self.modified = modified;
+        self.update_title(cx);
+    }
+
+    pub fn set_tabs(&mut self, cx: &mut Cx, tabs: Vec<TabInfo>, active: usize) {
+        self.tabs = tabs;
+        self.active_tab = active;
+        self.redraw(cx);
+    }
+
+    pub fn handle_tab_actions(&self, actions: &Actions) -> Option<TabRequest> {
+        let list = self.view.portal_list(ids!(tab_list));
+        for (item_id, item) in list.items_with_actions(actions) {
+            match actions.find_widget_action(item.widget_uid()).cast() {
+                TabItemAction::Clicked => return Some(TabRequest::Select(item_id)),
+                TabItemAction::CloseClicked => return Some(TabRequest::Close(item_id)),
+                TabItemAction::None => {}
+            }
+        }
+        None
+    }
+
+    // "•" devant le titre tant que le document n'est pas enregistré
+    fn update_title(&mut self, cx: &mut Cx) {
+        let text = if self.modified {
+            format!("• {}", self.title)
+        } else {
+            self.title.clone()
+        };
+        self.view.label(ids!(title)).set_text(cx, &text);
+    }
+}
+
+impl TopBarRef {
+    pub fn set_title(&self, cx: &mut Cx, title: &str) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_title(cx, title);
+        }
+    }
+
+    pub fn set_modified(&self, cx: &mut Cx, modified: bool) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_modified(cx, modified);
+        }
+    }
+
+    pub fn set_tabs(&self, cx: &mut Cx, tabs: Vec<TabInfo>, active: usize) {
+        if let Some(mut inner) = self.borrow_mut() {
+            inner.set_tabs(cx, tabs, active);
+        }
+    }
+}
+
 impl Widget for TopBar {
     fn handle_event(&mut self, cx: &mut Cx, event: &Event, scope: &mut Scope) {
         self.view.handle_event(cx, event, scope)
     }
 
     fn draw_walk(&mut self, cx: &mut Cx2d, scope: &mut Scope, walk: Walk) -> DrawStep {
-        self.view.draw_walk(cx, scope, walk)
+        while let Some(item) = self.view.draw_walk(cx, scope, walk).step() {
+            if let Some(mut list) = item.as_portal_list().borrow_mut() {
+                list.set_item_range(cx, 0, self.tabs.len());
+                while let Some(item_id) = list.next_visible_item(cx) {
+                    if let Some(tab) = self.tabs.get(item_id) {
+                        let template = if item_id == self.active_tab {
+                            live_id!(TabItemActive)
+                        } else {
+                            live_id!(TabItem)
+                        };
+                        let item = list.item(cx, item_id, template);
+                        let title = if tab.modified {
+                            format!("• {}", tab.title)
+                        } else {
+                            tab.title.clone()
+                        };
+                        item.label(ids!(title)).set_text(cx, &title);
+                        item.draw_all(cx, scope);
+                    }
+                }
+            }
+        }
+        DrawStep::done()
     }
 }